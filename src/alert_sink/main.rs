@@ -0,0 +1,251 @@
+/*!
+ * Receive the reports the daemon posts (the fatal_state_report_address of a program and
+ * the event_webhook of the server) and print them. Given expectations it exits once they
+ * were all met, or with a failure at the timeout, to check the notifications in tests
+ */
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use regex::Regex;
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use tcl::time::now_rfc3339;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constants                                 */
+/* -------------------------------------------------------------------------- */
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:8080";
+
+/// the reports are small, a bigger body is not one
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+const RESPONSE: &str = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+const USAGE: &str = "Usage: taskmaster-alert-sink [OPTIONS]
+
+Print the reports posted by the daemon to the fatal_state_report_address of a program
+or to the event_webhook of the server.
+
+Options:
+    -l, --listen ADDRESS    Address to listen on (default: 127.0.0.1:8080)
+    -e, --expect REGEX      Expect a report matching REGEX, can be repeated. the sink
+                            exits successfully once every expectation was met
+    -n, --count N           Exit after receiving N reports
+    -t, --timeout SECS      Exit after SECS seconds, failing if an expectation wasn't met
+    -h, --help              Show this help message";
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// represent the options given on the command line
+#[derive(Debug)]
+struct Arguments {
+    listen: String,
+    expectations: Vec<Regex>,
+    count: Option<usize>,
+    timeout: Option<Duration>,
+    help: bool,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Main                                    */
+/* -------------------------------------------------------------------------- */
+fn main() {
+    let arguments = match Arguments::parse() {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if arguments.help {
+        println!("{USAGE}");
+        return;
+    }
+
+    let listener = match TcpListener::bind(&arguments.listen) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Can't listen on {}: {error}", arguments.listen);
+            std::process::exit(1);
+        }
+    };
+    println!("Listening for reports on http://{}", arguments.listen);
+
+    let (sender, reports) = mpsc::channel();
+    thread::spawn(move || accept_reports(listener, sender));
+
+    let deadline = arguments.timeout.map(|timeout| Instant::now() + timeout);
+    let mut unmet = arguments.expectations;
+    let mut received = 0;
+    loop {
+        let report = match deadline {
+            Some(deadline) => {
+                reports.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => reports
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        let Ok(report) = report else {
+            break;
+        };
+        println!("[{}] {report}", now_rfc3339());
+        received += 1;
+
+        let expected = !unmet.is_empty();
+        unmet.retain(|expectation| !expectation.is_match(&report));
+        if (expected && unmet.is_empty()) || arguments.count == Some(received) {
+            break;
+        }
+    }
+
+    if !unmet.is_empty() {
+        for expectation in &unmet {
+            eprintln!("No report matched `{expectation}`");
+        }
+        std::process::exit(1);
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Arguments {
+    fn parse() -> Result<Self, String> {
+        let mut arguments = Arguments {
+            listen: DEFAULT_LISTEN_ADDRESS.to_owned(),
+            expectations: Vec::new(),
+            count: None,
+            timeout: None,
+            help: false,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("`{arg}` expect a value"));
+            match arg.as_str() {
+                "--listen" | "-l" => arguments.listen = value()?,
+                "--expect" | "-e" => {
+                    let pattern = value()?;
+                    let expectation = Regex::new(&pattern)
+                        .map_err(|error| format!("`{pattern}` is not a valid regex: {error}"))?;
+                    arguments.expectations.push(expectation);
+                }
+                "--count" | "-n" => {
+                    let count = value()?;
+                    arguments.count = Some(
+                        count
+                            .parse()
+                            .map_err(|_| format!("`{count}` is not a number of reports"))?,
+                    );
+                }
+                "--timeout" | "-t" => {
+                    let seconds = value()?;
+                    let seconds = seconds
+                        .parse()
+                        .map_err(|_| format!("`{seconds}` is not a number of seconds"))?;
+                    arguments.timeout = Some(Duration::from_secs(seconds));
+                }
+                "--help" | "-h" => arguments.help = true,
+                _ => return Err(format!("unknown argument: `{arg}`")),
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// read the reports of the connections one after the other, for ever
+fn accept_reports(listener: TcpListener, reports: mpsc::Sender<String>) {
+    for stream in listener.incoming() {
+        let report = stream.and_then(read_report);
+        match report {
+            Ok(report) => {
+                if reports.send(report).is_err() {
+                    return;
+                }
+            }
+            Err(error) => eprintln!("Ignoring a connection: {error}"),
+        }
+    }
+}
+
+/// read the POST request of the daemon and acknowledge it, returning the message it holds
+fn read_report(stream: TcpStream) -> io::Result<String> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+
+    let mut content_length = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the request ended before its body",
+            ));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "invalid content length")
+                })?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the body is too big for a report",
+        ));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    (&stream).write_all(RESPONSE.as_bytes())?;
+
+    let body = String::from_utf8_lossy(&body);
+    Ok(message_of(&body).unwrap_or_else(|| body.into_owned()))
+}
+
+/// extract the string of the `message` key of a `{"message":"..."}` body
+fn message_of(body: &str) -> Option<String> {
+    let rest = body.trim().strip_prefix('{')?.trim_start();
+    let rest = rest.strip_prefix("\"message\"")?.trim_start();
+    let rest = rest.strip_prefix(':')?.trim_start();
+    let mut characters = rest.strip_prefix('"')?.chars();
+
+    let mut message = String::new();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' => return Some(message),
+            '\\' => match characters.next()? {
+                'n' => message.push('\n'),
+                't' => message.push('\t'),
+                'r' => message.push('\r'),
+                'u' => {
+                    let code: String = characters.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&code, 16).ok()?;
+                    message.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                escaped => message.push(escaped),
+            },
+            character => message.push(character),
+        }
+    }
+    None
+}
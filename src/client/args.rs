@@ -0,0 +1,222 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use std::{net::Ipv6Addr, path::PathBuf, time::Duration};
+use tcl::{
+    args::{unknown_argument, Arg, ArgParser},
+    error::TaskmasterError,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Module                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constants                                 */
+/* -------------------------------------------------------------------------- */
+/// environment variable used to select the daemon when no flag is given
+const SERVER_ENV: &str = "TASKMASTER_SERVER";
+
+/// how long the client try to reach the server by default
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// how long the client wait for a response by default
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const USAGE: &str = "Usage: client [OPTIONS] [COMMAND [PROGRAM]]
+
+Without a command an interactive shell is started, otherwise the command is
+sent to the server and the client exits with a non-zero status on failure.
+
+Options:
+    -s, --server HOST[:PORT]    Address of the daemon (default: TASKMASTER_SERVER or 127.0.0.1:8042)
+    -p, --port PORT             Port of the daemon
+    -c, --config FILE           The client config (default: TASKMASTER_CLIENT_CONFIG or ~/.taskmasterctl.yaml)
+    -e, --command COMMAND       The command to send, like the words after the options
+    --json                      Print the responses of the command as JSON, one per line
+    --connect-timeout SECS      Give up connecting after SECS seconds, 0 retry forever (default: 10)
+    --request-timeout SECS      Stop waiting for a response after SECS seconds (default: 30)
+    --log-session FILE          Append every command and response to FILE
+    -h, --help                  Show this help message
+    -V, --version               Show the client version";
+
+/* -------------------------------------------------------------------------- */
+/*                             Struct Declaration                             */
+/* -------------------------------------------------------------------------- */
+/// represent the options given to the client on the command line
+#[derive(Debug)]
+pub struct Arguments {
+    /// the host (name or ip) of the daemon to connect to
+    pub host: String,
+
+    /// the port the daemon is listening on
+    pub port: u16,
+
+    /// the client config file, the default one if None
+    pub config: Option<PathBuf>,
+
+    /// print the responses as json instead of text
+    pub json: bool,
+
+    /// a file where the whole session is transcribed
+    pub log_session: Option<PathBuf>,
+
+    /// how long to retry connecting to the server, `None` meaning forever
+    pub connect_timeout: Option<Duration>,
+
+    /// how long to wait for the response of a request
+    pub request_timeout: Duration,
+
+    /// a command to execute instead of starting the interactive shell
+    pub command: Option<String>,
+
+    /// display the usage without doing anything else
+    pub help: bool,
+
+    /// display the version without doing anything else
+    pub version: bool,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Arguments {
+    /// parse the process argument, the priority being:
+    /// `--server`/`--port` flags > `TASKMASTER_SERVER` > compiled-in address
+    pub fn parse() -> Result<Self, TaskmasterError> {
+        let mut arguments = Self::default();
+
+        if let Ok(server) = std::env::var(SERVER_ENV) {
+            arguments.set_server(&server)?;
+        }
+
+        let mut args = ArgParser::from_env();
+        while let Some(arg) = args.next_arg()? {
+            let option = match arg {
+                Arg::Option(option) => option,
+                Arg::Positional(arg) => {
+                    // everything from the first positional argument is the command
+                    let command: Vec<String> = std::iter::once(arg).chain(args.rest()).collect();
+                    arguments.command = Some(command.join(" "));
+                    break;
+                }
+            };
+            match option.as_str() {
+                "--server" | "-s" => arguments.set_server(&args.value(&option)?)?,
+                "--port" | "-p" => arguments.port = args.parse_value(&option, "a valid port")?,
+                "--config" | "-c" => arguments.config = Some(PathBuf::from(args.value(&option)?)),
+                "--command" | "-e" => arguments.command = Some(args.value(&option)?),
+                "--json" => arguments.json = true,
+                "--log-session" => {
+                    arguments.log_session = Some(PathBuf::from(args.value(&option)?));
+                }
+                "--connect-timeout" => {
+                    let seconds = args.parse_value(&option, "a number of seconds")?;
+                    arguments.connect_timeout =
+                        (seconds != 0).then_some(Duration::from_secs(seconds));
+                }
+                "--request-timeout" => {
+                    let seconds = args.parse_value(&option, "a number of seconds")?;
+                    arguments.request_timeout = Duration::from_secs(seconds);
+                }
+                "--help" | "-h" => arguments.help = true,
+                "--version" | "-V" => arguments.version = true,
+                _ => return Err(unknown_argument(&option)),
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    /// return the text displayed by `--help`
+    pub fn usage() -> &'static str {
+        USAGE
+    }
+
+    /// return the address in a form usable by TcpStream::connect
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// return a short description of the daemon for display purpose,
+    /// the port being omitted when it is the default one
+    pub fn target(&self) -> String {
+        if self.port == tcl::PORT {
+            self.host.to_owned()
+        } else {
+            self.address()
+        }
+    }
+
+    /// set the host and optionally the port from a `HOST[:PORT]` string,
+    /// an IPv6 host being written in brackets like `[::1]:8042`
+    fn set_server(&mut self, server: &str) -> Result<(), TaskmasterError> {
+        let (host, port) = match server.strip_prefix('[') {
+            Some(bracketed) => {
+                let Some((ip, rest)) = bracketed.split_once(']') else {
+                    return Err(TaskmasterError::Custom(format!(
+                        "`{server}` is missing the closing `]`"
+                    )));
+                };
+                if ip.parse::<Ipv6Addr>().is_err() {
+                    return Err(TaskmasterError::Custom(format!(
+                        "`{ip}` is not an IPv6 address"
+                    )));
+                }
+                let port = match rest {
+                    "" => None,
+                    _ => Some(rest.strip_prefix(':').ok_or_else(|| {
+                        TaskmasterError::Custom(format!("`{server}` has garbage after the `]`"))
+                    })?),
+                };
+                (&server[..ip.len() + 2], port)
+            }
+            None => match server.rsplit_once(':') {
+                Some((host, _)) if host.contains(':') => {
+                    return Err(TaskmasterError::Custom(format!(
+                        "`{server}` looks like an IPv6 address, write it in brackets: `[{server}]` or `[IP]:PORT`"
+                    )));
+                }
+                Some((host, port)) => (host, Some(port)),
+                None => (server, None),
+            },
+        };
+        if host.is_empty() {
+            return Err(TaskmasterError::Custom(format!(
+                "`{server}` does not contain a host"
+            )));
+        }
+        if let Some(port) = port {
+            self.port = parse_port(port)?;
+        }
+        self.host = host.to_owned();
+        Ok(())
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, TaskmasterError> {
+    port.parse::<u16>()
+        .map_err(|_| TaskmasterError::Custom(format!("`{port}` is not a valid port")))
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl Default for Arguments {
+    fn default() -> Self {
+        Self {
+            host: tcl::ADDRESS.to_string(),
+            port: tcl::PORT,
+            config: None,
+            json: false,
+            log_session: None,
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            command: None,
+            help: false,
+            version: false,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::Arguments;
+use std::net::SocketAddr;
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[test]
+fn server_is_a_host_and_an_optional_port() {
+    let mut arguments = Arguments::default();
+
+    arguments.set_server("example.org:4242").unwrap();
+    assert_eq!(
+        (arguments.host.as_str(), arguments.port),
+        ("example.org", 4242)
+    );
+    arguments.set_server("127.0.0.2").unwrap();
+    assert_eq!(
+        (arguments.host.as_str(), arguments.port),
+        ("127.0.0.2", 4242)
+    );
+    assert!(arguments.set_server(":4242").is_err());
+    assert!(arguments.set_server("example.org:http").is_err());
+}
+
+#[test]
+fn bracketed_ipv6_server_is_accepted() {
+    let mut arguments = Arguments::default();
+
+    arguments.set_server("[::1]:4242").unwrap();
+    assert_eq!((arguments.host.as_str(), arguments.port), ("[::1]", 4242));
+    assert_eq!(
+        arguments.address().parse::<SocketAddr>().unwrap(),
+        "[::1]:4242".parse().unwrap()
+    );
+
+    arguments.set_server("[fe80::2]").unwrap();
+    assert_eq!(
+        (arguments.host.as_str(), arguments.port),
+        ("[fe80::2]", 4242)
+    );
+}
+
+#[test]
+fn unbracketed_or_malformed_ipv6_server_is_rejected() {
+    let mut arguments = Arguments::default();
+
+    let error = arguments.set_server("::1").unwrap_err().to_string();
+    assert!(error.contains("brackets"), "{error}");
+    for server in ["[::1", "[::1]4242", "[localhost]:4242", "[]:4242"] {
+        assert!(arguments.set_server(server).is_err(), "{server}");
+    }
+    // a rejected server leaves the previous one
+    assert_eq!(arguments.address(), tcl::SOCKET_ADDRESS.to_string());
+}
@@ -1,51 +1,108 @@
 /* -------------------------------------------------------------------------- */
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
-use crate::history::History;
-use libc::{tcgetattr, tcsetattr, termios, ECHO, ICANON, TCSANOW};
+use crate::{config::DetachKey, history::History};
 use std::io::{self, Read, Write};
 use std::os::unix::io::AsRawFd;
-use tcl::error::TaskmasterError;
+use std::sync::{mpsc, Arc, Mutex};
+use tcl::{error::TaskmasterError, mylibc};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
 /* -------------------------------------------------------------------------- */
 /*                                  Constants                                 */
 /* -------------------------------------------------------------------------- */
 const ESCAPE_KEY: u8 = 0x1B;
 const BACKSPACE: u8 = 0x7F;
-const CLEAR_LINE: &str = "\x1B[2K";
+const CTRL_C: u8 = 0x03;
+const CLEAR_TO_END: &str = "\x1B[0J";
 const CLEAR_CHAR: &str = "\x1B[1D \x1B[1D";
 const RESET_CURSOR: &str = "\x1B[0G";
 const ARROW_UP: [u8; 3] = [ESCAPE_KEY, b'[', b'A'];
 const ARROW_DOWN: [u8; 3] = [ESCAPE_KEY, b'[', b'B'];
-const PROMPT: &str = "> ";
 
 /* -------------------------------------------------------------------------- */
 /*                             Struct Declaration                             */
 /* -------------------------------------------------------------------------- */
-#[derive(Default)]
 pub struct Cli {
     line: String,
     history: History,
+    /// the daemon the shell is controlling, displayed in the prompt
+    target: String,
+    /// the program whose output is displayed, `None` when not attached
+    attached: Arc<Mutex<Option<String>>>,
+    /// the line currently displayed after the prompt, `None` when no prompt is displayed
+    displayed_line: Arc<Mutex<Option<String>>>,
+    /// the settings of the terminal while it is in raw mode, to restore them
+    saved_termios: SavedTermios,
+    /// the key detaching the shell from the program it is attached to
+    detach_key: DetachKey,
+}
+
+/// the settings of the terminal before it was put in raw mode, `None` when it isn't
+type SavedTermios = Arc<Mutex<Option<mylibc::termios>>>;
+
+/// the terminal is in raw mode while this lives, its settings are restored when it is
+/// dropped, or before by another thread with restore_terminal
+struct RawMode {
+    saved_termios: SavedTermios,
+}
+
+/// what the user typed at the prompt
+pub enum Input {
+    Line(String),
+    /// the detach key was pressed while attached to a program
+    Detach,
+}
+
+/// read the user input on a dedicated thread so the async side of the client
+/// can keep processing the messages of the server while waiting for a line
+pub struct InputReader {
+    lines: UnboundedReceiver<Result<Input, TaskmasterError>>,
+    ready: mpsc::Sender<()>,
+    target: String,
+    attached: Arc<Mutex<Option<String>>>,
+    displayed_line: Arc<Mutex<Option<String>>>,
+    saved_termios: SavedTermios,
 }
 
 /* -------------------------------------------------------------------------- */
 /*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
 impl Cli {
-    pub fn new() -> Self {
-        Self::default()
+    /// create a new shell whose prompt display the daemon it is controlling
+    pub fn new(target: &str, detach_key: DetachKey) -> Self {
+        Self {
+            line: String::new(),
+            history: History::default(),
+            target: target.to_owned(),
+            attached: Arc::new(Mutex::new(None)),
+            displayed_line: Arc::new(Mutex::new(None)),
+            saved_termios: Arc::new(Mutex::new(None)),
+            detach_key,
+        }
     }
 
-    pub fn read_line(&mut self) -> Result<String, TaskmasterError> {
-        let origin_termios = Self::enable_raw_mode();
-        Self::display_prompt()?;
+    /// read a line, while attached to a program the detach key detach from it. Ctrl+C
+    /// clears the line
+    pub fn read_line(&mut self) -> Result<Input, TaskmasterError> {
+        let _raw_mode = RawMode::enable(&self.saved_termios);
+        self.display_prompt()?;
         self.history.push(String::new());
         let _ = self.history.restore();
         let mut input = Self::getch()?;
         while !(input.len() == 1 && input[0] == b'\n') {
+            if input == [self.detach_key.byte()] && self.attached.lock().unwrap().is_some() {
+                *self.displayed_line.lock().unwrap() = None;
+                println!("{}", self.detach_key);
+                let _ = self.history.pop();
+                self.line.clear();
+                return Ok(Input::Detach);
+            }
             self.handle_input(input)?;
+            *self.displayed_line.lock().unwrap() = Some(self.line.clone());
             input = Self::getch()?;
         }
+        *self.displayed_line.lock().unwrap() = None;
         println!();
         if !self.line.is_empty() {
             let _ = self.history.set_last_line(self.line.clone());
@@ -54,34 +111,7 @@ impl Cli {
         }
         let return_line = self.line.clone();
         self.line.clear();
-        Self::disable_raw_mode(origin_termios);
-        Ok(return_line)
-    }
-
-    /// Enable raw mode to read single keypresses without waiting for Enter
-    fn enable_raw_mode() -> termios {
-        let fd = io::stdin().as_raw_fd();
-        let mut termios = unsafe {
-            let mut termios = std::mem::zeroed();
-            tcgetattr(fd, &mut termios);
-            termios
-        };
-
-        let orig_termios = termios;
-        // Disable canonical mode and echo
-        termios.c_lflag &= !(ICANON | ECHO);
-        // Apply changes immediately
-        unsafe { tcsetattr(fd, TCSANOW, &termios) };
-
-        orig_termios
-    }
-
-    /// Restore the terminal to its original settings
-    fn disable_raw_mode(orig_termios: termios) {
-        let fd = io::stdin().as_raw_fd();
-        unsafe {
-            tcsetattr(fd, TCSANOW, &orig_termios);
-        }
+        Ok(Input::Line(return_line))
     }
 
     /// Function to read a single keypress, including escape sequences
@@ -114,6 +144,11 @@ impl Cli {
         } else if ch == BACKSPACE && !self.line.is_empty() {
             self.line.pop();
             print!("{CLEAR_CHAR}");
+        } else if ch == CTRL_C {
+            // abandon the current line and start over on a fresh prompt
+            self.line.clear();
+            println!("^C");
+            self.display_prompt()?;
         }
         if self.history.is_last_line() {
             let _ = self.history.set_last_line(self.line.clone());
@@ -142,17 +177,155 @@ impl Cli {
     }
 
     fn refresh_prompt(&self) -> Result<(), TaskmasterError> {
-        print!("{}", CLEAR_LINE);
-        print!("{}", RESET_CURSOR);
-        print!("{}", PROMPT);
+        let prompt = prompt(&self.target, &self.attached);
+        if let Some(displayed_line) = self.displayed_line.lock().unwrap().as_ref() {
+            clear_prompt(&prompt, displayed_line);
+        }
+        print!("{}", prompt);
         print!("{}", self.line);
         io::stdout().flush()?;
         Ok(())
     }
 
-    fn display_prompt() -> Result<(), TaskmasterError> {
-        print!("{}", PROMPT);
+    fn display_prompt(&self) -> Result<(), TaskmasterError> {
+        *self.displayed_line.lock().unwrap() = Some(self.line.clone());
+        print!("{}", prompt(&self.target, &self.attached));
         io::stdout().flush()?;
         Ok(())
     }
 }
+
+impl RawMode {
+    /// Enable raw mode to read single keypresses without waiting for Enter
+    fn enable(saved_termios: &SavedTermios) -> Self {
+        let fd = io::stdin().as_raw_fd();
+        // stdin may not be a terminal, it is then read as is
+        let mut termios = mylibc::tcgetattr(fd).unwrap_or_default();
+
+        *saved_termios.lock().unwrap() = Some(termios);
+        // Disable canonical mode, echo and signal generation (Ctrl+C is handled by the cli)
+        termios.c_lflag &= !(mylibc::ICANON | mylibc::ECHO | mylibc::ISIG);
+        // Apply changes immediately
+        let _ = mylibc::tcsetattr(fd, &termios);
+
+        Self {
+            saved_termios: saved_termios.clone(),
+        }
+    }
+}
+
+impl InputReader {
+    /// move the shell to its own thread, the first prompt is displayed right away
+    pub fn spawn(mut cli: Cli) -> Self {
+        let (line_sender, lines) = unbounded_channel();
+        let (ready, ready_receiver) = mpsc::channel::<()>();
+        let target = cli.target.clone();
+        let attached = cli.attached.clone();
+        let displayed_line = cli.displayed_line.clone();
+        let saved_termios = cli.saved_termios.clone();
+
+        std::thread::spawn(move || {
+            // wait for the previous line to be processed before prompting again
+            while ready_receiver.recv().is_ok() {
+                let line = cli.read_line();
+                let failed = line.is_err();
+                if line_sender.send(line).is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        let reader = Self {
+            lines,
+            ready,
+            target,
+            attached,
+            displayed_line,
+            saved_termios,
+        };
+        reader.prompt_again();
+        reader
+    }
+
+    /// wait for the next line typed by the user, this is cancel safe
+    pub async fn next_line(&mut self) -> Option<Result<Input, TaskmasterError>> {
+        self.lines.recv().await
+    }
+
+    /// the program the shell is attached to, if any
+    pub fn attached(&self) -> Option<String> {
+        self.attached.lock().unwrap().clone()
+    }
+
+    /// attach the shell to a program or detach it, this is shown in the prompt
+    pub fn set_attached(&self, program: Option<String>) {
+        *self.attached.lock().unwrap() = program;
+    }
+
+    /// allow the shell to display a new prompt, to be called once a line has been processed
+    pub fn prompt_again(&self) {
+        let _ = self.ready.send(());
+    }
+
+    /// give the terminal its settings back if a line is being read, to be called before the
+    /// client exits since the reading thread doesn't get the chance to
+    pub fn restore_terminal(&self) {
+        restore_terminal(&self.saved_termios);
+    }
+
+    /// print a message without mixing it with the line being typed,
+    /// the prompt and the current line are displayed again below it
+    pub fn print_above_prompt(&self, message: &str) {
+        let displayed_line = self.displayed_line.lock().unwrap();
+        match displayed_line.as_ref() {
+            Some(line) => {
+                let prompt = prompt(&self.target, &self.attached);
+                clear_prompt(&prompt, line);
+                print!("{message}{prompt}{line}");
+            }
+            None => print!("{message}"),
+        }
+        let _ = io::stdout().flush();
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        restore_terminal(&self.saved_termios);
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// Restore the terminal to its original settings, once
+fn restore_terminal(saved_termios: &SavedTermios) {
+    if let Some(termios) = saved_termios.lock().unwrap().take() {
+        let _ = mylibc::tcsetattr(io::stdin().as_raw_fd(), &termios);
+    }
+}
+
+/// erase the prompt and the line typed after it, which wrap over several rows when they
+/// are wider than the terminal, leaving the cursor at the start of the prompt
+fn clear_prompt(prompt: &str, line: &str) {
+    print!("{RESET_CURSOR}");
+    if let Ok((_, columns)) = mylibc::get_terminal_size(io::stdout().as_raw_fd()) {
+        let width = prompt.chars().count() + line.chars().count();
+        let rows = width.saturating_sub(1) / usize::from(columns.max(1));
+        if rows > 0 {
+            print!("\x1B[{rows}A");
+        }
+    }
+    print!("{CLEAR_TO_END}");
+}
+
+/// the prompt shows the daemon being controlled and the program attached to, if any
+fn prompt(target: &str, attached: &Mutex<Option<String>>) -> String {
+    match attached.lock().unwrap().as_deref() {
+        Some(program) => format!("taskmaster({target})[attached:{program}]> "),
+        None => format!("taskmaster({target})> "),
+    }
+}
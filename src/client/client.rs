@@ -2,19 +2,34 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
-use std::{thread::sleep, time::Duration};
+use std::time::{Duration, Instant};
 
-use cli::Cli;
+use args::Arguments;
+use cli::{Cli, Input, InputReader};
 use command::Command;
-use tcl::SOCKET_ADDRESS;
+use config::ClientConfig;
+use connection::Connection;
+use session::SessionLog;
+use tcl::error::TaskmasterError;
+use tcl::message::{Request, Response, COMMIT, PROTOCOL_VERSION, VERSION};
 use tokio::net::TcpStream;
 
 /* -------------------------------------------------------------------------- */
 /*                                   Module                                   */
 /* -------------------------------------------------------------------------- */
+mod args;
 mod cli;
 mod command;
+mod config;
+mod connection;
 mod history;
+mod session;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constants                                 */
+/* -------------------------------------------------------------------------- */
+/// time to wait between two connection attempts
+const RETRY_DELAY: Duration = Duration::from_secs(2);
 
 /* -------------------------------------------------------------------------- */
 /*                                    Main                                    */
@@ -22,49 +37,295 @@ mod history;
 
 #[tokio::main]
 async fn main() {
-    // connect to the server
-    println!("Trying to connect to the server");
-    let mut stream = loop {
-        match TcpStream::connect(SOCKET_ADDRESS).await {
-            Ok(stream) => {
-                break stream;
+    // parse the command line
+    let arguments = match Arguments::parse() {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!("{}", Arguments::usage());
+            std::process::exit(2);
+        }
+    };
+    if arguments.help {
+        println!("{}", Arguments::usage());
+        println!();
+        Command::help();
+        return;
+    }
+    if arguments.version {
+        println!("client {VERSION} ({COMMIT}), protocol {PROTOCOL_VERSION}");
+        return;
+    }
+
+    // load the client config, an invalid file should not prevent the use of the client
+    let config = ClientConfig::load(arguments.config.as_deref()).unwrap_or_else(|error| {
+        eprintln!("Ignoring client config: {error}");
+        ClientConfig::default()
+    });
+
+    // open the session transcript if one was requested
+    let mut session = match arguments.log_session.as_deref().map(SessionLog::open) {
+        Some(Ok(session)) => Some(session),
+        Some(Err(error)) => {
+            eprintln!("Can't open the session log: {error}");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    // a command given on the command line is validated before trying to connect
+    if let Some(user_input) = arguments.command.as_deref() {
+        let user_input = config.expand_aliases(user_input);
+        match Command::try_from(user_input.as_str()) {
+            Ok(Command::Request(request)) => {
+                let mut connection = connect_or_exit(&arguments).await;
+                if let Some(session) = session.as_mut() {
+                    log_session(session.record_command(&user_input));
+                }
+                let subscribe = matches!(
+                    request,
+                    Request::Subscribe | Request::Attach { .. } | Request::Tail { .. }
+                );
+                let executed = match arguments.json {
+                    true => connection.request(&request).await.map(|response| {
+                        print_json(&response);
+                        Some(response)
+                    }),
+                    false => Command::Request(request).execute(&mut connection).await,
+                };
+                let exit_code = match executed {
+                    Ok(Some(response)) => {
+                        if let Some(session) = session.as_mut() {
+                            log_session(session.record_response(&response));
+                        }
+                        if subscribe && !response.is_error() {
+                            // display the events or the output until the server close the connection
+                            // or the end of the output
+                            while let Some(event) = connection.next_unsolicited().await {
+                                match arguments.json {
+                                    true => print_json(&event),
+                                    false => print!("{event}"),
+                                }
+                                if let Some(session) = session.as_mut() {
+                                    log_session(session.record_response(&event));
+                                }
+                                if matches!(event, Response::StreamEnd(_)) {
+                                    break;
+                                }
+                            }
+                        }
+                        i32::from(response.is_error())
+                    }
+                    Ok(None) => 0,
+                    Err(error) => {
+                        eprintln!("Error while executing command: {error}");
+                        1
+                    }
+                };
+                std::process::exit(exit_code);
             }
-            Err(e) => {
-                eprintln!("can't connect: {e}");
-                sleep(Duration::from_secs(2));
+            Ok(command) => {
+                if let Err(error) = command.execute_locally() {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Err(error) => {
+                eprintln!("Invalid command: {error}. Use --help for more info.");
+                std::process::exit(2);
             }
         }
-    };
+    }
+
+    let mut connection = connect_or_exit(&arguments).await;
     Command::help(); // display the cli manual
-    let mut shell = Cli::new();
+    let mut input = InputReader::spawn(Cli::new(&arguments.target(), config.detach_key));
+    // while following programs the typed lines are still commands
+    let mut tailing = false;
     loop {
-        match shell.read_line() {
-            Ok(user_input) => {
-                process_user_input(user_input, &mut stream).await;
-            }
-            Err(error) => {
-                eprintln!("Error reading line: {}", error);
-                return;
+        tokio::select! {
+            line = input.next_line() => match line {
+                Some(Ok(Input::Line(user_input))) => {
+                    match (input.attached(), tailing) {
+                        (Some(program), false) => {
+                            forward_input(program, user_input, &mut connection).await
+                        }
+                        _ => {
+                            let attached = process_user_input(
+                                user_input,
+                                &config,
+                                &mut session,
+                                &mut connection,
+                            )
+                            .await;
+                            if let Some((label, tail)) = attached {
+                                input.set_attached(Some(label));
+                                tailing = tail;
+                            }
+                        }
+                    }
+                    input.prompt_again();
+                }
+                Some(Ok(Input::Detach)) => {
+                    input.set_attached(None);
+                    tailing = false;
+                    match connection.request(&Request::Detach).await {
+                        Ok(response) => print!("{response}"),
+                        Err(error) => eprintln!("Error while detaching: {error}"),
+                    }
+                    input.prompt_again();
+                }
+                Some(Err(error)) => {
+                    eprintln!("Error reading line: {}", error);
+                    return;
+                }
+                None => return,
+            },
+            message = connection.next_unsolicited() => match message {
+                Some(message) => {
+                    // the attached program was removed, the typed lines are commands again
+                    if let Response::StreamEnd(program) = &message {
+                        if input.attached().as_ref() == Some(program) {
+                            input.set_attached(None);
+                            tailing = false;
+                        }
+                    }
+                    input.print_above_prompt(&message.to_string());
+                }
+                None => {
+                    println!();
+                    eprintln!("The server closed the connection");
+                    // the thread reading the line is left in raw mode
+                    input.restore_terminal();
+                    std::process::exit(1);
+                }
+            },
+        }
+    }
+}
+
+/// try to connect to the server until it succeed or the connection timeout is reached,
+/// exiting with an error in the latter case. the progress goes to stderr, so the output of
+/// a command given on the command line is only its response
+async fn connect_or_exit(arguments: &Arguments) -> Connection {
+    eprintln!("Trying to connect to the server at {}", arguments.address());
+    match connect(arguments).await {
+        Ok(stream) => Connection::new(stream, arguments.request_timeout),
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn connect(arguments: &Arguments) -> Result<TcpStream, TaskmasterError> {
+    let deadline = arguments
+        .connect_timeout
+        .map(|timeout| Instant::now() + timeout);
+    loop {
+        let attempt = TcpStream::connect(arguments.address());
+        let result = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match tokio::time::timeout(remaining, attempt).await {
+                    Ok(result) => result,
+                    Err(elapsed) => Err(elapsed.into()),
+                }
             }
+            None => attempt.await,
+        };
+        let error = match result {
+            Ok(stream) => return Ok(stream),
+            Err(error) => error,
+        };
+        if deadline.is_some_and(|deadline| Instant::now() + RETRY_DELAY >= deadline) {
+            return Err(TaskmasterError::Custom(format!(
+                "can't reach the server at {}: {error}",
+                arguments.address()
+            )));
         }
+        eprintln!("can't connect: {error}, retrying");
+        tokio::time::sleep(RETRY_DELAY).await;
     }
 }
 
-async fn process_user_input(user_input: String, stream: &mut TcpStream) {
-    let trimmed_user_input = user_input.trim().to_owned();
+/// execute the command typed by the user, returning what the shell is now attached to: a
+/// program, or the programs it follows if it is a tail
+async fn process_user_input(
+    user_input: String,
+    config: &ClientConfig,
+    session: &mut Option<SessionLog>,
+    connection: &mut Connection,
+) -> Option<(String, bool)> {
+    let trimmed_user_input = config.expand_aliases(user_input.trim());
 
     if trimmed_user_input.is_empty() {
-        return;
+        return None;
+    }
+
+    if let Some(session) = session {
+        log_session(session.record_command(&trimmed_user_input));
     }
 
     match Command::try_from(trimmed_user_input.as_str()) {
-        Ok(command) => {
-            if let Err(error) = command.execute(stream).await {
+        Ok(command) => match command.execute(connection).await {
+            Ok(Some(response)) => {
+                if let Some(session) = session {
+                    log_session(session.record_response(&response));
+                }
+                match (command, response.is_error()) {
+                    (Command::Request(Request::Attach { program, .. }), false) => {
+                        return Some((program, false))
+                    }
+                    (Command::Request(Request::Tail { programs }), false) => {
+                        return Some((programs.join(", "), true))
+                    }
+                    _ => {}
+                }
+            }
+            Ok(None) => {}
+            Err(error) => {
                 eprintln!("Error while executing command: {error}");
+                if let Some(session) = session {
+                    log_session(session.record_error(&error));
+                }
             }
-        }
+        },
         Err(error) => {
             eprintln!("Error while parsing command: {error}. Type 'help' for more info or 'exit' to close.");
+            if let Some(session) = session {
+                log_session(session.record_error(&error));
+            }
         }
     }
+    None
+}
+
+/// send a line typed while attached to the stdin of the program, only the errors are displayed
+async fn forward_input(program: String, line: String, connection: &mut Connection) {
+    let request = Request::Stdin {
+        program,
+        data: line + "\n",
+    };
+    match connection.request(&request).await {
+        Ok(response) if response.is_error() => print!("{response}"),
+        Ok(_) => {}
+        Err(error) => eprintln!("Error while writing to the program: {error}"),
+    }
+}
+
+/// report a failure to write the transcript without interrupting the session
+/// print a response on a line of its own as json, for the scripts
+fn print_json(response: &Response) {
+    match serde_json::to_string(response) {
+        Ok(json) => println!("{json}"),
+        Err(error) => eprintln!("Can't print the response as json: {error}"),
+    }
+}
+
+fn log_session(result: Result<(), TaskmasterError>) {
+    if let Err(error) = result {
+        eprintln!("Can't write to the session log: {error}");
+    }
 }
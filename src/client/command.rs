@@ -1,13 +1,18 @@
 /* -------------------------------------------------------------------------- */
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
-use std::ops::Deref;
-use tcl::message::{receive, Response};
+use crate::connection::Connection;
+use std::{ops::Deref, time::Duration};
 use tcl::{
     error::TaskmasterError,
-    message::{send, Request},
+    message::{LagPolicy, Request, Response, StatusQuery},
 };
-use tokio::net::TcpStream;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the number of lines shown by the history command when none is given
+const DEFAULT_HISTORY_LINES: usize = 50;
 
 /* -------------------------------------------------------------------------- */
 /*                             Struct Declaration                             */
@@ -23,28 +28,45 @@ pub enum Command {
 /*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
 impl Command {
-    /// This Function will match the command and execute it properly
-    pub async fn execute(&self, stream: &mut TcpStream) -> Result<(), TaskmasterError> {
+    /// This Function will match the command and execute it properly,
+    /// returning the response of the server if the command was forwarded to it
+    pub async fn execute(
+        &self,
+        connection: &mut Connection,
+    ) -> Result<Option<Response>, TaskmasterError> {
         match self {
             Command::Exit => {
                 Command::exit();
-                Ok(())
+                Ok(None)
             }
             Command::Help => {
                 Command::help();
-                Ok(())
+                Ok(None)
             }
             Command::Request(request) => {
-                Command::forward_to_server(request, stream).await?;
-                let response: Result<Response, TaskmasterError> = receive(stream).await;
-                match response {
-                    Ok(result) => print!("{result}"),
-                    Err(error) => {
-                        println!("{error}");
+                let response = connection.request(request).await?;
+                print!("{response}");
+                if let Response::Version(daemon) = &response {
+                    if let Some(mismatch) = daemon.mismatch() {
+                        println!("⚠️  {:15} {mismatch}", "Warning:");
                     }
                 }
+                Ok(Some(response))
+            }
+        }
+    }
+
+    /// execute a command that doesn't need the server
+    pub fn execute_locally(&self) -> Result<(), TaskmasterError> {
+        match self {
+            Command::Exit => Ok(()),
+            Command::Help => {
+                Command::help();
                 Ok(())
             }
+            Command::Request(_) => Err(TaskmasterError::Custom(
+                "this command need to be sent to the server".to_owned(),
+            )),
         }
     }
 
@@ -58,26 +80,54 @@ impl Command {
         println!(
             "Taskmaster Client/server architecture Commands:
 
-            status              Get the status of all the programs
-            start [PROGRAM]     Start a program
-            stop [PROGRAM]      Stop a program
-            restart [PROGRAM]   Restart a program
+            status [OPTIONS]    Get the status of all the programs, or of tag:TAG
+                --state STATES      only show the processes in these states (e.g. fatal,backoff)
+                --sort ORDER        order the programs by name, uptime or restarts
+                -v, --verbose       summarize the config of the programs
+            start [PROGRAM]     Start a program, or every program tagged TAG with tag:TAG
+            stop [PROGRAM]      Stop a program, or every program tagged TAG with tag:TAG
+            restart [PROGRAM]   Restart a program, or every program tagged TAG with tag:TAG
+                --rolling           restart the processes one at a time
+                --cascade           then restart the programs depending on it (depends_on)
+            run PROGRAM --for DURATION
+                                Start a program and stop it once the duration elapsed
+                                (e.g. 30s, 5m, 2h)
             reload              Reload configuration file
+            reload-program [PROGRAM]
+                                Send its reload_signal to a program, which isn't restarted
+            reexec              Replace the daemon by its (possibly upgraded) executable,
+                                the programs keep running
+            pause [PROGRAM]     Suspend the processes of a program (SIGSTOP)
+            resume [PROGRAM]    Resume the processes of a paused program (SIGCONT)
+            maintenance [PROGRAM] on|off
+                                Stop or resume reacting to the exits of a program
+            pause-restarts PROGRAM [--for DURATION]
+                                Stop restarting the processes of a program that exit, the
+                                running ones are left alone, until resumed or the duration
+                                elapsed
+            resume-restarts PROGRAM
+                                Restart again the processes of a program that exit
+            loglevel [LEVEL]    Show or set the daemon log level (debug, info, warn, error)
+            subscribe           Display the daemon events as they happen
+            stats               Show the statistics of the daemon itself
+            version             Show the version of the daemon and the config it runs
+            history PROGRAM [LINES]
+                                Show the last lines written on stdout by a program (default 50)
+            grep PROGRAM PATTERN
+                                Show the lines of the stdout history matching a regex
+            fg PROGRAM          Display the output of a program and send it the lines typed,
+                                Ctrl+] (detach_key) detach without stopping it
+                --on-lag POLICY     when too slow, skip the lines (drop) or detach (disconnect)
+            tail -f PROGRAM... [--merge]
+                                Display the output of programs as it is written, several
+                                programs are merged into one feed, Ctrl+] stops it
             exit                Exit client shell
             help                Show this help message
 
+            Aliases: st (status), rs (restart), more can be defined in ~/.taskmasterctl.yaml
         "
         )
     }
-
-    /// process the request command
-    async fn forward_to_server(
-        request: &Request,
-        stream: &mut TcpStream,
-    ) -> Result<(), TaskmasterError> {
-        send(stream, request).await?;
-        Ok(())
-    }
 }
 
 /* -------------------------------------------------------------------------- */
@@ -91,12 +141,7 @@ impl TryFrom<&str> for Command {
         let arguments: Vec<&str> = user_input.split_ascii_whitespace().collect();
 
         // check if too many or too little argument are present
-        if arguments.len() > 2 {
-            return Err(TaskmasterError::Custom(format!(
-                "`{}` contain to many arguments",
-                user_input
-            )));
-        } else if arguments.is_empty() {
+        if arguments.is_empty() {
             return Err(TaskmasterError::Custom(
                 "your command contain nothing".to_owned(),
             ));
@@ -109,14 +154,167 @@ impl TryFrom<&str> for Command {
             .to_ascii_lowercase()
             .to_owned();
 
+        // the status command is the only one taking options
+        if command == "status" {
+            let query = parse_status_query(&arguments[1..])?;
+            return Ok(Command::Request(Request::Status(query)));
+        }
+
+        if command == "maintenance" {
+            return match arguments[1..] {
+                [program, "on"] => Ok(Command::Request(Request::Maintenance(
+                    program.to_ascii_lowercase(),
+                    true,
+                ))),
+                [program, "off"] => Ok(Command::Request(Request::Maintenance(
+                    program.to_ascii_lowercase(),
+                    false,
+                ))),
+                _ => Err(TaskmasterError::Custom(
+                    "usage: maintenance PROGRAM on|off".to_owned(),
+                )),
+            };
+        }
+
+        if command == "pause-restarts" {
+            return match arguments[1..] {
+                [program] => Ok(Command::Request(Request::PauseRestarts {
+                    program: program.to_ascii_lowercase(),
+                    duration: None,
+                })),
+                [program, "--for", duration] => Ok(Command::Request(Request::PauseRestarts {
+                    program: program.to_ascii_lowercase(),
+                    duration: Some(parse_duration(duration)?),
+                })),
+                _ => Err(TaskmasterError::Custom(
+                    "usage: pause-restarts PROGRAM [--for DURATION]".to_owned(),
+                )),
+            };
+        }
+
+        if command == "history" {
+            let lines = match arguments.get(2) {
+                Some(lines) => lines.parse().map_err(|_| {
+                    TaskmasterError::Custom(format!("`{lines}` is not a number of lines"))
+                })?,
+                None => DEFAULT_HISTORY_LINES,
+            };
+            return match arguments[1..] {
+                [program] | [program, _] => Ok(Command::Request(Request::History {
+                    program: program.to_ascii_lowercase(),
+                    lines,
+                })),
+                _ => Err(TaskmasterError::Custom(
+                    "usage: history PROGRAM [LINES]".to_owned(),
+                )),
+            };
+        }
+
+        if command == "restart" && arguments.get(1) == Some(&"--cascade") {
+            return match arguments[2..] {
+                [program] => Ok(Command::Request(Request::CascadeRestart(
+                    program.to_ascii_lowercase(),
+                ))),
+                _ => Err(TaskmasterError::Custom(
+                    "usage: restart --cascade PROGRAM".to_owned(),
+                )),
+            };
+        }
+
+        if command == "restart" && arguments.get(1) == Some(&"--rolling") {
+            return match arguments[2..] {
+                [program] => Ok(Command::Request(Request::RollingRestart(
+                    program.to_ascii_lowercase(),
+                ))),
+                _ => Err(TaskmasterError::Custom(
+                    "usage: restart --rolling PROGRAM".to_owned(),
+                )),
+            };
+        }
+
+        if command == "run" {
+            return match arguments[1..] {
+                [program, "--for", duration] => Ok(Command::Request(Request::Run {
+                    program: program.to_ascii_lowercase(),
+                    duration: parse_duration(duration)?,
+                })),
+                _ => Err(TaskmasterError::Custom(
+                    "usage: run PROGRAM --for DURATION".to_owned(),
+                )),
+            };
+        }
+
+        // the pattern is taken as typed, it may contain spaces
+        if command == "grep" {
+            return match arguments[1..] {
+                [program, ref pattern @ ..] if !pattern.is_empty() => {
+                    Ok(Command::Request(Request::Grep {
+                        program: program.to_ascii_lowercase(),
+                        pattern: pattern.join(" "),
+                    }))
+                }
+                _ => Err(TaskmasterError::Custom(
+                    "usage: grep PROGRAM PATTERN".to_owned(),
+                )),
+            };
+        }
+
+        if command == "fg" {
+            let on_lag = match arguments[1..] {
+                [_] => LagPolicy::default(),
+                [_, "--on-lag", policy] => policy.parse()?,
+                _ => {
+                    return Err(TaskmasterError::Custom(
+                        "usage: fg PROGRAM [--on-lag drop|disconnect]".to_owned(),
+                    ))
+                }
+            };
+            return Ok(Command::Request(Request::Attach {
+                program: arguments[1].to_ascii_lowercase(),
+                on_lag,
+            }));
+        }
+
+        if command == "tail" {
+            let mut programs: Vec<String> = Vec::new();
+            for program in arguments.iter().skip(2) {
+                let program = program.to_ascii_lowercase();
+                if program != "--merge" && !programs.contains(&program) {
+                    programs.push(program);
+                }
+            }
+            let merged = arguments.contains(&"--merge");
+            return match (arguments.get(1), programs.len()) {
+                (Some(&"-f"), 1) => Ok(Command::Request(Request::Tail { programs })),
+                (Some(&"-f"), 2..) if merged => Ok(Command::Request(Request::Tail { programs })),
+                (Some(&"-f"), 2..) => Err(TaskmasterError::Custom(
+                    "several programs are followed merged, add --merge".to_owned(),
+                )),
+                _ => Err(TaskmasterError::Custom(
+                    "usage: tail -f PROGRAM... [--merge]".to_owned(),
+                )),
+            };
+        }
+
+        if arguments.len() > 2 {
+            return Err(TaskmasterError::Custom(format!(
+                "`{}` contain to many arguments",
+                user_input
+            )));
+        }
+
         // construct the CliCommand struct base on whenever there are only 1 or two word in the user input
         let cli_command = if arguments.len() == 1 {
             // try to match against command that need no argument
             match command.deref() {
                 "exit" => Command::Exit,
                 "help" => Command::Help,
-                "status" => Command::Request(Request::Status),
                 "reload" => Command::Request(Request::Reload),
+                "reexec" => Command::Request(Request::Reexec),
+                "loglevel" => Command::Request(Request::LogLevel(None)),
+                "subscribe" => Command::Request(Request::Subscribe),
+                "stats" => Command::Request(Request::Stats),
+                "version" => Command::Request(Request::Version),
                 _ => return Err(TaskmasterError::Custom(format!("'{command}' Not found"))),
             }
         } else {
@@ -127,6 +325,11 @@ impl TryFrom<&str> for Command {
                 "start" => Command::Request(Request::Start(argument.to_owned())),
                 "stop" => Command::Request(Request::Stop(argument.to_owned())),
                 "restart" => Command::Request(Request::Restart(argument.to_owned())),
+                "reload-program" => Command::Request(Request::ReloadProgram(argument.to_owned())),
+                "pause" => Command::Request(Request::Pause(argument.to_owned())),
+                "resume" => Command::Request(Request::Resume(argument.to_owned())),
+                "resume-restarts" => Command::Request(Request::ResumeRestarts(argument.to_owned())),
+                "loglevel" => Command::Request(Request::LogLevel(Some(argument.parse()?))),
                 _ => return Err(TaskmasterError::Custom(format!("'{command}' Not found"))),
             }
         };
@@ -134,3 +337,60 @@ impl TryFrom<&str> for Command {
         Ok(cli_command)
     }
 }
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// parse a duration in seconds, minutes or hours: `30s`, `5m`, `2h`, or `30` for seconds
+fn parse_duration(duration: &str) -> Result<Duration, TaskmasterError> {
+    let (number, unit) = match duration.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => duration.split_at(index),
+        None => (duration, "s"),
+    };
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => 0,
+    };
+    match number.parse::<u64>() {
+        Ok(number) if seconds != 0 && number != 0 => Ok(Duration::from_secs(number * seconds)),
+        _ => Err(TaskmasterError::Custom(format!(
+            "`{duration}` is not a duration (e.g. 30s, 5m, 2h)"
+        ))),
+    }
+}
+
+/// parse the options of the status command: `--state fatal,backoff`, `--sort uptime`, `-v`
+/// and `tag:gpu`
+fn parse_status_query(options: &[&str]) -> Result<StatusQuery, TaskmasterError> {
+    let mut query = StatusQuery::default();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        if let Some(tag) = option.strip_prefix("tag:") {
+            query.tag = Some(tag.to_ascii_lowercase());
+            continue;
+        }
+        if matches!(*option, "-v" | "--verbose") {
+            query.verbose = true;
+            continue;
+        }
+        let value = options
+            .next()
+            .ok_or_else(|| TaskmasterError::Custom(format!("`{option}` expect a value")))?;
+        match *option {
+            "--state" => {
+                for state in value.split(',') {
+                    query.states.push(state.parse()?);
+                }
+            }
+            "--sort" => query.sort = value.parse()?,
+            _ => {
+                return Err(TaskmasterError::Custom(format!(
+                    "`{option}` is not a status option (--state, --sort, -v, tag:TAG)"
+                )))
+            }
+        }
+    }
+    Ok(query)
+}
@@ -0,0 +1,172 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+use tcl::error::TaskmasterError;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Module                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constants                                 */
+/* -------------------------------------------------------------------------- */
+/// environment variable overriding the location of the client config file
+const CONFIG_ENV: &str = "TASKMASTER_CLIENT_CONFIG";
+
+/// name of the client config file looked up in the home directory
+const CONFIG_FILE_NAME: &str = ".taskmasterctl.yaml";
+
+/// aliases available even without any config file
+const BUILTIN_ALIASES: [(&str, &str); 2] = [("st", "status"), ("rs", "restart")];
+
+/// the key detaching the shell from a program by default, Ctrl+]
+const DEFAULT_DETACH_KEY: u8 = 0x1D;
+
+/// the control keys the shell already handles: Ctrl+C (clear the line), Enter and Escape
+const RESERVED_KEYS: [u8; 4] = [0x03, b'\n', b'\r', 0x1B];
+
+/* -------------------------------------------------------------------------- */
+/*                             Struct Declaration                             */
+/* -------------------------------------------------------------------------- */
+/// represent the optional settings of the client
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// shortcuts expanded before the command is parsed, the value may contain arguments
+    aliases: HashMap<String, String>,
+
+    /// the key detaching the shell from the program it is attached to
+    pub detach_key: DetachKey,
+}
+
+/// a control key, written `ctrl-]` or `^]` in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct DetachKey(u8);
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl ClientConfig {
+    /// load the given client config or the default one, the default file may be missing and
+    /// result in the default config
+    pub fn load(given: Option<&Path>) -> Result<Self, TaskmasterError> {
+        let Some(path) = given.map(Path::to_path_buf).or_else(Self::path) else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                let mut config: ClientConfig = serde_yaml::from_str(&contents)?;
+                // the first word of the input is lowercased before it is looked up
+                config.aliases = config
+                    .aliases
+                    .into_iter()
+                    .map(|(alias, command)| (alias.to_ascii_lowercase(), command))
+                    .collect();
+                for (alias, command) in BUILTIN_ALIASES {
+                    config
+                        .aliases
+                        .entry(alias.to_owned())
+                        .or_insert(command.to_owned());
+                }
+                Ok(config)
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound && given.is_none() => {
+                Ok(Self::default())
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// replace the first word of the user input by its alias if there is one,
+    /// aliases are expanded only once so they cannot loop
+    pub fn expand_aliases(&self, user_input: &str) -> String {
+        let user_input = user_input.trim_start();
+        let (first, rest) = user_input
+            .split_once(char::is_whitespace)
+            .unwrap_or((user_input, ""));
+        match self.aliases.get(&first.to_ascii_lowercase()) {
+            Some(command) if rest.is_empty() => command.to_owned(),
+            Some(command) => format!("{command} {rest}"),
+            None => user_input.to_owned(),
+        }
+    }
+
+    fn path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var(CONFIG_ENV) {
+            return Some(PathBuf::from(path));
+        }
+        std::env::var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join(CONFIG_FILE_NAME))
+    }
+}
+
+impl DetachKey {
+    /// the byte the terminal sends for the key
+    pub fn byte(self) -> u8 {
+        self.0
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            aliases: BUILTIN_ALIASES
+                .iter()
+                .map(|(alias, command)| (alias.to_string(), command.to_string()))
+                .collect(),
+            detach_key: DetachKey::default(),
+        }
+    }
+}
+
+impl Default for DetachKey {
+    fn default() -> Self {
+        Self(DEFAULT_DETACH_KEY)
+    }
+}
+
+impl TryFrom<String> for DetachKey {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let lowercase = value.to_ascii_lowercase();
+        let key = ["ctrl-", "ctrl+", "^"]
+            .iter()
+            .find_map(|prefix| lowercase.strip_prefix(prefix));
+        let byte = match key.map(str::as_bytes) {
+            // the control keys are the ones from @ to _, the letters in either case
+            Some(&[key]) if (b'@'..=b'_').contains(&key.to_ascii_uppercase()) => {
+                key.to_ascii_uppercase() ^ 0x40
+            }
+            _ => {
+                return Err(format!(
+                    "`{value}` is not a control key (e.g. ctrl-] or ^])"
+                ))
+            }
+        };
+        match RESERVED_KEYS.contains(&byte) {
+            true => Err(format!("`{value}` is already used by the shell")),
+            false => Ok(Self(byte)),
+        }
+    }
+}
+
+impl Display for DetachKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "^{}", (self.0 ^ 0x40) as char)
+    }
+}
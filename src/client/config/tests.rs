@@ -0,0 +1,47 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{ClientConfig, DetachKey};
+use std::fs;
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[test]
+fn detach_key_is_ctrl_right_bracket_by_default() {
+    let config: ClientConfig = serde_yaml::from_str("aliases: {}").unwrap();
+
+    assert_eq!(config.detach_key.byte(), 0x1D);
+    assert_eq!(config.detach_key.to_string(), "^]");
+}
+
+#[test]
+fn detach_key_is_any_control_key_the_shell_doesnt_use() {
+    for (written, byte) in [("ctrl-x", 0x18), ("Ctrl+D", 0x04), ("^\\", 0x1C)] {
+        let key = DetachKey::try_from(written.to_owned()).unwrap();
+        assert_eq!(key.byte(), byte, "{written}");
+    }
+    for written in ["ctrl-c", "^m", "^[", "ctrl-", "x", "ctrl-ab"] {
+        assert!(
+            DetachKey::try_from(written.to_owned()).is_err(),
+            "{written}"
+        );
+    }
+}
+
+#[test]
+fn aliases_are_expanded_whatever_their_case() {
+    let path = std::env::temp_dir().join(format!("taskmasterctl-{}.yaml", std::process::id()));
+    fs::write(&path, "aliases:\n  Dep: restart web\n").unwrap();
+    let config = ClientConfig::load(Some(&path)).unwrap();
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(config.expand_aliases("dep"), "restart web");
+    assert_eq!(
+        config.expand_aliases("DEP --rolling"),
+        "restart web --rolling"
+    );
+    assert_eq!(config.expand_aliases("St"), "status");
+    assert_eq!(config.expand_aliases("depot"), "depot");
+}
@@ -0,0 +1,109 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use std::time::Duration;
+use tcl::{
+    error::TaskmasterError,
+    message::{Framed, Identified, Request, Response, UNSOLICITED_ID},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, WriteHalf},
+    net::TcpStream,
+    sync::mpsc::{self, UnboundedReceiver},
+    time::timeout,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                             Struct Declaration                             */
+/* -------------------------------------------------------------------------- */
+/// a connection to the server, the responses are read by a dedicated task so
+/// waiting for one can be abandoned without corrupting the stream
+pub struct Connection<S = TcpStream> {
+    writer: Framed<WriteHalf<S>>,
+    responses: UnboundedReceiver<Result<Identified<Response>, TaskmasterError>>,
+    /// the messages the server sent on its own
+    unsolicited: UnboundedReceiver<Response>,
+    next_id: u64,
+    request_timeout: Duration,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl<S: AsyncRead + AsyncWrite + Send + 'static> Connection<S> {
+    /// take ownership of the stream and spawn the task reading the responses, the stream
+    /// is a tcp one unless it is an in-memory pipe of the tests
+    pub fn new(stream: S, request_timeout: Duration) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        let mut reader = Framed::new(reader);
+        let (sender, responses) = mpsc::unbounded_channel();
+        let (unsolicited_sender, unsolicited) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            loop {
+                let response = reader
+                    .receive::<Identified<Response>>()
+                    .await
+                    .map(|(response, _)| response);
+                let forwarded = match response {
+                    Ok(response) if response.id == UNSOLICITED_ID => {
+                        unsolicited_sender.send(response.message).is_ok()
+                    }
+                    Ok(response) => sender.send(Ok(response)).is_ok(),
+                    Err(error) => {
+                        let _ = sender.send(Err(error));
+                        false
+                    }
+                };
+                if !forwarded {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            writer: Framed::new(writer),
+            responses,
+            unsolicited,
+            next_id: 0,
+            request_timeout,
+        }
+    }
+
+    /// wait for a message sent by the server on its own, `None` meaning that
+    /// the connection is closed, this is cancel safe
+    pub async fn next_unsolicited(&mut self) -> Option<Response> {
+        self.unsolicited.recv().await
+    }
+
+    /// send the request and wait for its response, giving up after the request timeout.
+    /// a response arriving after its request timed out is discarded by the next request
+    pub async fn request(&mut self, request: &Request) -> Result<Response, TaskmasterError> {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.writer
+            .send(&Identified {
+                id,
+                message: request,
+            })
+            .await?;
+
+        let wait_for_response = async {
+            loop {
+                match self.responses.recv().await {
+                    Some(Ok(response)) if response.id == id => return Ok(response.message),
+                    Some(Ok(_late_response)) => continue,
+                    Some(Err(error)) => return Err(error),
+                    None => {
+                        return Err(TaskmasterError::Custom(
+                            "the connection to the server is closed".to_owned(),
+                        ))
+                    }
+                }
+            }
+        };
+        timeout(self.request_timeout, wait_for_response)
+            .await
+            .map_err(|_| TaskmasterError::Timeout(self.request_timeout))?
+    }
+}
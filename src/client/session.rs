@@ -0,0 +1,52 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+use tcl::{error::TaskmasterError, message::Response, time::now_rfc3339};
+
+/* -------------------------------------------------------------------------- */
+/*                             Struct Declaration                             */
+/* -------------------------------------------------------------------------- */
+/// a transcript of the client session, every command typed and every response
+/// received is appended to the file with a timestamp
+pub struct SessionLog {
+    file: File,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl SessionLog {
+    /// open the transcript file, creating it if it doesn't exist, appending to it if it does
+    pub fn open(path: &Path) -> Result<Self, TaskmasterError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut session = SessionLog { file };
+        session.write_entry("SESSION", "started")?;
+        Ok(session)
+    }
+
+    /// record a command typed by the user
+    pub fn record_command(&mut self, command: &str) -> Result<(), TaskmasterError> {
+        self.write_entry("COMMAND", command)
+    }
+
+    /// record a response received from the server
+    pub fn record_response(&mut self, response: &Response) -> Result<(), TaskmasterError> {
+        self.write_entry("RESPONSE", response.to_string().trim_end())
+    }
+
+    /// record an error that prevented a command from being executed
+    pub fn record_error(&mut self, error: &TaskmasterError) -> Result<(), TaskmasterError> {
+        self.write_entry("ERROR", &error.to_string())
+    }
+
+    fn write_entry(&mut self, kind: &str, content: &str) -> Result<(), TaskmasterError> {
+        writeln!(self.file, "[{}] {kind} {content}", now_rfc3339())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
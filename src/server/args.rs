@@ -0,0 +1,168 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use crate::config::DEFAULT_CONFIG_FILE;
+use std::{ffi::OsString, net::SocketAddr, os::fd::RawFd, path::PathBuf};
+use tcl::{
+    args::{unknown_argument, Arg, ArgParser},
+    error::TaskmasterError,
+    message::LogLevel,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constants                                 */
+/* -------------------------------------------------------------------------- */
+const USAGE: &str = "Usage: server [OPTIONS] [check]
+
+Start the daemon supervising the programs of the config file. With check, only
+check that it would start and run (config, listen address, log files, users)
+and exit with a non-zero status on problems.
+
+Options:
+    -c, --config FILE    The config file, reloaded from the same path (default: ./config.yaml)
+    -p, --profile NAME   Merge a profile of the config file over it (default: $TASKMASTER_PROFILE)
+    -a, --address ADDR   Listen for the clients on ADDR, an IP:PORT (default: 127.0.0.1:8042)
+    -l, --log-level LVL  Log from LVL (debug, info, warn, error) instead of the config loglevel
+    --foreground         Also log to the console, the default when stderr is a terminal
+    --init               Exit with the outcome of the children on termination like an init,
+                         the default when run as pid 1
+    --exit-when-done     Exit once every program is done (e.g. a oneshot workload)
+    -h, --help           Show this help message
+    -V, --version        Show the server version";
+
+/// the environment variable selecting the profile when none is given on the command line
+const PROFILE_VARIABLE: &str = "TASKMASTER_PROFILE";
+
+/* -------------------------------------------------------------------------- */
+/*                             Struct Declaration                             */
+/* -------------------------------------------------------------------------- */
+/// represent the options given to the server on the command line
+#[derive(Debug)]
+pub(super) struct Arguments {
+    /// the config file, read at the start and at each reload
+    pub(super) config: PathBuf,
+
+    /// the profile of the config file merged over it, if any
+    pub(super) profile: Option<String>,
+
+    /// the address the clients connect to
+    pub(super) address: SocketAddr,
+
+    /// the level the daemon logs from whatever the config says, kept across the reloads
+    pub(super) log_level: Option<LogLevel>,
+
+    /// log to the console on top of the log file
+    pub(super) foreground: bool,
+
+    /// exit with the aggregate code of the children once they are stopped on termination
+    pub(super) init: bool,
+
+    /// exit once every program reached a state it won't leave without an order
+    pub(super) exit_when_done: bool,
+
+    /// check the config and the environment of the daemon without starting it
+    pub(super) check: bool,
+
+    /// display the usage without doing anything else
+    pub(super) help: bool,
+
+    /// display the version without doing anything else
+    pub(super) version: bool,
+
+    /// the listening socket inherited from the daemon this one replaced on a reexec, whose
+    /// children are adopted. only given by the daemon to itself, it is not documented
+    pub(super) reexec: Option<RawFd>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Arguments {
+    /// parse the process arguments
+    pub(super) fn parse() -> Result<Self, TaskmasterError> {
+        let mut arguments = Self::default();
+
+        let mut args = ArgParser::from_env();
+        while let Some(arg) = args.next_arg()? {
+            match arg {
+                Arg::Option(option) => match option.as_str() {
+                    "--config" | "-c" => arguments.config = PathBuf::from(args.value(&option)?),
+                    "--profile" | "-p" => arguments.profile = Some(args.value(&option)?),
+                    "--address" | "-a" => {
+                        arguments.address = args.parse_value(&option, "an IP:PORT address")?
+                    }
+                    "--log-level" | "-l" => {
+                        arguments.log_level = Some(args.parse_value(&option, "a log level")?)
+                    }
+                    "--foreground" => arguments.foreground = true,
+                    "--init" => arguments.init = true,
+                    "--exit-when-done" => arguments.exit_when_done = true,
+                    "--help" | "-h" => arguments.help = true,
+                    "--version" | "-V" => arguments.version = true,
+                    "--reexec" => {
+                        arguments.reexec = Some(args.parse_value(&option, "a file descriptor")?)
+                    }
+                    _ => return Err(unknown_argument(&option)),
+                },
+                Arg::Positional(command) if command == "check" => arguments.check = true,
+                Arg::Positional(arg) => return Err(unknown_argument(&arg)),
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    /// the arguments of the daemon replacing this one on a reexec, it inherits the given
+    /// listening socket
+    pub(super) fn reexec_args(&self, listener: RawFd) -> Vec<OsString> {
+        let mut args = vec!["--config".into(), self.config.clone().into_os_string()];
+        if let Some(profile) = &self.profile {
+            args.extend(["--profile".into(), profile.into()]);
+        }
+        args.extend(["--address".into(), self.address.to_string().into()]);
+        if let Some(level) = self.log_level {
+            args.extend(["--log-level".into(), level.to_string().into()]);
+        }
+        let flags = [
+            (self.foreground, "--foreground"),
+            (self.init, "--init"),
+            (self.exit_when_done, "--exit-when-done"),
+        ];
+        args.extend(
+            flags
+                .into_iter()
+                .filter(|(given, _)| *given)
+                .map(|(_, flag)| flag.into()),
+        );
+        args.extend(["--reexec".into(), listener.to_string().into()]);
+        args
+    }
+
+    /// return the text displayed by `--help`
+    pub(super) fn usage() -> &'static str {
+        USAGE
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl Default for Arguments {
+    fn default() -> Self {
+        Self {
+            config: PathBuf::from(DEFAULT_CONFIG_FILE),
+            profile: std::env::var(PROFILE_VARIABLE)
+                .ok()
+                .filter(|profile| !profile.is_empty()),
+            address: SocketAddr::V4(tcl::SOCKET_ADDRESS),
+            log_level: None,
+            foreground: false,
+            init: false,
+            exit_when_done: false,
+            check: false,
+            help: false,
+            version: false,
+            reexec: None,
+        }
+    }
+}
@@ -14,7 +14,13 @@ pub fn send_http_message(address: String, message: String) {
         let mut stream = TcpStream::connect(address.to_owned()).unwrap();
 
         // Prepare the JSON payload
-        let body = format!("{{\"message\":\"{}\"}}", message);
+        let body = format!(
+            "{{\"message\":\"{}\"}}",
+            message
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        );
 
         // Construct the HTTP POST request with JSON content type
         let request = format!(
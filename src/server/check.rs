@@ -0,0 +1,123 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use crate::args::Arguments;
+use crate::config::{Config, LogBackend};
+use std::{net::TcpListener, path::Path};
+use tcl::mylibc;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// the outcome of the checks of `server check`, printed as they are made
+#[derive(Debug, Default)]
+struct Report {
+    problems: usize,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Report {
+    fn ok(&self, what: &str) {
+        println!("ok       {what}");
+    }
+
+    fn problem(&mut self, what: &str) {
+        println!("problem  {what}");
+        self.problems += 1;
+    }
+
+    /// check that the daemon can write the file at the path, or create it
+    fn check_writable(&mut self, description: &str, path: &str) {
+        match is_writable(Path::new(path)) {
+            true => self.ok(&format!("{description} {path} is writable")),
+            false => self.problem(&format!("{description} {path} can't be written")),
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// check that the daemon would start and run with the given arguments without starting
+/// anything, printing a report. return the exit code of the daemon, 1 if there are problems
+pub(super) fn run(arguments: &Arguments) -> i32 {
+    let mut report = Report::default();
+
+    // the programs referencing a user that doesn't exist are invalid
+    let config = match Config::load(&arguments.config, arguments.profile.as_deref()) {
+        Ok(config) => config,
+        Err(error) => {
+            report.problem(&format!(
+                "the config {} can't be loaded: {error}",
+                arguments.config.display()
+            ));
+            return summarize(&report);
+        }
+    };
+    report.ok(&format!(
+        "the config {} is loaded, {} programs",
+        arguments.config.display(),
+        config.len()
+    ));
+    for (name, error) in &config.invalid_programs {
+        report.problem(&format!("the program `{name}` is invalid: {error}"));
+    }
+
+    match TcpListener::bind(arguments.address) {
+        Ok(_) => report.ok(&format!("{} can be listened on", arguments.address)),
+        Err(error) => report.problem(&format!(
+            "{} can't be listened on: {error}",
+            arguments.address
+        )),
+    }
+
+    if config.server.log_backend == LogBackend::File {
+        report.check_writable("the daemon log", &config.server.log_file);
+    }
+    report.check_writable("the state file", &config.server.state_file);
+    let mut names: Vec<&String> = config.keys().collect();
+    names.sort();
+    for name in names {
+        let program = &config[name];
+        for redirection in program.redirections() {
+            for replica in 0..program.number_of_process {
+                if let Some(path) = redirection.file_path(replica) {
+                    report.check_writable(&format!("the log of `{name}`"), &path);
+                }
+            }
+        }
+    }
+
+    summarize(&report)
+}
+
+fn summarize(report: &Report) -> i32 {
+    match report.problems {
+        0 => {
+            println!("no problem found");
+            0
+        }
+        problems => {
+            println!("{problems} problem(s) found");
+            1
+        }
+    }
+}
+
+/// a missing file is created along with its missing directories, in the closest directory
+/// that exists
+fn is_writable(path: &Path) -> bool {
+    let existing = path
+        .ancestors()
+        .map(|ancestor| match ancestor.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => ancestor,
+        })
+        .find(|ancestor| ancestor.exists());
+    match existing {
+        Some(existing) if existing == path || existing.is_dir() => mylibc::is_writable(existing),
+        _ => false,
+    }
+}
@@ -2,94 +2,537 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
-use tcl::message::{receive, send, Request, Response};
-use tokio::net::TcpStream;
+use std::{fmt::Display, future::Future, net::SocketAddr, task::Poll, time::Instant};
+use tcl::message::{
+    Event, Framed, Identified, LagPolicy, OutputLine, Request, Response, VersionInfo,
+    UNSOLICITED_ID,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc,
+    },
+};
 
 use crate::{
-    config::{Config, SharedConfig},
-    log_error, log_info,
+    config::SharedConfig,
+    event::SharedEventBus,
+    log_error, log_info, log_warn,
     logger::SharedLogger,
-    process_manager::SharedProcessManager,
+    process_manager::{ProgramManager, SharedProcessManager},
+    reexec::{check_reexec, request_reexec},
+    reload::reload_config,
+    stats::STATS,
 };
 
+#[cfg(test)]
+mod tests;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// how many messages can wait to be written to a slow client before the handler waits for it
+const WRITE_QUEUE_SIZE: usize = 64;
+
+/// added to the status and reload responses while the daemon doesn't run the config file
+const CONFIG_CHANGED_WARNING: &str = "config changed on disk, reload pending";
+
 /* -------------------------------------------------------------------------- */
 /*                                   Struct                                   */
 /* -------------------------------------------------------------------------- */
-pub(super) struct ClientHandler {}
+/// a connected client along with what is needed to answer its requests,
+/// the id and address are put in front of every log entry about the client
+pub(super) struct ClientHandler {
+    id: u64,
+    address: SocketAddr,
+    shared_logger: SharedLogger,
+    shared_config: SharedConfig,
+    shared_process_manager: SharedProcessManager,
+    shared_event_bus: SharedEventBus,
+}
+
+/// the program whose output is streamed to a client, or the programs whose outputs are
+/// merged into one feed by a tail
+struct Attachment {
+    /// what the stream is about, given back when it ends
+    label: String,
+    on_lag: LagPolicy,
+    /// the output of each program along with its name, a single one unless merged
+    outputs: Vec<(String, broadcast::Receiver<OutputLine>)>,
+    /// whether the lines are tagged with their program
+    merged: bool,
+}
 
 /* -------------------------------------------------------------------------- */
 /*                               Implementation                               */
 /* -------------------------------------------------------------------------- */
 impl ClientHandler {
-    /// do the actual match of the client request
-    pub(super) async fn handle_client(
-        mut socket: TcpStream,
+    pub(super) fn new(
+        id: u64,
+        address: SocketAddr,
         shared_logger: SharedLogger,
         shared_config: SharedConfig,
         shared_process_manager: SharedProcessManager,
-    ) {
-        use Request as R;
+        shared_event_bus: SharedEventBus,
+    ) -> Self {
+        Self {
+            id,
+            address,
+            shared_logger,
+            shared_config,
+            shared_process_manager,
+            shared_event_bus,
+        }
+    }
+
+    /// answer the client requests until it disconnect, pushing it the daemon events
+    /// once it subscribed to them and the output of the program it is attached to.
+    /// the socket is usually a tcp stream, the tests give one end of an in-memory pipe
+    pub(super) async fn handle_client<S>(self, socket: S)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let shared_logger = &self.shared_logger;
+        log_info!(shared_logger, "{self}: connected");
+        STATS.record_connection(self.id, self.to_string());
+
+        // the requests are read by a dedicated task so waiting for one can be interrupted by an event
+        let (reader, writer) = tokio::io::split(socket);
+        let (mut reader, mut writer) = (Framed::new(reader), Framed::new(writer));
+        let (request_sender, mut requests) = mpsc::unbounded_channel();
+        let client_id = self.id;
+        tokio::spawn(async move {
+            loop {
+                let request =
+                    reader
+                        .receive::<Identified<Request>>()
+                        .await
+                        .map(|(request, bytes)| {
+                            STATS.record_bytes_received(client_id, bytes);
+                            request
+                        });
+                let disconnected = matches!(&request, Err(error) if error.client_disconnected());
+                if request_sender.send(request).is_err() || disconnected {
+                    return;
+                }
+            }
+        });
+
+        // the messages are written by a dedicated task so a complete message is always written
+        // before the next one, whoever produced it
+        let (writer_queue, mut messages) = mpsc::channel::<Identified<Response>>(WRITE_QUEUE_SIZE);
+        let writer_logger = shared_logger.clone();
+        let client = self.to_string();
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                match writer.send(&message).await {
+                    Ok(bytes) => STATS.record_bytes_streamed(client_id, bytes),
+                    Err(error) => {
+                        log_error!(writer_logger, "{client}: {error}");
+                        return;
+                    }
+                }
+            }
+        });
+
+        let mut events = None;
+        let mut attached: Option<Attachment> = None;
         loop {
-            match receive::<Request>(&mut socket).await {
-                Ok(message) => {
-                    let response = match message {
-                        R::Status => {
-                            log_info!(shared_logger, "Status Request gotten");
-                            shared_process_manager
-                                .write()
-                                .expect("Can't acquire process manager")
-                                .get_status()
-                        }
-                        R::Start(name) => {
-                            log_info!(shared_logger, "Start Request gotten");
-                            shared_process_manager
-                                .write()
-                                .unwrap()
-                                .start_program(&name, &shared_logger)
-                        }
-                        R::Stop(name) => {
-                            log_info!(shared_logger, "Stop Request gotten");
-                            shared_process_manager
-                                .write()
-                                .unwrap()
-                                .stop_program(&name, &shared_logger)
-                        }
-                        R::Restart(name) => {
-                            log_info!(shared_logger, "Restart Request gotten");
-                            shared_process_manager
-                                .write()
-                                .unwrap()
-                                .restart_program(&name, &shared_logger)
-                        }
-                        R::Reload => {
-                            log_info!(shared_logger, "Reload Request gotten");
-                            match Config::load() {
-                                Ok(config) => {
-                                    *shared_config.write().unwrap() = config;
-                                    shared_process_manager.write().unwrap().reload_config(
-                                        &shared_config.read().unwrap(),
-                                        &shared_logger,
-                                    );
-                                    Response::Success("Config Reload Successful".to_owned())
+            tokio::select! {
+                request = requests.recv() => match request {
+                    Some(Ok(Identified { id, message })) => {
+                        let handling_start = Instant::now();
+                        let response = match message {
+                            Request::Subscribe => {
+                                events = Some(self.shared_event_bus.subscribe());
+                                Response::Success("Subscribed to the daemon events".to_owned())
+                            }
+                            Request::Attach {
+                                ref program,
+                                on_lag,
+                            } => {
+                                let receiver = self
+                                    .shared_process_manager
+                                    .read()
+                                    .unwrap()
+                                    .attach(program);
+                                match receiver {
+                                    Ok(output) => {
+                                        attached = Some(Attachment {
+                                            label: program.to_owned(),
+                                            on_lag,
+                                            outputs: vec![(program.to_owned(), output)],
+                                            merged: false,
+                                        });
+                                        Response::Success(format!("Attached to {program}"))
+                                    }
+                                    Err(response) => response,
+                                }
+                            }
+                            Request::Tail { ref programs } => {
+                                let manager = self.shared_process_manager.read().unwrap();
+                                let outputs: Result<Vec<_>, Response> = programs
+                                    .iter()
+                                    .map(|program| {
+                                        manager.attach(program).map(|output| (program.to_owned(), output))
+                                    })
+                                    .collect();
+                                drop(manager);
+                                match outputs {
+                                    Ok(outputs) => {
+                                        let label = programs.join(", ");
+                                        attached = Some(Attachment {
+                                            label: label.clone(),
+                                            on_lag: LagPolicy::Drop,
+                                            merged: outputs.len() > 1,
+                                            outputs,
+                                        });
+                                        Response::Success(format!("Following {label}"))
+                                    }
+                                    Err(response) => response,
                                 }
-                                Err(e) => Response::Error(e.to_string()),
                             }
+                            Request::Detach => match attached.take() {
+                                Some(mut attachment) => {
+                                    // the lines already written by the programs are delivered
+                                    // before the end of the stream
+                                    push_available(&writer_queue, &mut attachment, Vec::new()).await;
+                                    let label = attachment.label;
+                                    push(&writer_queue, Response::StreamEnd(label.clone())).await;
+                                    Response::Success(format!("Detached from {label}"))
+                                }
+                                None => Response::Error("not attached to a program".to_owned()),
+                            },
+                            _ => self.process(&message),
+                        };
+                        STATS.record_request(message.name(), handling_start.elapsed());
+                        let outcome = outcome(&response);
+                        if response.is_error() {
+                            log_warn!(shared_logger, "{self}: {message:?} -> {outcome}")
+                        } else {
+                            log_info!(shared_logger, "{self}: {message:?} -> {outcome}")
                         }
-                    };
-                    if let Err(error) = send(&mut socket, &response).await {
-                        log_error!(shared_logger, "{}", error);
+                        let response = Identified {
+                            id,
+                            message: response,
+                        };
+                        // an error means that the writer task gave up on the client, it told why
+                        let _ = writer_queue.send(response).await;
                     }
-                }
-                Err(error) => {
-                    // if the error occurred because the client disconnected then the task of this thread is finished
-                    if error.client_disconnected() {
-                        log_info!(shared_logger, "Client Disconnected");
+                    // if the client disconnected then the task of this thread is finished
+                    Some(Err(error)) if !error.client_disconnected() => {
+                        log_error!(shared_logger, "{self}: {error}");
+                    }
+                    Some(Err(_)) | None => {
+                        log_info!(shared_logger, "{self}: disconnected");
+                        STATS.record_disconnection(self.id);
                         return;
-                    } else {
-                        log_error!(shared_logger, "{error}");
                     }
+                },
+                event = next_event(&mut events) => match event {
+                    Ok(event) => push(&writer_queue, Response::Event(event)).await,
+                    Err(RecvError::Lagged(missed)) => {
+                        log_warn!(shared_logger, "{self}: too slow, {missed} events were dropped");
+                    }
+                    Err(RecvError::Closed) => events = None,
+                },
+                (index, line) = next_output(&mut attached) => match (line, attached.as_mut()) {
+                    (_, None) => unreachable!("no output is received while not attached"),
+                    (Ok(line), Some(attachment)) => {
+                        let line = attachment.tag(index, line);
+                        // the lines the other programs wrote meanwhile are interleaved with it
+                        push_available(&writer_queue, attachment, vec![line]).await;
+                    }
+                    (Err(RecvError::Lagged(missed)), Some(attachment)) => {
+                        log_warn!(shared_logger, "{self}: too slow, {missed} output lines were dropped");
+                        push(&writer_queue, Response::OutputSkipped(missed)).await;
+                        if attachment.on_lag == LagPolicy::Disconnect {
+                            if let Some(Attachment { label, .. }) = attached.take() {
+                                push(&writer_queue, Response::StreamEnd(label)).await;
+                            }
+                        }
+                    }
+                    // the program was removed by a reload, the stream ends with the last one
+                    (Err(RecvError::Closed), Some(attachment)) => {
+                        attachment.outputs.remove(index);
+                        if attachment.outputs.is_empty() {
+                            if let Some(Attachment { label, .. }) = attached.take() {
+                                push(&writer_queue, Response::StreamEnd(label)).await;
+                            }
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// do the actual match of the client request
+    fn process(&self, request: &Request) -> Response {
+        use Request as R;
+        let shared_logger = &self.shared_logger;
+        let shared_process_manager = &self.shared_process_manager;
+        match request {
+            R::Status(query) => self.warn_if_config_changed(
+                shared_process_manager
+                    .write()
+                    .expect("Can't acquire process manager")
+                    .get_status(query),
+            ),
+            R::Start(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("start by {self}"),
+                shared_logger,
+                ProgramManager::start_program,
+            ),
+            R::Stop(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("stop by {self}"),
+                shared_logger,
+                ProgramManager::stop_program,
+            ),
+            R::Restart(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("restart by {self}"),
+                shared_logger,
+                ProgramManager::restart_program,
+            ),
+            R::RollingRestart(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("rolling restart by {self}"),
+                shared_logger,
+                ProgramManager::rolling_restart_program,
+            ),
+            R::CascadeRestart(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("cascade restart by {self}"),
+                shared_logger,
+                ProgramManager::cascade_restart_program,
+            ),
+            R::Run { program, duration } => shared_process_manager.write().unwrap().order(
+                program,
+                &format!("run by {self}"),
+                shared_logger,
+                |manager, name, logger| manager.run_program(name, *duration, logger),
+            ),
+            R::Reload => match reload_config(
+                &self.shared_config,
+                shared_logger,
+                shared_process_manager,
+                &self.shared_event_bus,
+            ) {
+                Ok(()) => Response::Success("Config Reload Successful".to_owned()),
+                // the previous config keeps running while the file on disk differs
+                Err(e) => self.warn_if_config_changed(Response::Error(e.to_string())),
+            },
+            R::Reexec => {
+                match check_reexec(&self.shared_config, shared_process_manager)
+                    .and_then(|_| request_reexec())
+                {
+                    Ok(()) => {
+                        log_info!(shared_logger, "{self}: reexec requested");
+                        Response::Success(
+                            "The daemon is re-executing, the connection will be closed".to_owned(),
+                        )
+                    }
+                    Err(e) => Response::Error(format!("Can't reexec: {e}")),
                 }
-            };
+            }
+            R::LogLevel(Some(level)) => {
+                shared_logger.set_level(*level);
+                log_info!(shared_logger, "{self}: log level set to {level}");
+                Response::Success(format!("Log level set to {level}"))
+            }
+            R::LogLevel(None) => {
+                Response::Success(format!("Log level is {}", shared_logger.level()))
+            }
+            R::ReloadProgram(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("reload by {self}"),
+                shared_logger,
+                ProgramManager::reload_program,
+            ),
+            R::Pause(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("pause by {self}"),
+                shared_logger,
+                ProgramManager::pause_program,
+            ),
+            R::Resume(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("resume by {self}"),
+                shared_logger,
+                ProgramManager::resume_program,
+            ),
+            R::Maintenance(target, enabled) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("maintenance by {self}"),
+                shared_logger,
+                |manager, name, logger| manager.set_maintenance(name, *enabled, logger),
+            ),
+            R::PauseRestarts { program, duration } => {
+                shared_process_manager.write().unwrap().order(
+                    program,
+                    &format!("pause restarts by {self}"),
+                    shared_logger,
+                    |manager, name, logger| {
+                        manager.set_restarts_paused(name, true, *duration, logger)
+                    },
+                )
+            }
+            R::ResumeRestarts(target) => shared_process_manager.write().unwrap().order(
+                target,
+                &format!("resume restarts by {self}"),
+                shared_logger,
+                |manager, name, logger| manager.set_restarts_paused(name, false, None, logger),
+            ),
+            R::Stats => Response::Stats(Box::new(STATS.snapshot())),
+            R::Version => Response::Version(VersionInfo::current(
+                self.shared_config
+                    .read()
+                    .unwrap()
+                    .path
+                    .display()
+                    .to_string(),
+            )),
+            R::History { program, lines } => shared_process_manager
+                .read()
+                .unwrap()
+                .history(program, *lines),
+            R::Grep { program, pattern } => shared_process_manager
+                .read()
+                .unwrap()
+                .grep(program, pattern),
+            R::Stdin { program, data } => {
+                shared_process_manager
+                    .write()
+                    .unwrap()
+                    .write_stdin(program, data, shared_logger)
+            }
+            R::Subscribe | R::Attach { .. } | R::Tail { .. } | R::Detach => {
+                unreachable!("the subscriptions are handled with the connection")
+            }
+        }
+    }
+
+    /// warn along the response that the config file changed since it was loaded, so the
+    /// operator isn't surprised that the daemon doesn't run what the file says
+    fn warn_if_config_changed(&self, response: Response) -> Response {
+        if !self.shared_config.read().unwrap().changed_on_disk() {
+            return response;
+        }
+        Response::Warned {
+            warning: CONFIG_CHANGED_WARNING.to_owned(),
+            response: Box::new(response),
+        }
+    }
+}
+
+/// summarize a response for the log, the details are for the client
+fn outcome(response: &Response) -> String {
+    match response {
+        Response::Warned { response, .. } => outcome(response),
+        Response::Success(_) => "Success".to_owned(),
+        Response::Status(programs) => format!("Status of {} programs", programs.len()),
+        Response::Error(error) => format!("Error: {error}"),
+        Response::Event(event) => format!("Event: {event}"),
+        Response::Stats(_) => "Stats".to_owned(),
+        Response::Version(_) => "Version".to_owned(),
+        Response::History(lines) => format!("History ({} lines)", lines.len()),
+        Response::Matches(lines) => format!("Matches ({} lines)", lines.len()),
+        Response::Output(_) => "Output".to_owned(),
+        Response::OutputSkipped(missed) => format!("Skipped {missed} output lines"),
+        Response::StreamEnd(program) => format!("End of the output of {program}"),
+        Response::OrderFailed(report) => format!("Error: {}", report.summary()),
+    }
+}
+
+/// queue a message the client didn't ask for (e.g. an event or an output line) to be written
+async fn push(writer_queue: &mpsc::Sender<Identified<Response>>, message: Response) {
+    let message = Identified {
+        id: UNSOLICITED_ID,
+        message,
+    };
+    // an error means that the writer task gave up on the client, it told why
+    let _ = writer_queue.send(message).await;
+}
+
+/// wait for the next event if subscribed to them, for ever otherwise. this is cancel safe
+async fn next_event(events: &mut Option<broadcast::Receiver<Event>>) -> Result<Event, RecvError> {
+    match events {
+        Some(events) => events.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// wait for the next output line of the attached programs along with the index of the one
+/// that wrote it, for ever if not attached. this is cancel safe
+async fn next_output(attached: &mut Option<Attachment>) -> (usize, Result<OutputLine, RecvError>) {
+    let Some(attachment) = attached else {
+        return std::future::pending().await;
+    };
+    let mut receptions: Vec<_> = attachment
+        .outputs
+        .iter_mut()
+        .map(|(_, output)| Box::pin(output.recv()))
+        .collect();
+    std::future::poll_fn(|context| {
+        receptions
+            .iter_mut()
+            .enumerate()
+            .find_map(
+                |(index, reception)| match reception.as_mut().poll(context) {
+                    Poll::Ready(result) => Some((index, result)),
+                    Poll::Pending => None,
+                },
+            )
+            .map_or(Poll::Pending, Poll::Ready)
+    })
+    .await
+}
+
+/// push the given lines along with the ones the attached programs already wrote, in the
+/// order they were written
+async fn push_available(
+    writer_queue: &mpsc::Sender<Identified<Response>>,
+    attachment: &mut Attachment,
+    mut lines: Vec<OutputLine>,
+) {
+    let mut missed = 0;
+    for index in 0..attachment.outputs.len() {
+        loop {
+            match attachment.outputs[index].1.try_recv() {
+                Ok(line) => lines.push(attachment.tag(index, line)),
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => missed += skipped,
+                Err(_) => break,
+            }
         }
     }
+    if missed > 0 {
+        push(writer_queue, Response::OutputSkipped(missed)).await;
+    }
+    // the times are RFC3339 in UTC with a fixed precision, they sort as strings
+    lines.sort_by(|left, right| left.time.cmp(&right.time));
+    for line in lines {
+        push(writer_queue, Response::Output(line)).await;
+    }
+}
+
+impl Attachment {
+    /// tag a line with the program that wrote it if the feed is merged
+    fn tag(&self, index: usize, mut line: OutputLine) -> OutputLine {
+        if self.merged {
+            line.program = Some(self.outputs[index].0.to_owned());
+        }
+        line
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl Display for ClientHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "client#{} {}", self.id, self.address.ip())
+    }
 }
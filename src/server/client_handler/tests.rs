@@ -0,0 +1,188 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::ClientHandler;
+use crate::{
+    config::{new_shared_config, ServerConfig},
+    event::new_shared_event_bus,
+    logger::new_shared_logger,
+    process_manager::{manager::new_shared_process_manager, SharedProcessManager},
+};
+use std::{fs, path::PathBuf, time::Duration};
+use tcl::{
+    message::{receive, send, Identified, LagPolicy, Request, Response, StatusQuery},
+    mylibc,
+};
+use tokio::{
+    io::{duplex, DuplexStream},
+    time::timeout,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the size of the in-memory pipe between the client and the daemon, small enough for the
+/// messages to be written in several parts
+const PIPE_SIZE: usize = 256;
+
+/// how long a test waits for the daemon before failing instead of hanging
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// a program that is never started, its output is written by the tests
+const SLEEPER: &str = "sleeper:\n  cmd: sleep 1000\n  autostart: false\n";
+
+/* -------------------------------------------------------------------------- */
+/*                                   Helper                                   */
+/* -------------------------------------------------------------------------- */
+/// a daemon serving a single client through an in-memory pipe, no port is opened and no
+/// program is started (autostart is false)
+struct Harness {
+    client: DuplexStream,
+    process_manager: SharedProcessManager,
+    next_id: u64,
+}
+
+impl Harness {
+    /// a daemon running the programs of the yaml, its files are named after the test
+    fn new(name: &str, programs: &str) -> Self {
+        let path = |extension: &str| -> PathBuf {
+            std::env::temp_dir().join(format!(
+                "taskmasterd-{}-{name}.{extension}",
+                mylibc::getpid()
+            ))
+        };
+        let server = ServerConfig {
+            log_file: path("log").display().to_string(),
+            ..Default::default()
+        };
+        fs::write(
+            path("yaml"),
+            format!(
+                "server:\n  logfile: {}\n  statefile: {}\n{programs}",
+                path("log").display(),
+                path("state").display()
+            ),
+        )
+        .expect("can't write the config");
+        let config = new_shared_config(&path("yaml"), None, None).expect("can't load the config");
+        let logger = new_shared_logger(&server, false).expect("can't create the logger");
+        let event_bus = new_shared_event_bus(&server);
+        let process_manager =
+            new_shared_process_manager(&config.read().unwrap(), event_bus.clone());
+
+        let (client, daemon) = duplex(PIPE_SIZE);
+        let handler = ClientHandler::new(
+            1,
+            "127.0.0.1:4242".parse().unwrap(),
+            logger,
+            config,
+            process_manager.clone(),
+            event_bus,
+        );
+        tokio::spawn(handler.handle_client(daemon));
+        Self {
+            client,
+            process_manager,
+            next_id: 0,
+        }
+    }
+
+    /// send the request and return the response to it
+    async fn request(&mut self, request: Request) -> Response {
+        self.next_id += 1;
+        let id = self.next_id;
+        send(
+            &mut self.client,
+            &Identified {
+                id,
+                message: request,
+            },
+        )
+        .await
+        .expect("can't send the request");
+        let response = self.next_message().await;
+        assert_eq!(response.id, id, "{:?} isn't the response", response.message);
+        response.message
+    }
+
+    /// the next message of the daemon, a response or a message it sent on its own
+    async fn next_message(&mut self) -> Identified<Response> {
+        timeout(RESPONSE_TIMEOUT, receive(&mut self.client))
+            .await
+            .expect("the daemon didn't answer")
+            .expect("can't receive the message")
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[tokio::test]
+async fn status_lists_the_programs() {
+    let mut harness = Harness::new("status", SLEEPER);
+
+    match harness
+        .request(Request::Status(StatusQuery::default()))
+        .await
+    {
+        Response::Status(programs) => {
+            assert_eq!(programs.len(), 1);
+            assert_eq!(programs[0].name, "sleeper");
+        }
+        response => panic!("unexpected response {response:?}"),
+    }
+}
+
+#[tokio::test]
+async fn order_to_an_unknown_program_is_an_error() {
+    let mut harness = Harness::new("unknown", SLEEPER);
+
+    let response = harness.request(Request::Start("sleepr".to_owned())).await;
+    assert!(response.is_error(), "{response:?}");
+}
+
+#[tokio::test]
+async fn attached_client_gets_the_output_until_it_detaches() {
+    let mut harness = Harness::new("attach", SLEEPER);
+
+    let response = harness
+        .request(Request::Attach {
+            program: "sleeper".to_owned(),
+            on_lag: LagPolicy::Drop,
+        })
+        .await;
+    assert!(matches!(response, Response::Success(_)), "{response:?}");
+
+    let written = ["first line\n", "second line\n"];
+    for line in written {
+        assert!(harness
+            .process_manager
+            .read()
+            .unwrap()
+            .write_output("sleeper", line));
+    }
+    for line in written {
+        match harness.next_message().await.message {
+            Response::Output(output) => assert_eq!(output.line, line.trim_end()),
+            message => panic!("unexpected message {message:?}"),
+        }
+    }
+
+    // the end of the stream comes before the response to the detach
+    harness.next_id += 1;
+    send(
+        &mut harness.client,
+        &Identified {
+            id: harness.next_id,
+            message: Request::Detach,
+        },
+    )
+    .await
+    .unwrap();
+    let end = harness.next_message().await;
+    assert!(matches!(end.message, Response::StreamEnd(ref label) if label == "sleeper"));
+    let detached = harness.next_message().await;
+    assert_eq!(detached.id, harness.next_id);
+    assert!(matches!(detached.message, Response::Success(_)));
+}
@@ -2,19 +2,47 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
-use serde::de::{self, Unexpected};
+use serde::de::{self, MapAccess, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
-use std::ffi::CStr;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::ops::{Deref, DerefMut};
+use std::os::unix::fs::PermissionsExt;
 use std::sync::{Arc, RwLock};
-use std::{fs, path::Path};
-use tcl::error::TaskmasterError;
+use std::time::{Duration, SystemTime};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+use tcl::{error::TaskmasterError, message::LogLevel, mylibc};
 
 /* -------------------------------------------------------------------------- */
 /*                                  Constants                                 */
 /* -------------------------------------------------------------------------- */
-const CONFIG_FILE_PATH: &str = "./config.yaml";
+/// the config file used when none is given on the command line
+pub(super) const DEFAULT_CONFIG_FILE: &str = "./config.yaml";
+
+/// the top level key reserved for the daemon settings, every other key is a program
+const SERVER_SECTION: &str = "server";
+
+/// the parts of the env names whose values are masked without being listed in sensitiveenv
+const SENSITIVE_ENV_PATTERNS: [&str; 7] = [
+    "PASSWORD",
+    "PASSWD",
+    "SECRET",
+    "TOKEN",
+    "API_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
+
+/// what is shown instead of a sensitive value
+const MASK: &str = "********";
+
+/// the top level key reserved for the profiles, each one is merged over the rest of the
+/// config when it is selected
+const PROFILES_SECTION: &str = "profiles";
 
 /* -------------------------------------------------------------------------- */
 /*                                   Struct                                   */
@@ -22,8 +50,129 @@ const CONFIG_FILE_PATH: &str = "./config.yaml";
 pub(super) type SharedConfig = Arc<RwLock<Config>>;
 
 /// struct representing the process the server should monitor
-#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
-pub struct Config(#[serde(default)] HashMap<String, ProgramConfig>);
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// the file the config was loaded from, it is reloaded from there
+    pub(super) path: PathBuf,
+
+    /// the profile merged over the config, it is merged again on reload
+    pub(super) profile: Option<String>,
+
+    /// the settings of the daemon itself
+    pub(super) server: ServerConfig,
+
+    /// the level given on the command line, it replaces the loglevel on reload too
+    pub(super) forced_log_level: Option<LogLevel>,
+
+    /// the programs to monitor, indexed by their name
+    programs: HashMap<String, ProgramConfig>,
+
+    /// the programs whose definition is invalid along with why, they are left out when
+    /// the server oninvalidprogram policy is skip
+    pub(super) invalid_programs: BTreeMap<String, String>,
+
+    /// what the file looked like when the config was loaded from it
+    fingerprint: FileFingerprint,
+}
+
+/// identify the content of the config file, to notice that it changed since it was loaded
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FileFingerprint {
+    modified: Option<SystemTime>,
+    hash: u64,
+}
+
+/// represent the settings of the daemon, found under the `server` key
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// The file the daemon log is written to, its directory is created if needed
+    #[serde(rename = "logfile")]
+    pub(super) log_file: String,
+
+    /// The minimum level of the entries written to the daemon log
+    #[serde(rename = "loglevel")]
+    pub(super) log_level: LogLevel,
+
+    /// The size in bytes above which the daemon log is rotated, 0 to never rotate
+    #[serde(rename = "logfile_maxbytes")]
+    pub(super) log_max_bytes: u64,
+
+    /// How many rotated daemon logs are kept
+    #[serde(rename = "logfile_backups")]
+    pub(super) log_backups: u32,
+
+    /// Whether the daemon log entries are written as text or json lines
+    #[serde(rename = "logformat")]
+    pub(super) log_format: LogFormat,
+
+    /// Whether the daemon log entries are written to the log file or sent to syslog
+    #[serde(rename = "logbackend")]
+    pub(super) log_backend: LogBackend,
+
+    /// The socket of the local syslog daemon, used by the syslog backend
+    pub(super) syslog_socket: String,
+
+    /// An optional address the daemon events are posted to as json
+    pub(super) event_webhook: Option<String>,
+
+    /// The file the daemon state is saved to (the programs stopped by an operator),
+    /// its directory is created if needed
+    #[serde(rename = "statefile")]
+    pub(super) state_file: String,
+
+    /// An optional cgroup v2 directory (e.g. /sys/fs/cgroup/taskmaster) under which each
+    /// program get its own cgroup, applying its cpumax and memorymax
+    pub(super) cgroup_parent: Option<String>,
+
+    /// The time in milliseconds the monitor wait between two checks of the processes,
+    /// it is also woken up when an order is given or a child exits
+    #[serde(rename = "refresh_period_ms")]
+    pub(super) refresh_period_ms: u64,
+
+    /// What the daemon does at startup when a program definition is invalid
+    #[serde(rename = "oninvalidprogram")]
+    pub(super) on_invalid_program: InvalidProgramPolicy,
+}
+
+/// what the daemon does at startup with a config defining invalid programs
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidProgramPolicy {
+    /// refuse to start
+    #[default]
+    #[serde(rename = "abort")]
+    Abort,
+
+    /// start the valid programs, the invalid ones are shown in error by the status
+    #[serde(rename = "skip")]
+    Skip,
+}
+
+/// represent how the daemon log entries are written
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `[timestamp] LEVEL - message`
+    #[default]
+    #[serde(rename = "text")]
+    Text,
+
+    /// one json object per line, for log aggregators
+    #[serde(rename = "json")]
+    Json,
+}
+
+/// represent where the daemon log entries are written
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogBackend {
+    /// the daemon log file, rotated by size
+    #[default]
+    #[serde(rename = "file")]
+    File,
+
+    /// the local syslog socket (also served by journald), using the daemon facility
+    #[serde(rename = "syslog")]
+    Syslog,
+}
 
 /// represent all configuration of a monitored program
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
@@ -33,10 +182,33 @@ pub struct ProgramConfig {
     #[serde(rename = "cmd", default)]
     pub(super) command: String,
 
+    /// The command split into the executable and its arguments, when the config is loaded
+    #[serde(skip)]
+    pub(super) argv: Vec<String>,
+
+    /// The absolute path of the executable found in the PATH when the config is loaded,
+    /// None if it is given as a path or wasn't found
+    #[serde(skip)]
+    pub(super) executable: Option<PathBuf>,
+
     /// The number of processes to start and keep running
     #[serde(rename = "numprocs", default)]
     pub(super) number_of_process: usize,
 
+    /// The number of processes that must be running for the program to be healthy,
+    /// every process by default
+    #[serde(rename = "minrunning", default)]
+    pub(super) min_running: Option<usize>,
+
+    /// The number of processes spawned at the same time, one after the other by default
+    #[serde(rename = "spawnconcurrency", default = "default_spawn_concurrency")]
+    pub(super) spawn_concurrency: usize,
+
+    /// The order of the program relative to the others: the lower ones are started first
+    /// and the higher ones stopped first when the daemon shuts down
+    #[serde(rename = "priority", default = "default_priority")]
+    pub(super) priority: u32,
+
     /// Whether to start this program at launch or not
     #[serde(rename = "autostart", default)]
     pub(super) start_at_launch: bool,
@@ -49,10 +221,22 @@ pub struct ProgramConfig {
     #[serde(rename = "exitcodes", default = "default_exit_code")]
     pub(super) expected_exit_code: Vec<i32>,
 
+    /// Which signals terminating the program represent an "expected" exit status, e.g. a
+    /// service shutting down on SIGTERM. None by default
+    #[serde(rename = "expected_signals", default)]
+    pub(super) expected_signals: Vec<Signal>,
+
     /// How long the program should be running after it’s started for it to be considered "successfully started"
     #[serde(rename = "starttime", default)]
     pub(super) time_to_start: u64,
 
+    /// How the program tells it is ready, it is running as soon as it does whatever the
+    /// starttime, which is then the time it is given to be ready (forever if 0): it creates
+    /// a file (a path), writes to or closes a pipe it inherits (`fd:3`), or sends READY=1 to
+    /// the socket in its NOTIFY_SOCKET like systemd services do (`notify`, also `startup: notify`)
+    #[serde(rename = "readiness", alias = "startup", default)]
+    pub(super) readiness: Option<Readiness>,
+
     /// How many times a restart should be attempted before aborting
     #[serde(rename = "startretries", default)]
     pub(super) max_number_of_restart: u32,
@@ -61,29 +245,69 @@ pub struct ProgramConfig {
     #[serde(rename = "stopsignal", default)]
     pub(super) stop_signal: Signal,
 
+    /// The signal sent by the reload-program order, for a program that reloads its own
+    /// config without being restarted (e.g. SIGHUP)
+    #[serde(rename = "reload_signal", default)]
+    pub(super) reload_signal: Option<Signal>,
+
     /// How long to wait after a graceful stop before killing the program
     #[serde(rename = "stoptime", default = "default_graceful_shutdown")]
     pub(super) time_to_stop_gracefully: u64,
 
-    /// Optional stdout redirection
+    /// Optional stdout redirection, a file path or NONE, INHERIT, SYSLOG. the path may
+    /// contain %(program_name)s and %(process_num)d (zero padded as %(process_num)02d)
     #[serde(rename = "stdout")]
-    pub(super) stdout_redirection: Option<String>,
+    pub(super) stdout_redirection: Option<Redirection>,
 
-    /// Optional stderr redirection
+    /// Optional stderr redirection, a file path or NONE, INHERIT, SYSLOG, templated as stdout
     #[serde(rename = "stderr")]
-    pub(super) stderr_redirection: Option<String>,
+    pub(super) stderr_redirection: Option<Redirection>,
+
+    /// The mode of the missing directories of the redirection files created at spawn time
+    /// (0755 by default)
+    #[serde(rename = "outputdirmode", deserialize_with = "parse_mode", default)]
+    pub(super) output_dir_mode: Option<mylibc::mode_t>,
+
+    /// The user owning the missing directories of the redirection files created at spawn
+    /// time, the daemon one by default
+    #[serde(rename = "outputdirowner", default, deserialize_with = "parse_user")]
+    pub(super) output_dir_owner: Option<User>,
+
+    /// The mode the redirection files are created with (under the umask of the program),
+    /// an existing file is given it when opened. the umask decides by default
+    #[serde(rename = "logfile_mode", deserialize_with = "parse_mode", default)]
+    pub(super) log_file_mode: Option<mylibc::mode_t>,
+
+    /// The user owning the redirection files when they are created, the daemon one by default
+    #[serde(rename = "logfile_owner", default, deserialize_with = "parse_user")]
+    pub(super) log_file_owner: Option<User>,
+
+    /// The socket of the syslog daemon the SYSLOG redirections are sent to, the one of
+    /// the server when the config is loaded
+    #[serde(skip)]
+    pub(super) syslog_socket: Option<PathBuf>,
+
+    /// Whether the stderr of the processes is merged into their stdout, sharing its
+    /// redirection file, history and attached clients
+    #[serde(rename = "redirect_stderr", default)]
+    pub(super) redirect_stderr: bool,
 
     /// Environment variables to set before launching the program
     #[serde(rename = "env")]
-    pub(super) environmental_variable_to_set: HashMap<String, String>,
+    pub(super) environmental_variable_to_set: HashMap<String, EnvValue>,
+
+    /// The env names whose values are masked wherever the config is shown, on top of the
+    /// ones looking like a credential (e.g. DB_PASSWORD, API_TOKEN)
+    #[serde(rename = "sensitiveenv", default)]
+    pub(super) sensitive_env: Vec<String>,
 
     /// A working directory to set before launching the program
     #[serde(rename = "workingdir")]
     pub(super) working_directory: Option<String>,
 
     /// An umask to set before launching the program
-    #[serde(rename = "umask", deserialize_with = "parse_umask", default)]
-    pub(super) umask: Option<libc::mode_t>,
+    #[serde(rename = "umask", deserialize_with = "parse_mode", default)]
+    pub(super) umask: Option<mylibc::mode_t>,
 
     /// Execute the process with a specific user (root required)
     #[serde(rename = "user", default, deserialize_with = "parse_user")]
@@ -91,13 +315,208 @@ pub struct ProgramConfig {
 
     #[serde(default)]
     pub(super) fatal_state_report_address: String,
+
+    /// Labels used to target several programs at once (e.g. `stop tag:batch`)
+    #[serde(default)]
+    pub(super) tags: Vec<String>,
+
+    /// Whether to write the supervision history of the program to programs/<name>.log
+    #[serde(rename = "lifecyclelog", default)]
+    pub(super) lifecycle_log: bool,
+
+    /// The cpu.max of the program cgroup (e.g. "50000 100000" for half a cpu), needs cgroup_parent
+    #[serde(rename = "cpumax")]
+    pub(super) cpu_max: Option<String>,
+
+    /// The memory.max of the program cgroup in bytes, needs cgroup_parent
+    #[serde(rename = "memorymax")]
+    pub(super) memory_max: Option<String>,
+
+    /// The size in bytes the stdout and stderr redirection files shouldn't exceed
+    #[serde(rename = "outputmaxbytes")]
+    pub(super) output_max_bytes: Option<u64>,
+
+    /// The percentage of free space the filesystem of the working directory should keep
+    #[serde(rename = "minfreedisk")]
+    pub(super) min_free_disk: Option<u8>,
+
+    /// What to do when outputmaxbytes or minfreedisk is crossed
+    #[serde(rename = "guardrailaction")]
+    pub(super) guardrail_action: GuardrailAction,
+
+    /// Whether every output line is prefixed with the time it was read (RFC3339), in the
+    /// redirection files and in the history
+    #[serde(rename = "logtimestamps", default)]
+    pub(super) log_timestamps: bool,
+
+    /// The number of stdout lines the daemon keeps in memory for the history command
+    #[serde(rename = "historylines", default = "default_history_lines")]
+    pub(super) history_lines: usize,
+
+    /// The bytes per second of output the daemon takes in from the processes of the program,
+    /// the lines beyond are only written to the redirection files, unlimited by default
+    #[serde(rename = "outputratelimit", default)]
+    pub(super) output_rate_limit: Option<usize>,
+
+    /// Whether a restart stops every process at once or one after the other
+    #[serde(rename = "restartstrategy")]
+    pub(super) restart_strategy: RestartStrategy,
+
+    /// The events the program listens to on its stdin (e.g. ProcessState, or all),
+    /// it then follows the supervisord event listener protocol on its stdout
+    #[serde(default)]
+    pub(super) events: Vec<String>,
+
+    /// The files and directories whose changes restart the program once they settled,
+    /// the directories are watched recursively
+    #[serde(rename = "watchpaths", default)]
+    pub(super) watch_paths: Vec<String>,
+
+    /// Whether the stderr lines are also written to the daemon log at the warn level, a few
+    /// per second at most
+    #[serde(rename = "stderr_to_daemon_log", default)]
+    pub(super) stderr_to_daemon_log: bool,
+
+    /// The time ranges during which the autorestart policy or the alerts of the program are
+    /// changed (e.g. the nightly backup of the database it depends on), in local time
+    #[serde(rename = "maintenance_windows", default)]
+    pub(super) maintenance_windows: Vec<MaintenanceWindow>,
+
+    /// The programs this one depends on, it is restarted after them by a cascade restart
+    #[serde(rename = "depends_on", default)]
+    pub(super) depends_on: Vec<String>,
+}
+
+/// what the monitor does when a program crosses one of its disk guardrails,
+/// an event is published in both cases
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GuardrailAction {
+    /// only warn
+    #[default]
+    #[serde(rename = "warn")]
+    Warn,
+
+    /// stop the program, it has to be started again by hand
+    #[serde(rename = "stop")]
+    Stop,
+}
+
+/// how the processes of a program are restarted
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RestartStrategy {
+    /// every process is restarted at once
+    #[default]
+    #[serde(rename = "all")]
+    All,
+
+    /// the processes are restarted one at a time, each one waiting for the previous one to
+    /// be running, so the program keeps most of its capacity
+    #[serde(rename = "rolling")]
+    Rolling,
+}
+
+/// a weekly time range during which the supervision of a program is changed, the monitor
+/// applies it as long as the local time is in it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    /// the days the window opens on, every day if none is given
+    #[serde(default)]
+    pub(super) days: Vec<Weekday>,
+
+    pub(super) from: TimeOfDay,
+
+    /// the window closes the next day if it is not after the opening, a window from and to
+    /// the same time lasts the whole day
+    pub(super) to: TimeOfDay,
+
+    /// the autorestart policy during the window, the one of the program if not given
+    #[serde(default)]
+    pub(super) autorestart: Option<AutoRestart>,
+
+    /// whether the fatal state is reported (fatalstatereportaddress) during the window
+    #[serde(default = "default_alerts")]
+    pub(super) alerts: bool,
+}
+
+/// a day of the week, numbered from sunday like the libc does
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    #[serde(rename = "sun")]
+    Sunday = 0,
+    #[serde(rename = "mon")]
+    Monday,
+    #[serde(rename = "tue")]
+    Tuesday,
+    #[serde(rename = "wed")]
+    Wednesday,
+    #[serde(rename = "thu")]
+    Thursday,
+    #[serde(rename = "fri")]
+    Friday,
+    #[serde(rename = "sat")]
+    Saturday,
+}
+
+/// how a program tells the daemon its processes are ready
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Readiness {
+    /// the file at the path is created, it is removed before each spawn. its
+    /// %(program_name)s and %(process_num)d are expanded like the ones of the redirections
+    File(String),
+
+    /// the pipe the child inherits as the file descriptor (`fd:N`) is written to or closed
+    Fd(i32),
+
+    /// READY=1 is sent to the datagram socket found in NOTIFY_SOCKET (sd_notify)
+    Notify,
+}
+
+/// a time of the day written `HH:MM`, kept as the minutes since midnight
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(u32);
+
+/// where an output stream of the processes of a program goes, it is captured by the daemon
+/// for the history and the attached clients unless it is discarded or inherited
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(from = "String", into = "String")]
+pub enum Redirection {
+    /// appended to the file at the path
+    File(String),
+
+    /// thrown away (NONE)
+    Discard,
+
+    /// written to the descriptor of the daemon itself (INHERIT), for a daemon in the foreground
+    Inherit,
+
+    /// sent line by line to syslog (SYSLOG)
+    Syslog,
+}
+
+/// the value of an environment variable of a program, a secret is only read when a process
+/// is spawned so it is never part of the config, which is logged
+#[derive(Clone, PartialEq, Eq)]
+pub enum EnvValue {
+    /// the value written in the config
+    Plain(String),
+
+    /// the value written in the config, masked wherever the config is shown
+    Sensitive(String),
+
+    /// `!file PATH`, the content of the file without its trailing newline
+    File(PathBuf),
+
+    /// `!env NAME`, the value of a variable of the daemon environment
+    Env(String),
 }
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct User {
     pub username: String,
-    pub uid: libc::uid_t,
-    pub gid: libc::gid_t,
+    pub uid: mylibc::uid_t,
+    pub gid: mylibc::gid_t,
 }
 
 /// this enum represent whenever a program should be auto restart if it's termination
@@ -158,37 +577,370 @@ pub enum Signal {
 /* -------------------------------------------------------------------------- */
 
 impl Config {
-    /// create a config base on the file located in the root of the project
-    pub fn load() -> Result<Self, TaskmasterError> {
-        let path = Path::new(CONFIG_FILE_PATH);
+    /// create a config from the given file, with the given profile of the file merged over it
+    pub fn load(path: &Path, profile: Option<&str>) -> Result<Self, TaskmasterError> {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
         let contents = fs::read_to_string(path)?;
-        let config: Config = serde_yaml::from_str(&contents)?;
+        let mut value: serde_yaml::Value = serde_yaml::from_str(&contents)?;
+        let profiles = value
+            .as_mapping_mut()
+            .and_then(|sections| sections.remove(PROFILES_SECTION));
+        if let Some(profile) = profile {
+            let overlay = profiles
+                .and_then(|mut profiles| profiles.get_mut(profile).map(std::mem::take))
+                .ok_or_else(|| {
+                    TaskmasterError::Custom(format!("the profile `{profile}` is not defined"))
+                })?;
+            merge(&mut value, overlay);
+        }
+        let mut config: Config = serde_yaml::from_value(value)?;
+        config.check_dependencies()?;
+        let syslog_socket = PathBuf::from(&config.server.syslog_socket);
+        config
+            .programs
+            .values_mut()
+            .filter(|program| program.redirections().contains(&&Redirection::Syslog))
+            .for_each(|program| program.syslog_socket = Some(syslog_socket.clone()));
+        config.path = path.to_owned();
+        config.profile = profile.map(str::to_owned);
+        config.fingerprint = FileFingerprint {
+            modified: modified.ok(),
+            hash: hash_of(&contents),
+        };
         Ok(config)
     }
+
+    /// log from the given level whatever the loglevel of the file, if there is one
+    pub(super) fn force_log_level(mut self, level: Option<LogLevel>) -> Self {
+        if let Some(level) = level {
+            self.server.log_level = level;
+        }
+        self.forced_log_level = level;
+        self
+    }
+
+    /// reject a config defining invalid programs whatever the oninvalidprogram policy, a
+    /// reload mustn't drop a program that runs because of a typo
+    pub(super) fn reject_invalid_programs(self) -> Result<Self, TaskmasterError> {
+        match self.invalid_programs.iter().next() {
+            Some((name, error)) => Err(TaskmasterError::Custom(format!(
+                "the program `{name}` is invalid: {error}"
+            ))),
+            None => Ok(self),
+        }
+    }
+
+    /// check that the programs depend on programs that are defined, without depending on
+    /// themselves through the others
+    fn check_dependencies(&self) -> Result<(), TaskmasterError> {
+        let mut names: Vec<&String> = self.programs.keys().collect();
+        names.sort();
+        for name in &names {
+            let undefined = self.programs[*name].depends_on.iter().find(|dependency| {
+                !self.programs.contains_key(*dependency)
+                    && !self.invalid_programs.contains_key(*dependency)
+            });
+            if let Some(undefined) = undefined {
+                return Err(TaskmasterError::Custom(format!(
+                    "the program `{name}` depends on `{undefined}` which is not defined"
+                )));
+            }
+        }
+        for name in names {
+            if let Some(cycle) = self.dependency_cycle(&mut vec![name.to_owned()]) {
+                return Err(TaskmasterError::Custom(format!(
+                    "the programs depend on each other: {}",
+                    cycle.join(" -> ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// follow the dependencies from the last program of the path, returning the first cycle
+    /// found
+    fn dependency_cycle(&self, path: &mut Vec<String>) -> Option<Vec<String>> {
+        let last = path.last()?;
+        let dependencies = self.programs.get(last)?.depends_on.to_owned();
+        for dependency in dependencies {
+            if let Some(start) = path.iter().position(|name| *name == dependency) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(dependency);
+                return Some(cycle);
+            }
+            path.push(dependency);
+            if let Some(cycle) = self.dependency_cycle(path) {
+                return Some(cycle);
+            }
+            path.pop();
+        }
+        None
+    }
+
+    /// tell if the file changed since the config was loaded from it, a file that can no
+    /// longer be read counts as changed. the content is only compared if it was modified
+    pub(super) fn changed_on_disk(&self) -> bool {
+        let modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified());
+        if modified.is_ok_and(|modified| Some(modified) == self.fingerprint.modified) {
+            return false;
+        }
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => hash_of(&contents) != self.fingerprint.hash,
+            Err(_) => true,
+        }
+    }
 }
 
-pub(super) fn new_shared_config() -> Result<SharedConfig, TaskmasterError> {
-    Ok(Arc::new(RwLock::new(Config::load()?)))
+impl ProgramConfig {
+    /// tell if the processes running with this config can switch to the new one without
+    /// being restarted, i.e. it only changes fields read as they go (autorestart, exitcodes,
+    /// expected_signals, stoptime, historylines, stderr_to_daemon_log, maintenance_windows,
+    /// depends_on, reload_signal) or the numprocs, handled by scaling the program
+    pub(super) fn is_hot_applicable(&self, new: &ProgramConfig) -> bool {
+        let disruptive = ProgramConfig {
+            number_of_process: self.number_of_process,
+            auto_restart: self.auto_restart.to_owned(),
+            expected_exit_code: self.expected_exit_code.to_owned(),
+            expected_signals: self.expected_signals.to_owned(),
+            time_to_stop_gracefully: self.time_to_stop_gracefully,
+            history_lines: self.history_lines,
+            watch_paths: self.watch_paths.to_owned(),
+            stderr_to_daemon_log: self.stderr_to_daemon_log,
+            maintenance_windows: self.maintenance_windows.to_owned(),
+            depends_on: self.depends_on.to_owned(),
+            reload_signal: self.reload_signal.to_owned(),
+            ..new.to_owned()
+        };
+        disruptive == *self
+    }
+
+    /// the maintenance window the local time is in, the first one listed if several overlap
+    pub(super) fn maintenance_window(&self, now: mylibc::LocalTime) -> Option<&MaintenanceWindow> {
+        self.maintenance_windows
+            .iter()
+            .find(|window| window.contains(now))
+    }
+
+    /// the redirections of the stdout and the stderr that are set
+    pub(super) fn redirections(&self) -> Vec<&Redirection> {
+        [&self.stdout_redirection, &self.stderr_redirection]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// create the config of a program from its definition, telling why it is invalid if
+    /// it is
+    fn parse(name: &str, definition: serde_yaml::Value) -> Result<Self, String> {
+        let mut program = ProgramConfig::deserialize(definition).map_err(|e| e.to_string())?;
+        program.check_redirections()?;
+        program.expand_program_name(name);
+        program.resolve_command();
+        program.mask_sensitive_env();
+        Ok(program)
+    }
+
+    /// an event listener speaks the events protocol on its stdout so the daemon has to read
+    /// it, and a stderr merged into the stdout can't have its own redirection nor be mixed
+    /// with that protocol
+    fn check_redirections(&self) -> Result<(), String> {
+        let uncaptured = matches!(
+            self.stdout_redirection,
+            Some(Redirection::Discard | Redirection::Inherit)
+        );
+        if !self.events.is_empty() && uncaptured {
+            return Err("the stdout of an event listener can't be NONE or INHERIT".to_owned());
+        }
+        if !self.redirect_stderr {
+            return Ok(());
+        }
+        if self.stderr_redirection.is_some() {
+            return Err("redirect_stderr and stderr can't be both set".to_owned());
+        }
+        if !self.events.is_empty() {
+            return Err("an event listener can't redirect its stderr".to_owned());
+        }
+        Ok(())
+    }
+
+    /// replace the %(program_name)s of the redirection and readiness paths, the
+    /// %(process_num)d are replaced for each process when it is spawned
+    fn expand_program_name(&mut self, name: &str) {
+        for redirection in [&mut self.stdout_redirection, &mut self.stderr_redirection] {
+            if let Some(Redirection::File(path)) = redirection {
+                *path = path.replace("%(program_name)s", name);
+            }
+        }
+        if let Some(Readiness::File(path)) = &mut self.readiness {
+            *path = path.replace("%(program_name)s", name);
+        }
+    }
+
+    /// split the command and look its executable up in the PATH of the program (set in its
+    /// env or the one of the daemon) once instead of at every start of its processes
+    fn resolve_command(&mut self) {
+        self.argv = self.command.split_whitespace().map(str::to_owned).collect();
+        self.executable = self
+            .argv
+            .first()
+            .and_then(|program| self.find_executable(program));
+    }
+
+    /// mask the plain env values that are listed in sensitiveenv or look like a credential
+    fn mask_sensitive_env(&mut self) {
+        for (name, value) in self.environmental_variable_to_set.iter_mut() {
+            let upper = name.to_ascii_uppercase();
+            let sensitive = self.sensitive_env.contains(name)
+                || SENSITIVE_ENV_PATTERNS
+                    .iter()
+                    .any(|pattern| upper.contains(pattern));
+            if let (true, EnvValue::Plain(plain)) = (sensitive, &mut *value) {
+                *value = EnvValue::Sensitive(std::mem::take(plain));
+            }
+        }
+    }
+
+    fn find_executable(&self, program: &str) -> Option<PathBuf> {
+        // a path is resolved from the working directory of the process
+        if program.contains('/') {
+            return None;
+        }
+        let path = match self.environmental_variable_to_set.get("PATH") {
+            Some(path) => path.resolve().ok()?.into(),
+            None => env::var_os("PATH")?,
+        };
+        env::split_paths(&path)
+            .filter(|directory| directory.is_absolute())
+            .map(|directory| directory.join(program))
+            .find(|candidate| is_executable(candidate))
+    }
+}
+
+impl Redirection {
+    /// the path of the file the given process writes to, its %(process_num)d expanded,
+    /// None if it isn't a file
+    pub(super) fn file_path(&self, replica: usize) -> Option<String> {
+        match self {
+            Redirection::File(template) => Some(expand_process_num(template, replica)),
+            _ => None,
+        }
+    }
+}
+
+impl Readiness {
+    /// the path of the file the given process creates once ready, its %(process_num)d
+    /// expanded, None if it tells it through a pipe or a socket
+    pub(super) fn file_path(&self, replica: usize) -> Option<String> {
+        match self {
+            Readiness::File(template) => Some(expand_process_num(template, replica)),
+            Readiness::Fd(_) | Readiness::Notify => None,
+        }
+    }
+}
+
+impl EnvValue {
+    /// the value given to the processes, a secret is read each time
+    pub(super) fn resolve(&self) -> Result<String, std::io::Error> {
+        match self {
+            EnvValue::Plain(value) | EnvValue::Sensitive(value) => Ok(value.to_owned()),
+            EnvValue::File(path) => {
+                let contents = fs::read_to_string(path).map_err(|e| {
+                    std::io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+                })?;
+                Ok(contents.trim_end_matches(['\n', '\r']).to_owned())
+            }
+            EnvValue::Env(name) => env::var(name).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("`{name}` is not set in the daemon environment"),
+                )
+            }),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// the refresh period of the monitor, never 0 so it can't spin
+    pub(super) fn refresh_period(&self) -> Duration {
+        Duration::from_millis(self.refresh_period_ms.max(1))
+    }
+}
+
+/// merge a profile over the config: the maps are merged key by key, a null removing the key
+/// (e.g. a program), and any other value replaces the one of the config
+fn merge(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    use serde_yaml::Value;
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match (base.get_mut(&key), value) {
+                    (_, Value::Null) => {
+                        base.remove(&key);
+                    }
+                    (Some(base), value) => merge(base, value),
+                    (None, value) => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn hash_of(contents: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub(super) fn new_shared_config(
+    path: &Path,
+    profile: Option<&str>,
+    log_level: Option<LogLevel>,
+) -> Result<SharedConfig, TaskmasterError> {
+    let config = Config::load(path, profile)?.force_log_level(log_level);
+    Ok(Arc::new(RwLock::new(config)))
+}
+
+impl MaintenanceWindow {
+    /// tell if the local time is in the window, a window closing the next day is open in
+    /// the morning after each of its days
+    fn contains(&self, now: mylibc::LocalTime) -> bool {
+        let minute = now.hour * 60 + now.minute;
+        let opens_on = |weekday: u32| {
+            self.days.is_empty() || self.days.iter().any(|day| *day as u32 == weekday)
+        };
+        let yesterday = (now.weekday + 6) % 7;
+        match self.from < self.to {
+            true => opens_on(now.weekday) && self.from.0 <= minute && minute < self.to.0,
+            false => {
+                (opens_on(now.weekday) && minute >= self.from.0)
+                    || (opens_on(yesterday) && minute < self.to.0)
+            }
+        }
+    }
 }
 
 /* -------------------------------------------------------------------------- */
 /*                              Parsing Functions                             */
 /* -------------------------------------------------------------------------- */
-fn parse_umask<'de, D>(deserializer: D) -> Result<Option<libc::mode_t>, D::Error>
+/// parse an octal mode (umask, outputdirmode, logfile_mode)
+fn parse_mode<'de, D>(deserializer: D) -> Result<Option<mylibc::mode_t>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let umask_deserialize = Option::<String>::deserialize(deserializer)?;
-    if let Some(umask_str) = umask_deserialize {
-        if !umask_str.chars().all(|c| ('0'..='7').contains(&c)) {
+    let mode_deserialize = Option::<String>::deserialize(deserializer)?;
+    if let Some(mode_str) = mode_deserialize {
+        if !mode_str.chars().all(|c| ('0'..='7').contains(&c)) {
             return Err(de::Error::invalid_value(
-                Unexpected::Str(&umask_str),
+                Unexpected::Str(&mode_str),
                 &"octal number",
             ));
         }
-        libc::mode_t::from_str_radix(&umask_str, 8)
+        mylibc::mode_t::from_str_radix(&mode_str, 8)
             .map(Some)
-            .map_err(|_| de::Error::custom("invalid umask"))
+            .map_err(|_| de::Error::custom("invalid mode"))
     } else {
         Ok(None)
     }
@@ -200,43 +952,94 @@ where
 {
     let user_deserialize = Option::<String>::deserialize(deserializer)?;
     match user_deserialize {
-        Some(user_str) => {
-            if let Some(user) = get_all_users()
-                .iter()
-                .find(|u| u.username == user_str)
-                .cloned()
-            {
-                Ok(Some(user))
-            } else {
-                Err(de::Error::custom("invalid user"))
-            }
-        }
+        Some(user_str) => match mylibc::getpwnam(&user_str).map_err(de::Error::custom)? {
+            Some(user) => Ok(Some(User {
+                username: user.name,
+                uid: user.uid,
+                gid: user.gid,
+            })),
+            None => Err(de::Error::custom(format!(
+                "the user `{user_str}` doesn't exist"
+            ))),
+        },
         None => Ok(None),
     }
 }
 
-fn get_all_users() -> Vec<User> {
-    let mut users: Vec<User> = Vec::new();
-    unsafe {
-        libc::setpwent();
-        while let Some(user) = libc::getpwent().as_mut() {
-            let username = CStr::from_ptr(user.pw_name);
-            if let Ok(username) = username.to_str() {
-                users.push(User {
-                    username: username.to_owned(),
-                    uid: user.pw_uid,
-                    gid: user.pw_gid,
-                })
-            }
-        }
+/// the daemon log goes to /var/log when run as root, to the XDG state directory otherwise
+fn default_log_file() -> String {
+    const LOG_FILE_NAME: &str = "taskmaster/taskmasterd.log";
+    if mylibc::geteuid() == 0 {
+        return format!("/var/log/{LOG_FILE_NAME}");
+    }
+    match (std::env::var("XDG_STATE_HOME"), std::env::var("HOME")) {
+        (Ok(state_home), _) if !state_home.is_empty() => format!("{state_home}/{LOG_FILE_NAME}"),
+        (_, Ok(home)) if !home.is_empty() => format!("{home}/.local/state/{LOG_FILE_NAME}"),
+        _ => "./taskmasterd.log".to_owned(),
+    }
+}
+
+/// the daemon state goes to /var/lib when run as root, to the XDG state directory otherwise
+fn default_state_file() -> String {
+    const STATE_FILE_NAME: &str = "taskmaster/taskmasterd.state";
+    if mylibc::geteuid() == 0 {
+        return format!("/var/lib/{STATE_FILE_NAME}");
+    }
+    match (std::env::var("XDG_STATE_HOME"), std::env::var("HOME")) {
+        (Ok(state_home), _) if !state_home.is_empty() => format!("{state_home}/{STATE_FILE_NAME}"),
+        (_, Ok(home)) if !home.is_empty() => format!("{home}/.local/state/{STATE_FILE_NAME}"),
+        _ => "./taskmasterd.state".to_owned(),
     }
-    users
 }
 
 fn default_exit_code() -> Vec<i32> {
     vec![0]
 }
 
+/// replace the %(process_num)d of the path by the index of the process, padded with zeros
+/// for a %(process_num)02d
+fn expand_process_num(template: &str, replica: usize) -> String {
+    const PROCESS_NUM: &str = "%(process_num)";
+    let mut path = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(PROCESS_NUM) {
+        path.push_str(&rest[..start]);
+        rest = &rest[start + PROCESS_NUM.len()..];
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        match rest[digits..].strip_prefix('d') {
+            Some(after) => {
+                let width = rest[..digits].parse().unwrap_or(0);
+                path.push_str(&format!("{replica:0width$}"));
+                rest = after;
+            }
+            None => path.push_str(PROCESS_NUM),
+        }
+    }
+    path.push_str(rest);
+    path
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+fn default_alerts() -> bool {
+    true
+}
+
+fn default_history_lines() -> usize {
+    1000
+}
+
+fn default_priority() -> u32 {
+    999
+}
+
+fn default_spawn_concurrency() -> usize {
+    1
+}
+
 fn default_graceful_shutdown() -> u64 {
     1
 }
@@ -244,16 +1047,241 @@ fn default_graceful_shutdown() -> u64 {
 /* -------------------------------------------------------------------------- */
 /*                            Trait Implementation                            */
 /* -------------------------------------------------------------------------- */
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            log_file: default_log_file(),
+            log_level: LogLevel::default(),
+            log_max_bytes: 50 * 1024 * 1024,
+            log_backups: 10,
+            log_format: LogFormat::default(),
+            log_backend: LogBackend::default(),
+            syslog_socket: "/dev/log".to_owned(),
+            event_webhook: None,
+            refresh_period_ms: 1000,
+            state_file: default_state_file(),
+            cgroup_parent: None,
+            on_invalid_program: InvalidProgramPolicy::default(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EnvValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde_yaml::Value;
+        let unexpected = || de::Error::custom("expected a string, `!file PATH` or `!env NAME`");
+        match Value::deserialize(deserializer)? {
+            Value::String(value) => Ok(EnvValue::Plain(value)),
+            Value::Number(value) => Ok(EnvValue::Plain(value.to_string())),
+            Value::Bool(value) => Ok(EnvValue::Plain(value.to_string())),
+            Value::Tagged(tagged) => match (tagged.value, tagged.tag.to_string().as_str()) {
+                (Value::String(path), "!file") => Ok(EnvValue::File(PathBuf::from(path))),
+                (Value::String(name), "!env") => Ok(EnvValue::Env(name)),
+                _ => Err(unexpected()),
+            },
+            _ => Err(unexpected()),
+        }
+    }
+}
+
+impl fmt::Debug for EnvValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvValue::Plain(value) => write!(f, "{value:?}"),
+            EnvValue::Sensitive(_) => write!(f, "{MASK:?}"),
+            EnvValue::File(path) => write!(f, "!file {}", path.display()),
+            EnvValue::Env(name) => write!(f, "!env {name}"),
+        }
+    }
+}
+
+impl From<String> for Redirection {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "NONE" => Redirection::Discard,
+            "INHERIT" => Redirection::Inherit,
+            "SYSLOG" => Redirection::Syslog,
+            _ => Redirection::File(value),
+        }
+    }
+}
+
+/// the readiness pipe can't be given one of the standard streams
+impl TryFrom<String> for Readiness {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value == "notify" {
+            return Ok(Readiness::Notify);
+        }
+        let Some(fd) = value.strip_prefix("fd:") else {
+            return Ok(Readiness::File(value));
+        };
+        match fd.parse::<i32>() {
+            Ok(fd) if fd >= 3 => Ok(Readiness::Fd(fd)),
+            Ok(fd) => Err(format!(
+                "the readiness fd {fd} is a standard stream, it must be 3 or more"
+            )),
+            Err(_) => Err(format!("`{value}` is not a readiness fd (e.g. fd:3)")),
+        }
+    }
+}
+
+impl From<Readiness> for String {
+    fn from(readiness: Readiness) -> Self {
+        match readiness {
+            Readiness::File(path) => path,
+            Readiness::Fd(fd) => format!("fd:{fd}"),
+            Readiness::Notify => "notify".to_owned(),
+        }
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let time = value.split_once(':').and_then(|(hour, minute)| {
+            let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+            (hour < 24 && minute < 60).then_some(TimeOfDay(hour * 60 + minute))
+        });
+        time.ok_or_else(|| format!("`{value}` is not a time of the day (e.g. 02:30)"))
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+impl fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days: Vec<String> = self.days.iter().map(ToString::to_string).collect();
+        if !days.is_empty() {
+            write!(f, "{} ", days.join(","))?;
+        }
+        write!(f, "{}-{}", self.from, self.to)
+    }
+}
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Weekday::Sunday => "sun",
+            Weekday::Monday => "mon",
+            Weekday::Tuesday => "tue",
+            Weekday::Wednesday => "wed",
+            Weekday::Thursday => "thu",
+            Weekday::Friday => "fri",
+            Weekday::Saturday => "sat",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl From<Redirection> for String {
+    fn from(redirection: Redirection) -> Self {
+        match redirection {
+            Redirection::File(path) => path,
+            Redirection::Discard => "NONE".to_owned(),
+            Redirection::Inherit => "INHERIT".to_owned(),
+            Redirection::Syslog => "SYSLOG".to_owned(),
+        }
+    }
+}
+
+impl Serialize for EnvValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde_yaml::value::{Tag, TaggedValue, Value};
+        let tagged = |tag: &str, value: String| TaggedValue {
+            tag: Tag::new(tag),
+            value: Value::String(value),
+        };
+        match self {
+            EnvValue::Plain(value) => serializer.serialize_str(value),
+            EnvValue::Sensitive(_) => serializer.serialize_str(MASK),
+            EnvValue::File(path) => {
+                tagged("file", path.display().to_string()).serialize(serializer)
+            }
+            EnvValue::Env(name) => tagged("env", name.to_owned()).serialize(serializer),
+        }
+    }
+}
+
 impl Deref for Config {
     type Target = HashMap<String, ProgramConfig>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.programs
     }
 }
 
 impl DerefMut for Config {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.programs
+    }
+}
+
+/// the config is a map of programs in which the `server` key is reserved for
+/// the daemon settings, the entries are deserialized one by one straight from
+/// the yaml so scalars keep being accepted as strings (e.g. `umask: 77`)
+impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ConfigVisitor;
+
+        impl<'de> Visitor<'de> for ConfigVisitor {
+            type Value = Config;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of programs and an optional server section")
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Config, E> {
+                Ok(Config::default())
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Config, A::Error> {
+                let mut config = Config::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == SERVER_SECTION {
+                        config.server = map.next_value()?;
+                    } else {
+                        match ProgramConfig::parse(&key, map.next_value()?) {
+                            Ok(program) => {
+                                config.programs.insert(key, program);
+                            }
+                            Err(error) => {
+                                config.invalid_programs.insert(key, error);
+                            }
+                        }
+                    }
+                }
+                let invalid = config.invalid_programs.iter().next();
+                match (config.server.on_invalid_program, invalid) {
+                    (InvalidProgramPolicy::Abort, Some((name, error))) => Err(de::Error::custom(
+                        format!("the program `{name}` is invalid: {error}"),
+                    )),
+                    _ => Ok(config),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ConfigVisitor)
     }
 }
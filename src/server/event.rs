@@ -0,0 +1,63 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use crate::{better_logs::send_http_message, config::ServerConfig};
+use std::sync::{Arc, RwLock};
+use tcl::message::Event;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// how many events a slow subscriber can fall behind before missing some
+const EVENT_BUFFER_SIZE: usize = 64;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// dispatch the daemon events to the subscribed clients and to the events webhook
+#[derive(Debug)]
+pub(super) struct EventBus {
+    sender: Sender<Event>,
+    /// the address the events are posted to, if any
+    webhook: RwLock<Option<String>>,
+}
+
+pub(super) type SharedEventBus = Arc<EventBus>;
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl EventBus {
+    fn new(config: &ServerConfig) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        Self {
+            sender,
+            webhook: RwLock::new(config.event_webhook.clone()),
+        }
+    }
+
+    /// apply the events settings of the server config
+    pub(super) fn configure(&self, config: &ServerConfig) {
+        *self.webhook.write().unwrap() = config.event_webhook.clone();
+    }
+
+    /// return a receiver getting every event published from now on
+    pub(super) fn subscribe(&self) -> Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// hand the event to every subscriber and post it to the webhook, never waiting for them
+    pub(super) fn publish(&self, event: Event) {
+        if let Some(address) = self.webhook.read().unwrap().clone() {
+            send_http_message(address, event.to_string());
+        }
+        // an error only means that nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+}
+
+pub(super) fn new_shared_event_bus(config: &ServerConfig) -> SharedEventBus {
+    Arc::new(EventBus::new(config))
+}
@@ -2,23 +2,125 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
+use crate::config::{LogBackend, LogFormat, ServerConfig};
 use std::{
-    fs::{File, OpenOptions},
-    io::Write,
-    sync::{Arc, RwLock},
-    time::{SystemTime, UNIX_EPOCH},
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, IsTerminal, Write},
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tcl::{
+    message::LogLevel,
+    time::{now_rfc3339, MonotonicTime},
 };
 
 /* -------------------------------------------------------------------------- */
 /*                                  Constant                                  */
 /* -------------------------------------------------------------------------- */
-const LOG_PATH: &str = "./log.txt";
+/// the maximum time an entry can stay in the buffer before being written to the file
+const FLUSH_PERIOD: Duration = Duration::from_millis(500);
+
+/// the name the entries are tagged with when sent to syslog
+const SYSLOG_TAG: &str = "taskmasterd";
+
+/// the syslog facility of system daemons, as defined by RFC 5424
+const SYSLOG_FACILITY_DAEMON: u8 = 3;
+
+/// the ansi escape sequence resetting the color of the console
+const COLOR_RESET: &str = "\x1b[0m";
 
 /* -------------------------------------------------------------------------- */
 /*                             Struct Declaration                             */
 /* -------------------------------------------------------------------------- */
+/// the entries are formatted by the caller and handed to a dedicated writer thread,
+/// so logging never wait for the disk (or the syslog daemon)
 pub(super) struct Logger {
-    file: RwLock<File>,
+    /// the channel feeding the writer thread
+    sender: Sender<LogCommand>,
+    /// the writer thread, taken when the logger is shut down
+    writer: Mutex<Option<JoinHandle<()>>>,
+    /// the minimum level an entry need to be written, stored as a u8 so it can be changed at runtime
+    level: AtomicU8,
+    /// whenever the entries are written as json objects instead of plain text
+    json: AtomicBool,
+}
+
+/// what can be asked to the writer thread
+enum LogCommand {
+    /// write an already formatted entry
+    Entry(LogEntry),
+    /// change the rotation settings
+    Rotation { max_bytes: u64, backups: u32 },
+    /// change where the entries are written
+    Sink {
+        backend: LogBackend,
+        log_file: PathBuf,
+        socket: PathBuf,
+    },
+    /// write everything that is pending, acknowledging it once done
+    Flush(mpsc::Sender<()>),
+    /// write everything that is pending and stop
+    Shutdown,
+}
+
+/// an entry of the log along with the context it was emitted in
+pub(super) struct LogRecord<'a> {
+    pub(super) level: LogLevel,
+    /// the module that emitted the entry
+    pub(super) target: &'a str,
+    /// the program the entry is about, if any
+    pub(super) program: Option<&'a str>,
+    /// the process the entry is about, if any
+    pub(super) pid: Option<u32>,
+    pub(super) message: &'a str,
+}
+
+/// an entry formatted by the caller, the header holds what syslog already
+/// records on its own (timestamp and level) so it is only written to the file
+struct LogEntry {
+    level: LogLevel,
+    header: Option<String>,
+    body: String,
+}
+
+/// the state of the writer thread
+struct LogWriter {
+    sink: LogSink,
+    /// the size above which the log file is rotated, 0 meaning never
+    max_bytes: u64,
+    /// how many rotated files are kept
+    backups: u32,
+    /// whenever the entries are also written to stderr
+    console: bool,
+    /// whenever the entries written to stderr are colored, only done for a terminal
+    colored: bool,
+}
+
+/// where the writer thread send the entries
+enum LogSink {
+    File(LogFile),
+    Syslog(Syslog),
+}
+
+/// the currently opened log file along with its size, to know when to rotate it
+struct LogFile {
+    file: BufWriter<File>,
+    size: u64,
+    path: PathBuf,
+}
+
+/// a datagram socket connected to the local syslog daemon (or journald)
+struct Syslog {
+    socket: UnixDatagram,
+    path: PathBuf,
 }
 
 pub(super) type SharedLogger = Arc<Logger>;
@@ -27,62 +129,441 @@ pub(super) type SharedLogger = Arc<Logger>;
 /*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
 impl Logger {
-    /// open a log file specified by the LOG_PATH constant, creating it if it doesn't exist
-    /// appending to it if it does.
-    pub(super) fn new() -> Result<Self, std::io::Error> {
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(LOG_PATH)?;
+    /// open the log file (or syslog socket) of the server config, creating the file and its
+    /// directory if they don't exist, appending to it if it does.
+    /// the entries are also written to stderr if console is set
+    pub(super) fn new(config: &ServerConfig, console: bool) -> Result<Self, std::io::Error> {
+        let log_writer = LogWriter {
+            sink: LogSink::open(
+                config.log_backend,
+                Path::new(&config.log_file),
+                Path::new(&config.syslog_socket),
+            )?,
+            max_bytes: config.log_max_bytes,
+            backups: config.log_backups,
+            console,
+            colored: std::io::stderr().is_terminal(),
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let writer = thread::Builder::new()
+            .name("logger".to_owned())
+            .spawn(move || log_writer.run(receiver))?;
+
         Ok(Logger {
-            file: RwLock::new(file),
+            sender,
+            writer: Mutex::new(Some(writer)),
+            level: AtomicU8::new(config.log_level as u8),
+            json: AtomicBool::new(config.log_format == LogFormat::Json),
         })
     }
 
-    /// write the message to the logging file
-    pub(super) fn log(&self, level: &str, message: &str) -> Result<(), std::io::Error> {
-        // get the time since unix epoch TODO! reworked for better formatting
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("the time returned by SystemTime::now() is earlier than UNIX_EPOCH")
-            .as_secs();
+    /// apply the logging settings of the server config
+    pub(super) fn configure(&self, config: &ServerConfig) {
+        self.set_level(config.log_level);
+        self.json
+            .store(config.log_format == LogFormat::Json, Ordering::Relaxed);
+        let _ = self.sender.send(LogCommand::Rotation {
+            max_bytes: config.log_max_bytes,
+            backups: config.log_backups,
+        });
+        let _ = self.sender.send(LogCommand::Sink {
+            backend: config.log_backend,
+            log_file: PathBuf::from(&config.log_file),
+            socket: PathBuf::from(&config.syslog_socket),
+        });
+    }
 
-        // format the log
-        let log_entry = format!("[{}] {} - {}\n", timestamp, level, message);
+    /// write every pending entry, waiting for it to be done
+    pub(super) fn flush(&self) {
+        let (done, flushed) = mpsc::channel();
+        if self.sender.send(LogCommand::Flush(done)).is_ok() {
+            let _ = flushed.recv();
+        }
+    }
+
+    /// write every pending entry and stop the writer thread, entries logged afterward are lost
+    pub(super) fn shutdown(&self) {
+        let _ = self.sender.send(LogCommand::Shutdown);
+        if let Some(writer) = self.writer.lock().unwrap().take() {
+            let _ = writer.join();
+        }
+    }
+
+    /// return whenever an entry of the given level would be written
+    pub(super) fn enabled(&self, level: LogLevel) -> bool {
+        level as u8 >= self.level.load(Ordering::Relaxed)
+    }
+
+    /// return the minimum level an entry need to be written
+    pub(super) fn level(&self) -> LogLevel {
+        match self.level.load(Ordering::Relaxed) {
+            level if level == LogLevel::Debug as u8 => LogLevel::Debug,
+            level if level == LogLevel::Info as u8 => LogLevel::Info,
+            level if level == LogLevel::Warn as u8 => LogLevel::Warn,
+            _ => LogLevel::Error,
+        }
+    }
+
+    /// change the minimum level an entry need to be written
+    pub(super) fn set_level(&self, level: LogLevel) {
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// format the entry and hand it to the writer thread
+    pub(super) fn log(&self, record: &LogRecord) -> Result<(), std::io::Error> {
+        let log_entry = if self.json.load(Ordering::Relaxed) {
+            LogEntry {
+                level: record.level,
+                header: None,
+                body: record.to_json(),
+            }
+        } else {
+            LogEntry {
+                level: record.level,
+                header: Some(format!("[{}] {} - ", now_rfc3339(), record.level)),
+                body: record.to_text(),
+            }
+        };
 
-        // write the log to the file
-        let mut file = self.file.write().unwrap();
-        file.write_all(log_entry.as_bytes())?;
-        file.flush()?;
+        self.sender
+            .send(LogCommand::Entry(log_entry))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "logger is shut down"))
+    }
+}
+
+impl LogRecord<'_> {
+    /// format the entry as `[program:pid] message`, the timestamp and level are added by the sink
+    fn to_text(&self) -> String {
+        let mut entry = String::new();
+        match (self.program, self.pid) {
+            (Some(program), Some(pid)) => {
+                let _ = write!(entry, "[{program}:{pid}] ");
+            }
+            (Some(program), None) => {
+                let _ = write!(entry, "[{program}] ");
+            }
+            (None, _) => {}
+        }
+        entry.push_str(self.message);
+        entry
+    }
+
+    /// format the entry as a single line json object
+    fn to_json(&self) -> String {
+        let mut entry = format!(
+            "{{\"ts\":\"{}\",\"level\":\"{}\",\"target\":\"{}\",\"message\":\"{}\"",
+            now_rfc3339(),
+            self.level,
+            json_escape(self.target),
+            json_escape(self.message)
+        );
+        if let Some(program) = self.program {
+            let _ = write!(entry, ",\"program\":\"{}\"", json_escape(program));
+        }
+        if let Some(pid) = self.pid {
+            let _ = write!(entry, ",\"pid\":{pid}");
+        }
+        entry.push('}');
+        entry
+    }
+}
+
+/// escape a string so it can be put between double quotes in a json document
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
 
+impl LogWriter {
+    /// the writer thread loop, entries are buffered and flushed at most FLUSH_PERIOD after
+    /// being received, the loop end once every entry sent before the shutdown is written
+    fn run(mut self, receiver: mpsc::Receiver<LogCommand>) {
+        let mut next_flush: Option<MonotonicTime> = None;
+        loop {
+            let command = match next_flush {
+                Some(deadline) => {
+                    receiver.recv_timeout(deadline.saturating_duration_since(MonotonicTime::now()))
+                }
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let result = match command {
+                Ok(LogCommand::Entry(entry)) => {
+                    next_flush.get_or_insert_with(|| MonotonicTime::now() + FLUSH_PERIOD);
+                    self.write(&entry)
+                }
+                Ok(LogCommand::Rotation { max_bytes, backups }) => {
+                    self.max_bytes = max_bytes;
+                    self.backups = backups;
+                    Ok(())
+                }
+                Ok(LogCommand::Sink {
+                    backend,
+                    log_file,
+                    socket,
+                }) => self.switch(backend, &log_file, &socket),
+                Err(RecvTimeoutError::Timeout) => {
+                    next_flush = None;
+                    self.flush()
+                }
+                Ok(LogCommand::Flush(done)) => {
+                    next_flush = None;
+                    let result = self.flush();
+                    let _ = done.send(());
+                    result
+                }
+                Ok(LogCommand::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
+                    if let Err(error) = self.flush() {
+                        eprintln!("Logging error: {error}");
+                    }
+                    return;
+                }
+            };
+            if let Err(error) = result {
+                eprintln!("Logging error: {error}");
+            }
+        }
+    }
+
+    fn write(&mut self, entry: &LogEntry) -> Result<(), std::io::Error> {
+        if self.console {
+            match &entry.header {
+                Some(header) if self.colored => eprintln!(
+                    "{}{header}{COLOR_RESET}{}",
+                    console_color(entry.level),
+                    entry.body
+                ),
+                Some(header) => eprintln!("{header}{}", entry.body),
+                None => eprintln!("{}", entry.body),
+            }
+        }
+        match &mut self.sink {
+            LogSink::File(log_file) => {
+                let mut line = entry.header.clone().unwrap_or_default();
+                line.push_str(&entry.body);
+                line.push('\n');
+                log_file.write(&line, self.max_bytes, self.backups)
+            }
+            LogSink::Syslog(syslog) => syslog.send(entry),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        match &mut self.sink {
+            LogSink::File(log_file) => log_file.file.flush(),
+            LogSink::Syslog(_) => Ok(()),
+        }
+    }
+
+    /// start writing the entries to the given log file or syslog socket,
+    /// the current one is kept if the new one can't be opened
+    fn switch(
+        &mut self,
+        backend: LogBackend,
+        log_file: &Path,
+        socket: &Path,
+    ) -> Result<(), std::io::Error> {
+        match (&self.sink, backend) {
+            (LogSink::File(current), LogBackend::File) if current.path == log_file => return Ok(()),
+            (LogSink::Syslog(current), LogBackend::Syslog) if current.path == socket => {
+                return Ok(())
+            }
+            _ => {}
+        }
+        let sink = LogSink::open(backend, log_file, socket)?;
+        self.flush()?;
+        self.sink = sink;
+        Ok(())
+    }
+}
+
+impl LogSink {
+    fn open(backend: LogBackend, log_file: &Path, socket: &Path) -> Result<Self, std::io::Error> {
+        Ok(match backend {
+            LogBackend::File => LogSink::File(LogFile::open(log_file.to_owned())?),
+            LogBackend::Syslog => LogSink::Syslog(Syslog::connect(socket.to_owned())?),
+        })
+    }
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> Result<Self, std::io::Error> {
+        if let Some(directory) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(directory)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            file: BufWriter::new(file),
+            size,
+            path,
+        })
+    }
+
+    /// write the entry, rotating the file first if the entry would make it bigger than max_bytes
+    fn write(&mut self, entry: &str, max_bytes: u64, backups: u32) -> Result<(), std::io::Error> {
+        if max_bytes != 0 && self.size + entry.len() as u64 > max_bytes {
+            self.rotate(backups)?;
+        }
+        self.file.write_all(entry.as_bytes())?;
+        self.size += entry.len() as u64;
+        Ok(())
+    }
+
+    /// shift the backups (taskmasterd.log.1 -> taskmasterd.log.2, ...), dropping the oldest one,
+    /// move the current file to taskmasterd.log.1 and start a new one.
+    /// only the writer thread touch the file so no entry can be written in between
+    fn rotate(&mut self, backups: u32) -> Result<(), std::io::Error> {
+        self.file.flush()?;
+        let path = self.path.display().to_string();
+        if backups == 0 {
+            fs::remove_file(&path)?;
+        } else {
+            for index in (1..backups).rev() {
+                let from = format!("{path}.{index}");
+                if fs::metadata(&from).is_ok() {
+                    fs::rename(&from, format!("{path}.{}", index + 1))?;
+                }
+            }
+            fs::rename(&path, format!("{path}.1"))?;
+        }
+        *self = Self::open(self.path.clone())?;
         Ok(())
     }
 }
 
-pub(crate) fn new_shared_logger() -> Result<SharedLogger, std::io::Error> {
-    Ok(Arc::new(Logger::new()?))
+impl Syslog {
+    fn connect(path: PathBuf) -> Result<Self, std::io::Error> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&path)?;
+        Ok(Self { socket, path })
+    }
+
+    /// send the entry as `<priority>taskmasterd[pid]: message`, the syslog daemon
+    /// adds the timestamp and hostname itself. the socket is reconnected once if
+    /// the daemon was restarted since the last entry
+    fn send(&mut self, entry: &LogEntry) -> Result<(), std::io::Error> {
+        let datagram = format!(
+            "<{}>{SYSLOG_TAG}[{}]: {}",
+            SYSLOG_FACILITY_DAEMON * 8 + syslog_severity(entry.level),
+            std::process::id(),
+            entry.body
+        );
+        if self.socket.send(datagram.as_bytes()).is_ok() {
+            return Ok(());
+        }
+        *self = Self::connect(self.path.clone())?;
+        self.socket.send(datagram.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// return the ansi escape sequence coloring the entries of the given level on the console
+fn console_color(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Debug => "\x1b[2m",
+        LogLevel::Info => "\x1b[32m",
+        LogLevel::Warn => "\x1b[33m",
+        LogLevel::Error => "\x1b[31m",
+    }
+}
+
+/// map a level to its syslog severity, as defined by RFC 5424
+fn syslog_severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Debug => 7,
+        LogLevel::Info => 6,
+        LogLevel::Warn => 4,
+        LogLevel::Error => 3,
+    }
+}
+
+pub(crate) fn new_shared_logger(
+    config: &ServerConfig,
+    console: bool,
+) -> Result<SharedLogger, std::io::Error> {
+    Ok(Arc::new(Logger::new(config, console)?))
 }
 
 /* -------------------------------------------------------------------------- */
 /*                                    Macro                                   */
 /* -------------------------------------------------------------------------- */
+/// log the message if the logger level allow it, the message is only formatted in that case.
+/// the program and pid the entry is about can be given before the message:
+/// `log_error!(logger, program = name, pid = Some(pid); "{error}")`
+#[macro_export]
+macro_rules! log_at {
+    ($logger:expr, $level:expr, program = $program:expr, pid = $pid:expr; $($arg:tt)*) => {
+        if $logger.enabled($level) {
+            $logger
+                .log(&$crate::logger::LogRecord {
+                    level: $level,
+                    target: module_path!(),
+                    program: Some(&$program[..]),
+                    pid: $pid,
+                    message: &format!($($arg)*),
+                })
+                .unwrap_or_else(|e| eprintln!("Logging error: {}", e));
+        }
+    };
+    ($logger:expr, $level:expr, program = $program:expr; $($arg:tt)*) => {
+        $crate::log_at!($logger, $level, program = $program, pid = None; $($arg)*)
+    };
+    ($logger:expr, $level:expr, $($arg:tt)*) => {
+        if $logger.enabled($level) {
+            $logger
+                .log(&$crate::logger::LogRecord {
+                    level: $level,
+                    target: module_path!(),
+                    program: None,
+                    pid: None,
+                    message: &format!($($arg)*),
+                })
+                .unwrap_or_else(|e| eprintln!("Logging error: {}", e));
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! log_debug {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.log("DEBUG", &format!($($arg)*)).unwrap_or_else(|e| eprintln!("Logging error: {}", e));
+        $crate::log_at!($logger, tcl::message::LogLevel::Debug, $($arg)*)
     }
 }
 
 #[macro_export]
 macro_rules! log_info {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.log("INFO", &format!($($arg)*)).unwrap_or_else(|e| eprintln!("Logging error: {}", e));
+        $crate::log_at!($logger, tcl::message::LogLevel::Info, $($arg)*)
+    }
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::log_at!($logger, tcl::message::LogLevel::Warn, $($arg)*)
     }
 }
 
 #[macro_export]
 macro_rules! log_error {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.log("ERROR", &format!($($arg)*)).unwrap_or_else(|e| eprintln!("Logging error: {}", e));
+        $crate::log_at!($logger, tcl::message::LogLevel::Error, $($arg)*)
     }
 }
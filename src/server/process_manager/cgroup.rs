@@ -0,0 +1,70 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use crate::config::ProgramConfig;
+use std::{
+    fs::{self, File, OpenOptions},
+    os::fd::RawFd,
+    path::Path,
+};
+use tcl::{message::CgroupUsage, mylibc};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the controllers the program cgroups need from their parent
+const CONTROLLERS: &str = "+cpu +memory";
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// create the cgroup of a program if needed and apply the limits of its config, returning
+/// the cgroup.procs file a child write itself to with `join_from_child`
+pub(super) fn prepare(path: &Path, config: &ProgramConfig) -> Result<File, std::io::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+        // the controllers may already be enabled, or unavailable, the limits would then fail
+        let _ = fs::write(parent.join("cgroup.subtree_control"), CONTROLLERS);
+    }
+    if !path.is_dir() {
+        fs::create_dir(path)?;
+    }
+    if let Some(cpu_max) = &config.cpu_max {
+        fs::write(path.join("cpu.max"), cpu_max)?;
+    }
+    if let Some(memory_max) = &config.memory_max {
+        fs::write(path.join("memory.max"), memory_max)?;
+    }
+    OpenOptions::new()
+        .write(true)
+        .open(path.join("cgroup.procs"))
+}
+
+/// move the calling process to the cgroup whose cgroup.procs is open on the given fd,
+/// it is run in the child between fork and exec so it must not allocate
+pub(super) fn join_from_child(procs: RawFd) -> Result<(), std::io::Error> {
+    // writing 0 move the writer itself
+    mylibc::write(procs, b"0").map(|_| ())
+}
+
+/// read what the processes of the cgroup use, None if the cgroup doesn't exist (yet)
+pub(super) fn usage(path: &Path) -> Option<CgroupUsage> {
+    let cpu_usage_usec = fs::read_to_string(path.join("cpu.stat"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|value| value.trim().parse().ok());
+    let memory_bytes = fs::read_to_string(path.join("memory.current"))
+        .ok()
+        .and_then(|value| value.trim().parse().ok());
+    Some(CgroupUsage {
+        cpu_usage_usec,
+        memory_bytes,
+    })
+}
+
+/// remove the cgroup of a program that is gone, it only succeed once it is empty
+pub(super) fn remove(path: &Path) {
+    let _ = fs::remove_dir(path);
+}
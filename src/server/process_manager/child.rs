@@ -0,0 +1,91 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::Child;
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+use std::{ffi::c_int, io, process::ExitStatus};
+use tcl::{
+    message::ProcessExit,
+    mylibc::{self, WaitStatus},
+};
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Child {
+    pub(super) fn id(&self) -> u32 {
+        match self {
+            Child::Spawned(child) => child.id(),
+            Child::Adopted { pid, .. } => *pid,
+            #[cfg(any(test, feature = "test-harness"))]
+            Child::Scripted(child) => child.id(),
+        }
+    }
+
+    /// reap the child if it exited, returning how it ended. None if it is still running
+    pub(super) fn try_wait(&mut self) -> io::Result<Option<ProcessExit>> {
+        match self {
+            Child::Spawned(child) => Ok(child.try_wait()?.map(Self::extract_exit)),
+            Child::Adopted {
+                exit: Some(exit), ..
+            } => Ok(Some(*exit)),
+            Child::Adopted { pid, exit } => {
+                *exit = match mylibc::waitpid(*pid as mylibc::pid_t, mylibc::WNOHANG)? {
+                    Some((_, WaitStatus::Exited(code))) => Some(ProcessExit::Exited(code)),
+                    Some((_, WaitStatus::Signaled(signal, _))) => {
+                        Some(ProcessExit::ExitedBySignal(signal))
+                    }
+                    Some((_, WaitStatus::Stopped(_) | WaitStatus::Continued)) | None => None,
+                };
+                Ok(*exit)
+            }
+            #[cfg(any(test, feature = "test-harness"))]
+            Child::Scripted(child) => Ok(child.try_wait()),
+        }
+    }
+
+    /// send SIGKILL to the child
+    pub(super) fn kill(&mut self) -> io::Result<()> {
+        match self {
+            Child::Spawned(child) => child.kill(),
+            _ => self.signal(mylibc::SIGKILL),
+        }
+    }
+
+    /// send the signal to the child
+    pub(super) fn signal(&mut self, signal: c_int) -> io::Result<()> {
+        match self {
+            #[cfg(any(test, feature = "test-harness"))]
+            Child::Scripted(child) => {
+                child.signal(signal);
+                Ok(())
+            }
+            _ => mylibc::kill(self.id() as mylibc::pid_t, signal),
+        }
+    }
+
+    #[cfg(unix)]
+    fn extract_exit(status: ExitStatus) -> ProcessExit {
+        status.code().map_or_else(
+            || {
+                ProcessExit::ExitedBySignal(
+                    status
+                        .signal()
+                        .expect("Process terminated by signal, but no signal number found"),
+                )
+            },
+            ProcessExit::Exited,
+        )
+    }
+
+    #[cfg(not(unix))]
+    fn extract_exit(status: ExitStatus) -> ProcessExit {
+        ProcessExit::Exited(
+            status
+                .code()
+                .expect("Exit code should always be available on non-unix systems"),
+        )
+    }
+}
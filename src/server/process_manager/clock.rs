@@ -0,0 +1,24 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use std::time::Duration;
+use tcl::time::MonotonicTime;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// the time read by the timers of the processes (starttime, stoptime, backoff), the
+/// monotonic clock unless the test harness mocks it on the calling thread
+pub(super) fn now() -> MonotonicTime {
+    #[cfg(any(test, feature = "test-harness"))]
+    if let Some(now) = super::harness::mock_now() {
+        return now;
+    }
+    MonotonicTime::now()
+}
+
+/// the time elapsed since the given point, zero if it is later
+pub(super) fn elapsed(since: MonotonicTime) -> Duration {
+    now().saturating_duration_since(since)
+}
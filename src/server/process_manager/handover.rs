@@ -0,0 +1,303 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{
+    Child, ChildPipes, DesiredState, Process, ProcessState, Program, ProgramManager,
+    TransitionCause,
+};
+use crate::{log_error, log_info, log_warn, logger::Logger};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+};
+use tcl::{error::TaskmasterError, message::ProcessExit, mylibc, time::MonotonicTime};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// what a daemon hands over to the one replacing it on a reexec, written to the state file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct Handover {
+    programs: BTreeMap<String, ProgramHandover>,
+}
+
+/// the runtime state of a program
+#[derive(Debug, Serialize, Deserialize)]
+struct ProgramHandover {
+    desired: DesiredState,
+    maintenance: bool,
+    /// when the program is stopped, if its run time is limited
+    #[serde(default)]
+    run_deadline: Option<MonotonicTime>,
+    #[serde(default)]
+    restarts_paused: bool,
+    /// when the restarts are resumed, if their pause was given a duration
+    #[serde(default)]
+    restarts_resume_at: Option<MonotonicTime>,
+    processes: Vec<ProcessHandover>,
+    /// the processes left over by a decrease of numprocs
+    retiring: Vec<ProcessHandover>,
+}
+
+/// the runtime state of a process, its child is inherited by the new daemon along with the
+/// file descriptors of the pipes to it
+#[derive(Debug, Serialize, Deserialize)]
+struct ProcessHandover {
+    replica: usize,
+    pid: Option<u32>,
+    state: ProcessState,
+    started_since: Option<MonotonicTime>,
+    time_since_shutdown: Option<MonotonicTime>,
+    number_of_restart: u32,
+    number_of_autorestart: u32,
+    #[serde(default)]
+    exit: Option<ProcessExit>,
+    paused: bool,
+    last_cause: Option<TransitionCause>,
+    stdin: Option<RawFd>,
+    stdout: Option<RawFd>,
+    stderr: Option<RawFd>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl ProgramManager {
+    /// tell why the programs can't be handed over to a new daemon right now, if they can't
+    pub fn check_handover(&self) -> Result<(), TaskmasterError> {
+        match self.purgatory.is_empty() {
+            true => Ok(()),
+            false => Err(TaskmasterError::Custom(
+                "the programs removed by the last reload are still shutting down".to_owned(),
+            )),
+        }
+    }
+
+    /// hand the programs over to the daemon replacing this one on a reexec: their runtime
+    /// state is written to the state file and the pipes to their children are kept open
+    /// across the exec, returning the file descriptors of the pipes. the programs must not be
+    /// touched until the daemon is replaced, or until `cancel_handover` if it couldn't be
+    pub fn hand_over(&mut self) -> Result<Vec<RawFd>, TaskmasterError> {
+        self.check_handover()?;
+        let handover = Handover {
+            programs: self
+                .programs
+                .iter()
+                .map(|(name, program)| (name.to_owned(), program.hand_over()))
+                .collect(),
+        };
+        let inherited = handover.inherited_fds();
+        self.state_file.save_handover(handover)?;
+        if let Err(e) = inherited
+            .iter()
+            .try_for_each(|fd| mylibc::set_inheritable(*fd))
+        {
+            self.cancel_handover(&inherited);
+            return Err(e.into());
+        }
+        Ok(inherited)
+    }
+
+    /// go on supervising the programs after a failed reexec
+    pub fn cancel_handover(&mut self, inherited: &[RawFd]) {
+        for fd in inherited {
+            let _ = mylibc::set_cloexec(*fd);
+        }
+        let _ = self.state_file.take_handover();
+    }
+
+    /// take over the programs handed over by the daemon this one replaced on a reexec, it has
+    /// to be done before the monitor starts. the children of the programs that are no longer
+    /// part of the config are killed
+    pub fn adopt_handover(&mut self, logger: &Logger) {
+        let handover = match self.state_file.take_handover() {
+            Ok(Some(handover)) => handover,
+            Ok(None) => {
+                log_warn!(logger, "Nothing was handed over by the previous daemon");
+                return;
+            }
+            Err(e) => {
+                log_error!(
+                    logger,
+                    "Can't read what the previous daemon handed over: {e}"
+                );
+                return;
+            }
+        };
+        for (name, handover) in handover.programs {
+            match self.programs.get_mut(&name) {
+                Some(program) => program.adopt(handover, logger),
+                None => handover.abandon(&name, logger),
+            }
+        }
+    }
+}
+
+impl Program {
+    fn hand_over(&self) -> ProgramHandover {
+        ProgramHandover {
+            desired: self.desired,
+            maintenance: self.maintenance,
+            run_deadline: self.run_deadline,
+            restarts_paused: self.restarts_paused,
+            restarts_resume_at: self.restarts_resume_at,
+            processes: self.process_vec.iter().map(Process::hand_over).collect(),
+            retiring: self.retiring.iter().map(Process::hand_over).collect(),
+        }
+    }
+
+    /// take the runtime state of the program over, the processes that are no longer part of
+    /// it are retired. the event listeners are restarted since the events protocol can't be
+    /// picked up where the previous daemon left it
+    fn adopt(&mut self, handover: ProgramHandover, logger: &Logger) {
+        self.desired = handover.desired;
+        self.maintenance = handover.maintenance;
+        // the monotonic clock goes on across the exec
+        self.run_deadline = handover.run_deadline;
+        self.restarts_paused = handover.restarts_paused;
+        self.restarts_resume_at = handover.restarts_resume_at;
+
+        let processes = handover
+            .processes
+            .into_iter()
+            .map(|process| (process, false))
+            .chain(handover.retiring.into_iter().map(|process| (process, true)));
+        for (process_handover, retired) in processes {
+            let replica = process_handover.replica;
+            let process = match self.process_vec.get_mut(replica) {
+                Some(process) if !retired => process,
+                _ => {
+                    self.retiring.push(Process::new(
+                        self.config.to_owned(),
+                        self.cgroup.clone(),
+                        self.history.clone(),
+                        replica,
+                        None,
+                    ));
+                    self.retiring.last_mut().expect("a process was just pushed")
+                }
+            };
+            if let Err(e) = process.adopt(process_handover) {
+                log_error!(logger, program = self.name, pid = process.pid();
+                    "process {replica}: can't open the output redirections: {e}");
+            }
+            log_info!(logger, program = self.name, pid = process.pid();
+                "process {replica}: adopted in state {:?}", process.state);
+            if process.event_bus.is_some() && process.is_active() {
+                if let Err(e) = process.restart() {
+                    log_error!(logger, program = self.name, pid = process.pid(); "{e}");
+                }
+            }
+            if let Some(reported) = self.reported_states.get_mut(replica).filter(|_| !retired) {
+                *reported = process.state;
+            }
+        }
+    }
+}
+
+impl Process {
+    fn hand_over(&self) -> ProcessHandover {
+        ProcessHandover {
+            replica: self.replica,
+            pid: self.pid(),
+            state: self.state,
+            started_since: self.started_since,
+            time_since_shutdown: self.time_since_shutdown,
+            number_of_restart: self.number_of_restart,
+            number_of_autorestart: self.number_of_autorestart,
+            exit: self.exit,
+            paused: self.paused,
+            last_cause: self.last_cause.clone(),
+            stdin: self.pipes.stdin.as_ref().map(AsRawFd::as_raw_fd),
+            stdout: self.pipes.stdout.as_ref().map(AsRawFd::as_raw_fd),
+            stderr: self.pipes.stderr.as_ref().map(AsRawFd::as_raw_fd),
+        }
+    }
+
+    /// take the runtime state of the process over, along with its child and the pipes to it.
+    /// a child that is gone in the meantime is started again if it should be running
+    fn adopt(&mut self, mut handover: ProcessHandover) -> Result<(), std::io::Error> {
+        let pipes = handover.take_pipes();
+        self.state = handover.state;
+        self.started_since = handover.started_since;
+        self.time_since_shutdown = handover.time_since_shutdown;
+        self.number_of_restart = handover.number_of_restart;
+        self.number_of_autorestart = handover.number_of_autorestart;
+        self.exit = handover.exit;
+        self.last_cause = handover.last_cause;
+
+        let Some(pid) = handover.pid.filter(|pid| is_child(*pid)) else {
+            if handover.pid.is_some() {
+                self.state = ProcessState::Stopped;
+            }
+            return Ok(());
+        };
+        self.child = Some(Child::Adopted { pid, exit: None });
+        self.paused = handover.paused;
+        let (redirections, result) = match self.open_redirections() {
+            Ok(redirections) => (redirections, Ok(())),
+            Err(e) => ((None, None), Err(e)),
+        };
+        self.serve_pipes(pipes, redirections, false);
+        result
+    }
+}
+
+impl Handover {
+    /// the file descriptors the new daemon has to inherit
+    fn inherited_fds(&self) -> Vec<RawFd> {
+        self.programs
+            .values()
+            .flat_map(|program| program.processes.iter().chain(&program.retiring))
+            .flat_map(|process| [process.stdin, process.stdout, process.stderr])
+            .flatten()
+            .collect()
+    }
+}
+
+impl ProgramHandover {
+    /// kill the children of a program that is no longer part of the config
+    fn abandon(self, name: &str, logger: &Logger) {
+        for mut process in self.processes.into_iter().chain(self.retiring) {
+            // closed right away
+            process.take_pipes();
+            let Some(pid) = process.pid.filter(|pid| is_child(*pid)) else {
+                continue;
+            };
+            log_warn!(logger, program = name, pid = Some(pid);
+                "killed, the program is no longer part of the config");
+            let pid = pid as mylibc::pid_t;
+            if mylibc::kill(pid, mylibc::SIGKILL).is_ok() {
+                let _ = mylibc::waitpid(pid, 0);
+            }
+        }
+    }
+}
+
+impl ProcessHandover {
+    /// own the pipes inherited from the previous daemon, they are not inherited any further
+    fn take_pipes(&mut self) -> ChildPipes {
+        let inherit = |fd: RawFd| {
+            // the previous daemon handed the fd over and nothing else owns it
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            let _ = mylibc::set_cloexec(fd.as_raw_fd());
+            fd
+        };
+        ChildPipes {
+            stdin: self.stdin.take().map(inherit).map(File::from),
+            stdout: self.stdout.take().map(inherit),
+            stderr: self.stderr.take().map(inherit),
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// tell if the pid is still a child of the daemon, a handover is not trusted blindly
+fn is_child(pid: u32) -> bool {
+    mylibc::is_child(pid as mylibc::pid_t).unwrap_or(false)
+}
@@ -0,0 +1,175 @@
+/*!
+ * The test mode of the process manager (test-harness): the timers of the processes read a
+ * mock clock only moving when told to, and the children are scripted fakes instead of
+ * spawned programs. Both are set on the calling thread, so the processes must be
+ * reconciled on it (a spawnconcurrency of 1)
+ */
+// only driven by the tests, a daemon built with the feature doesn't call it
+#![cfg_attr(not(test), allow(dead_code))]
+
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{clock, Child, Process, ProcessError, ProcessState, ProgramManager};
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    ffi::c_int,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use tcl::{message::ProcessExit, mylibc, time::MonotonicTime};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the pids of the scripted children, above the ones of the system (pid_max is 2^22)
+const FIRST_SCRIPTED_PID: u32 = 1 << 30;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Static                                   */
+/* -------------------------------------------------------------------------- */
+static NEXT_SCRIPTED_PID: AtomicU32 = AtomicU32::new(FIRST_SCRIPTED_PID);
+
+thread_local! {
+    /// the time of the mock clock of the thread, None if it reads the monotonic clock
+    static MOCK_NOW: Cell<Option<MonotonicTime>> = const { Cell::new(None) };
+
+    /// the scripts of the next children started on the thread, in order
+    static SCRIPTS: RefCell<VecDeque<Script>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// how a scripted child behaves, by default it runs until it is signaled
+#[derive(Debug, Clone, Default)]
+pub(super) struct Script {
+    /// how long it runs before it exits on its own, and how
+    exit_after: Option<(Duration, ProcessExit)>,
+    /// the signals it survives, SIGKILL ends it whatever
+    ignored_signals: Vec<c_int>,
+}
+
+/// a fake child following its script on the mock clock
+#[derive(Debug)]
+pub(super) struct ScriptedChild {
+    pid: u32,
+    script: Script,
+    started: MonotonicTime,
+    exit: Option<ProcessExit>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Script {
+    /// a child exiting on its own once the duration elapsed
+    pub(super) fn exits_after(duration: Duration, exit: ProcessExit) -> Self {
+        Self {
+            exit_after: Some((duration, exit)),
+            ..Default::default()
+        }
+    }
+
+    /// the child survives the signal (e.g. the stop signal)
+    pub(super) fn ignoring(mut self, signal: c_int) -> Self {
+        self.ignored_signals.push(signal);
+        self
+    }
+}
+
+impl ScriptedChild {
+    fn new(script: Script) -> Self {
+        Self {
+            pid: NEXT_SCRIPTED_PID.fetch_add(1, Ordering::Relaxed),
+            script,
+            started: clock::now(),
+            exit: None,
+        }
+    }
+
+    pub(super) fn id(&self) -> u32 {
+        self.pid
+    }
+
+    /// how the child ended, None if it is still running
+    pub(super) fn try_wait(&mut self) -> Option<ProcessExit> {
+        if let Some((duration, exit)) = self.script.exit_after {
+            if self.exit.is_none() && clock::elapsed(self.started) >= duration {
+                self.exit = Some(exit);
+            }
+        }
+        self.exit
+    }
+
+    /// a signal the child doesn't ignore terminates it, SIGSTOP and SIGCONT have no effect
+    pub(super) fn signal(&mut self, signal: c_int) {
+        let survives = signal != mylibc::SIGKILL
+            && (self.script.ignored_signals.contains(&signal)
+                || [mylibc::SIGSTOP, mylibc::SIGCONT].contains(&signal));
+        if self.exit.is_none() && !survives {
+            self.exit = Some(ProcessExit::ExitedBySignal(signal));
+        }
+    }
+}
+
+impl Process {
+    /// start a scripted child instead of spawning the command, like start does
+    pub(super) fn start_scripted(&mut self, script: Script) -> Result<(), ProcessError> {
+        self.child = Some(Child::Scripted(ScriptedChild::new(script)));
+        self.readiness = None;
+        self.state = ProcessState::Starting;
+        self.started_since = Some(clock::now());
+        self.time_since_shutdown = None;
+        Ok(())
+    }
+}
+
+impl ProgramManager {
+    /// write a line to the output of a program as its first process would, the attached
+    /// clients get it. false if the program doesn't exist
+    pub(crate) fn write_output(&self, program_name: &str, line: &str) -> bool {
+        let Some(program) = self.programs.get(program_name) else {
+            return false;
+        };
+        program
+            .history
+            .lock()
+            .unwrap()
+            .push(0, line.as_bytes(), true);
+        true
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// make the thread read a mock clock, starting at the current time
+pub(super) fn mock_clock() {
+    MOCK_NOW.set(Some(MonotonicTime::now()));
+}
+
+/// move the mock clock of the thread forward
+pub(super) fn advance(duration: Duration) {
+    let now = MOCK_NOW
+        .get()
+        .expect("the clock of the thread isn't mocked");
+    MOCK_NOW.set(Some(now + duration));
+}
+
+/// the time of the mock clock of the thread, None if it isn't mocked
+pub(super) fn mock_now() -> Option<MonotonicTime> {
+    MOCK_NOW.get()
+}
+
+/// the next child started on the thread follows the script instead of being spawned
+pub(super) fn script_next_child(script: Script) {
+    SCRIPTS.with_borrow_mut(|scripts| scripts.push_back(script));
+}
+
+/// the script of the child about to be started on the thread, if one was given
+pub(super) fn take_script() -> Option<Script> {
+    SCRIPTS.with_borrow_mut(VecDeque::pop_front)
+}
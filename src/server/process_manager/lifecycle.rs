@@ -0,0 +1,123 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{Process, ProcessState};
+use std::{
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+use tcl::time::now_rfc3339;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the directory holding the lifecycle log of each program
+const LIFECYCLE_LOG_DIRECTORY: &str = "./programs";
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// the supervision history of a program (spawns, state changes, restarts and exit codes),
+/// written to `programs/<name>.log`, separately from the program own output
+#[derive(Debug)]
+pub(super) struct LifecycleLog {
+    file: File,
+    /// what was last written about each process, indexed like the program processes
+    reported: Vec<Snapshot>,
+}
+
+/// what is written about a process, a new entry is written when it change
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    state: ProcessState,
+    pid: Option<u32>,
+    number_of_restart: u32,
+    number_of_autorestart: u32,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl LifecycleLog {
+    /// open the lifecycle log of the given program, creating the directory and the file
+    /// if they don't exist, appending to the file if it does
+    pub(super) fn open(program_name: &str) -> Result<Self, std::io::Error> {
+        fs::create_dir_all(LIFECYCLE_LOG_DIRECTORY)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Path::new(LIFECYCLE_LOG_DIRECTORY).join(format!("{program_name}.log")))?;
+        Ok(Self {
+            file,
+            reported: Vec::new(),
+        })
+    }
+
+    /// write an entry for every process whose state, pid or restart counts changed
+    /// since the last call
+    pub(super) fn record(&mut self, processes: &[Process]) -> Result<(), std::io::Error> {
+        self.reported.resize(processes.len(), Snapshot::default());
+        for (index, process) in processes.iter().enumerate() {
+            let current = Snapshot {
+                state: process.state,
+                pid: process.pid(),
+                number_of_restart: process.number_of_restart,
+                number_of_autorestart: process.number_of_autorestart,
+            };
+            let previous = self.reported[index];
+            if current == previous {
+                continue;
+            }
+            self.reported[index] = current;
+            self.file
+                .write_all(format_entry(index, &previous, &current, process).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// format an entry as `[timestamp] process 0: Starting -> Running, pid 42`
+fn format_entry(
+    index: usize,
+    previous: &Snapshot,
+    current: &Snapshot,
+    process: &Process,
+) -> String {
+    let mut entry = format!("[{}] process {index}: ", now_rfc3339());
+    if previous.state == current.state {
+        let _ = write!(entry, "{:?}", current.state);
+    } else {
+        let _ = write!(entry, "{:?} -> {:?}", previous.state, current.state);
+    }
+    if let Some(pid) = current.pid.or(previous.pid) {
+        let _ = write!(entry, ", pid {pid}");
+    }
+    if let (true, Some(cause)) = (previous.state != current.state, &process.last_cause) {
+        let _ = write!(entry, ", cause {cause}");
+    }
+    // how the child ended is written when it exited, or when it was replaced by a new one
+    use ProcessState as PS;
+    let exited = matches!(
+        current.state,
+        PS::Backoff | PS::ExitedExpectedly | PS::ExitedUnExpectedly | PS::Stopped
+    );
+    let replaced = previous.pid.is_some() && current.pid != previous.pid;
+    if let (true, Some(exit)) = (exited || replaced, process.exit) {
+        let _ = write!(entry, ", {exit}");
+    }
+    if current.number_of_restart != previous.number_of_restart {
+        let _ = write!(
+            entry,
+            ", restart {}/{}",
+            current.number_of_restart, process.config.max_number_of_restart
+        );
+    }
+    if current.number_of_autorestart != previous.number_of_autorestart {
+        let _ = write!(entry, ", autorestart {}", current.number_of_autorestart);
+    }
+    entry.push('\n');
+    entry
+}
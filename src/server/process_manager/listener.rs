@@ -0,0 +1,96 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::output::{read_line, OutputSink};
+use std::io::{BufRead, BufReader, Read, Write};
+use tcl::message::Event;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// serve the events to a listener process the way supervisord does, to be run on a dedicated
+/// thread until the listener exits:
+/// - the listener writes `READY` on its stdout when it can handle an event
+/// - it is sent a header line `ver:3.0 server:taskmaster serial:N eventname:NAME len:N`
+///   followed by the event as yaml on its stdin
+/// - it answers `RESULT 2\nOK`, or `RESULT 4\nFAIL` to be sent the event again
+///
+/// the other lines written while it isn't handling an event are captured as output
+pub(super) fn serve(
+    stdout: impl Read,
+    mut stdin: impl Write,
+    mut events: Receiver<Event>,
+    listened: &[String],
+    mut sink: OutputSink,
+) {
+    let mut stdout = BufReader::new(stdout);
+    let mut line = Vec::new();
+    let mut failed: Option<Event> = None;
+    let mut serial: u64 = 0;
+    while let Some(line) = read_line(&mut stdout, &mut line) {
+        if line.trim_ascii() != b"READY" {
+            sink.write(line);
+            continue;
+        }
+        let Some(event) = failed.take().or_else(|| next_event(&mut events, listened)) else {
+            return;
+        };
+        serial += 1;
+        let payload = serde_yaml::to_string(&event).unwrap_or_default();
+        let header = format!(
+            "ver:3.0 server:taskmaster serial:{serial} eventname:{} len:{}\n",
+            event.name(),
+            payload.len()
+        );
+        if stdin
+            .write_all(header.as_bytes())
+            .and_then(|_| stdin.write_all(payload.as_bytes()))
+            .and_then(|_| stdin.flush())
+            .is_err()
+        {
+            return;
+        }
+        match read_result(&mut stdout) {
+            Some(true) => {}
+            Some(false) => failed = Some(event),
+            None => return,
+        }
+    }
+}
+
+/// wait for the next event the listener subscribed to, None once the daemon is shutting down
+fn next_event(events: &mut Receiver<Event>, listened: &[String]) -> Option<Event> {
+    loop {
+        match events.blocking_recv() {
+            Ok(event) if is_listened(&event, listened) => return Some(event),
+            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// tell if the event is one of the listened ones, `all` listen to every event
+fn is_listened(event: &Event, listened: &[String]) -> bool {
+    listened
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case("all") || name.eq_ignore_ascii_case(event.name()))
+}
+
+/// read the answer of the listener, `RESULT <len>\n` followed by len bytes, telling if the
+/// event was handled, a malformed answer is a failure. None if the listener exited
+fn read_result(stdout: &mut impl BufRead) -> Option<bool> {
+    let mut line = Vec::new();
+    let header = read_line(stdout, &mut line)?;
+    let len = std::str::from_utf8(header)
+        .ok()
+        .and_then(|header| header.trim().strip_prefix("RESULT "))
+        .and_then(|len| len.parse().ok());
+    let Some(len) = len else {
+        return Some(false);
+    };
+    let mut result = vec![0; len];
+    stdout.read_exact(&mut result).ok()?;
+    Some(result == b"OK")
+}
@@ -2,50 +2,134 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
-use super::{Program, ProgramError, ProgramManager, SharedProcessManager};
+use super::{
+    Admission, Cascade, MonitorWakeup, Operation, OrderError, Program, ProgramManager,
+    SharedProcessManager, StateFile, TransitionCause,
+};
 use crate::{
-    config::Config,
-    log_error,
+    config::{Config, Signal},
+    event::SharedEventBus,
+    log_error, log_info, log_warn,
     logger::{Logger, SharedLogger},
+    stats::STATS,
 };
+use regex::Regex;
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
     sync::{Arc, RwLock},
     thread::{self, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
+};
+use tcl::{
+    message::{
+        Event, OrderReport, OutputLine, ProgramStatus, ReplicaResult, Response, StatusQuery,
+    },
+    mylibc,
+    time::MonotonicTime,
 };
-use tcl::message::Response;
+use tokio::sync::broadcast::Receiver;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the prefix of an order target naming a tag instead of a program
+const TAG_PREFIX: &str = "tag:";
 
 /* -------------------------------------------------------------------------- */
 /*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
 impl ProgramManager {
-    /// return an instance of ProcessManager
-    fn new(config: &Config) -> Self {
-        let mut programs = HashMap::<String, Program>::default();
-        let purgatory = HashMap::<String, Program>::default();
-
-        config.iter().for_each(|(program_name, program_config)| {
-            let program = Program::new(program_name.to_owned(), program_config.to_owned());
-            programs.insert(program_name.to_owned(), program);
-        });
+    /// return an instance of ProcessManager, the programs stopped by an operator before the
+    /// daemon restarted are kept stopped
+    fn new(config: &Config, event_bus: SharedEventBus) -> Self {
+        let mut manager = Self {
+            programs: HashMap::default(),
+            purgatory: HashMap::default(),
+            refresh_period: config.server.refresh_period(),
+            wakeup: Arc::new(MonitorWakeup::new()),
+            state_file: StateFile::load(PathBuf::from(&config.server.state_file)),
+            cgroup_parent: config.server.cgroup_parent.as_ref().map(PathBuf::from),
+            event_bus,
+            order_cause: TransitionCause::default(),
+            invalid_programs: config.invalid_programs.clone(),
+            cascade: None,
+        };
+        manager.add_new_program(config, TransitionCause::Autostart);
+        manager
+    }
 
-        Self {
-            programs,
-            purgatory,
+    /// monitor every process once, returning the state changes of the programs as events
+    fn monitor_once(&mut self, logger: &Logger) -> Vec<Event> {
+        let events = self.monitor_program_once(logger);
+        self.advance_cascade(logger);
+        self.monitor_purgatory_once(logger);
+        self.reap_unknown_children(logger);
+        events
+    }
+
+    /// check the disk guardrails of every program, returning the events of the ones that
+    /// were just crossed
+    fn check_guardrails(&mut self, logger: &Logger) -> Vec<Event> {
+        self.programs
+            .iter_mut()
+            .filter_map(|(name, program)| {
+                let message = program.check_guardrails()?;
+                log_warn!(logger, program = name; "disk guardrail crossed: {message}");
+                Some(Event::DiskGuardrail {
+                    program: name.to_owned(),
+                    message,
+                })
+            })
+            .collect()
+    }
+
+    /// reap the exited children the daemon didn't spawn (orphans adopted as pid 1 or as a
+    /// subreaper), they would stay zombies otherwise. the managed children have just been
+    /// updated, one of them exiting since then is left to the next monitoring
+    fn reap_unknown_children(&self, logger: &Logger) {
+        loop {
+            let pid = match mylibc::peek_exited_child() {
+                Ok(Some(pid)) => pid,
+                Ok(None) => return,
+                Err(e) => {
+                    log_error!(logger, "Can't look for exited children: {e}");
+                    return;
+                }
+            };
+            if self.is_managed_child(pid) {
+                return;
+            }
+            match mylibc::waitpid(pid, mylibc::WNOHANG) {
+                Ok(Some((pid, status))) => {
+                    log_warn!(logger, "Reaped unknown child {pid} ({status})")
+                }
+                Ok(None) => return,
+                Err(e) => {
+                    log_error!(logger, "Can't reap unknown child {pid}: {e}");
+                    return;
+                }
+            }
         }
     }
 
-    fn monitor_once(&mut self, logger: &Logger) {
-        self.monitor_program_once(logger);
-        self.monitor_purgatory_once(logger);
+    /// tell if the pid is the one of a child spawned for a program, kept or in the purgatory
+    fn is_managed_child(&self, pid: mylibc::pid_t) -> bool {
+        self.programs
+            .values()
+            .chain(self.purgatory.values())
+            .any(|program| program.has_child(pid as u32))
     }
 
     /// this function iter over every process in programs and check update it's status
-    fn monitor_program_once(&mut self, logger: &Logger) {
-        self.programs.iter_mut().for_each(|(_name, program)| {
-            program.monitor(logger);
-        });
+    fn monitor_program_once(&mut self, logger: &Logger) -> Vec<Event> {
+        // the programs with the lowest priority are started first
+        let mut programs: Vec<&mut Program> = self.programs.values_mut().collect();
+        programs.sort_by(|a, b| (a.config.priority, &a.name).cmp(&(b.config.priority, &b.name)));
+        programs
+            .into_iter()
+            .flat_map(|program| program.monitor(logger))
+            .collect()
     }
 
     /// this function iter over every process in the purgatory and check update it's status
@@ -58,31 +142,74 @@ impl ProgramManager {
 
     /// try to conform to the new config
     pub fn reload_config(&mut self, config: &Config, logger: &Logger) {
+        self.refresh_period = config.server.refresh_period();
+        self.invalid_programs = config.invalid_programs.clone();
+        self.cgroup_parent = config.server.cgroup_parent.as_ref().map(PathBuf::from);
+        if let Err(e) = self
+            .state_file
+            .reload(PathBuf::from(&config.server.state_file), |name| {
+                config.contains_key(name)
+            })
+        {
+            log_error!(logger, "Can't save the state file: {e}");
+        }
+        // update in place the programs whose config only changed in a non-disruptive way
+        for (name, program) in self.programs.iter_mut() {
+            let new_config = config.get(name);
+            if let Some(new_config) = new_config.filter(|new| program.can_update_in_place(new)) {
+                program.update_in_place(new_config, &self.event_bus, logger);
+            }
+        }
         // remove unwanted program from the list of program
         self.drain_to_purgatory(config);
         // shut them down
         self.shutdown_purgatory(logger);
         // add the new program
-        self.add_new_program(config);
+        self.add_new_program(config, TransitionCause::Reload);
+        self.wake_monitor();
+    }
+
+    /// make the monitor run an iteration now instead of waiting for the end of the refresh period
+    pub fn wake_monitor(&self) {
+        self.wakeup.notify();
     }
 
-    /// this function add to self every program in the config that are not already present in self
-    fn add_new_program(&mut self, config: &Config) {
+    /// this function add to self every program in the config that are not already present in self,
+    /// the ones stopped by an operator are kept stopped
+    fn add_new_program(&mut self, config: &Config, start_cause: TransitionCause) {
         config.iter().for_each(|(name, config)| {
             if !self.programs.contains_key(name) {
-                self.programs.insert(
+                let mut program = Program::new(
                     name.to_owned(),
-                    Program::new(name.to_owned(), config.to_owned()),
+                    config.to_owned(),
+                    self.cgroup_parent.as_deref(),
+                    &self.event_bus,
+                    start_cause.clone(),
                 );
+                if self.state_file.is_stopped(name) {
+                    program.keep_stopped();
+                }
+                self.programs.insert(name.to_owned(), program);
             }
         });
     }
 
+    /// remember in the state file that an operator stopped or started the program
+    fn remember_manual_stop(&mut self, program_name: &str, stopped: bool, logger: &Logger) {
+        if !self.programs.contains_key(program_name) {
+            return;
+        }
+        if let Err(e) = self.state_file.set_stopped(program_name, stopped) {
+            log_error!(logger, "Can't save the state file: {e}");
+        }
+    }
+
+    /// move the programs that are no longer part of the config, or whose config changed,
+    /// to the purgatory, the others are kept as is
     fn drain_to_purgatory(&mut self, config: &Config) {
         self.purgatory.extend(
             self.programs
-                .drain()
-                .filter(|(_name, program)| !program.should_be_kept(config)),
+                .extract_if(|_name, program| !program.should_be_kept(config)),
         );
     }
 
@@ -99,145 +226,557 @@ impl ProgramManager {
         self.purgatory.iter_mut().for_each(|(_name, program)| {
             program.clean_inactive_process();
         });
-        self.purgatory.retain(|_name, program| !program.is_clean());
+        self.purgatory.retain(|name, program| {
+            if program.is_clean() {
+                program.remove_cgroup(self.programs.contains_key(name));
+            }
+            !program.is_clean()
+        });
     }
 
-    /// this function spawn a thread the will monitor all process in self updating there status as needed, refreshing every refresh_period
+    /// the priorities of the programs in the order they are stopped, the highest first
+    pub fn shutdown_order(&self) -> Vec<u32> {
+        let mut priorities: Vec<u32> = self
+            .programs
+            .values()
+            .chain(self.purgatory.values())
+            .map(|program| program.config.priority)
+            .collect();
+        priorities.sort_unstable_by(|a, b| b.cmp(a));
+        priorities.dedup();
+        priorities
+    }
+
+    /// forward the termination signal received by the daemon to every managed child of the
+    /// programs with the given priority, none of them is restarted afterward
+    pub fn forward_signal(&mut self, signal: &Signal, priority: u32, logger: &Logger) {
+        self.programs
+            .values_mut()
+            .chain(self.purgatory.values_mut())
+            .filter(|program| program.config.priority == priority)
+            .for_each(|program| {
+                program.forward_signal(signal, logger);
+                program.record_transitions(Some(&TransitionCause::Shutdown), logger);
+            });
+        self.wake_monitor();
+    }
+
+    /// tell if a managed child of a program with the given priority is still alive
+    pub fn has_active_children(&self, priority: u32) -> bool {
+        self.programs
+            .values()
+            .chain(self.purgatory.values())
+            .filter(|program| program.config.priority == priority)
+            .any(Program::has_active_process)
+    }
+
+    /// tell if every program reached a state it won't leave without an order,
+    /// a oneshot workload is then done
+    pub fn is_done(&self) -> bool {
+        self.purgatory.is_empty() && self.programs.values().all(Program::is_settled)
+    }
+
+    /// the exit code of the daemon as a supervisor: the exit code of the first program
+    /// (by name) that failed, 0 if none did
+    pub fn exit_code(&self) -> i32 {
+        let mut names: Vec<&String> = self.programs.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .find_map(|name| self.programs[name].failure_code())
+            .unwrap_or(0)
+    }
+
+    /// this function spawn a thread the will monitor all process in self updating there status as needed,
+    /// refreshing every refresh period or sooner when woken up
     pub async fn monitor(
         shared_process_manager: SharedProcessManager,
         shared_logger: SharedLogger,
-        refresh_period: Duration,
+        shared_event_bus: SharedEventBus,
     ) -> Result<JoinHandle<()>, std::io::Error> {
-        thread::Builder::new().spawn(move || loop {
-            shared_process_manager
-                .write()
-                .unwrap()
-                .monitor_once(&shared_logger);
-            thread::sleep(refresh_period);
-        })
+        let wakeup = shared_process_manager.read().unwrap().wakeup.clone();
+        thread::Builder::new()
+            .name("monitor".to_owned())
+            .spawn(move || loop {
+                let iteration_start = Instant::now();
+                let (refresh_period, events) = {
+                    let mut manager = shared_process_manager.write().unwrap();
+                    let mut events = manager.monitor_once(&shared_logger);
+                    events.extend(manager.check_guardrails(&shared_logger));
+                    (manager.refresh_period, events)
+                };
+                STATS.record_monitor_iteration(iteration_start.elapsed());
+                events
+                    .into_iter()
+                    .for_each(|event| shared_event_bus.publish(event));
+                wakeup.wait(refresh_period);
+            })
+    }
+
+    /// give the order to the target, either a program name or `tag:<tag>` for every program
+    /// with that tag, in which case the responses are merged into one. the state changes it
+    /// caused are attributed to the origin of the order (e.g. `stop by client#1 127.0.0.1`)
+    pub fn order(
+        &mut self,
+        target: &str,
+        origin: &str,
+        logger: &Logger,
+        mut order: impl FnMut(&mut Self, &str, &Logger) -> Response,
+    ) -> Response {
+        // the outcome of the order is reflected without waiting for the next refresh
+        self.wake_monitor();
+        let cause = TransitionCause::Order(origin.to_owned());
+        self.order_cause = cause.clone();
+        let mut order = |manager: &mut Self, name: &str, logger: &Logger| {
+            let response = order(manager, name, logger);
+            manager.record_transitions(name, &cause, logger);
+            response
+        };
+        let Some(tag) = target.strip_prefix(TAG_PREFIX) else {
+            return order(self, target, logger);
+        };
+        let names = self.tagged(tag);
+        if names.is_empty() {
+            return Response::Error(format!("no program is tagged `{tag}`"));
+        }
+
+        let errors: Vec<String> = names
+            .iter()
+            .filter_map(|name| match order(self, name, logger) {
+                Response::Error(error) => Some(error),
+                Response::OrderFailed(report) => Some(report.summary()),
+                _ => None,
+            })
+            .collect();
+        if errors.is_empty() {
+            Response::Success(format!("order succeed for {}", names.join(", ")))
+        } else {
+            Response::Error(errors.join("\n"))
+        }
+    }
+
+    /// attribute the state changes of a program to the cause, they are published right away
+    fn record_transitions(&mut self, program_name: &str, cause: &TransitionCause, logger: &Logger) {
+        if let Some(program) = self.programs.get_mut(program_name) {
+            program
+                .record_transitions(Some(cause), logger)
+                .into_iter()
+                .for_each(|event| self.event_bus.publish(event));
+        }
+    }
+
+    /// return the sorted names of the programs with the given tag
+    fn tagged(&self, tag: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .programs
+            .iter()
+            .filter(|(_, program)| program.has_tag(tag))
+            .map(|(name, _)| name.to_owned())
+            .collect();
+        names.sort();
+        names
     }
 
     /// Use for user manual starting of a program's process
     pub fn start_program(&mut self, program_name: &str, logger: &Logger) -> Response {
-        self.programs.get_mut(program_name).map_or(
-            Response::Error("couldn't found a program named : {program_name}".to_string()),
-            |program| match program.start() {
-                Ok(_) => Response::Success("Starting task succeed".to_string()),
-                Err(e) => match e {
-                    super::OrderError::PartialSuccess(errors) => {
-                        let error_message = format!(
-                            "Partial success starting program '{}'. Errors: {}",
-                            program_name,
-                            format_errors(&errors)
-                        );
-                        log_error!(logger, "{error_message}");
-                        Response::Error(error_message)
-                    }
-                    super::OrderError::TotalFailure(errors) => {
-                        let error_message = format!(
-                            "Failed to start program '{}'. Errors: {}",
-                            program_name,
-                            format_errors(&errors)
-                        );
-                        log_error!(logger, "{error_message}");
-                        Response::Error(error_message)
-                    }
-                },
-            },
-        )
+        self.remember_manual_stop(program_name, false, logger);
+        self.operate(program_name, Operation::Start, logger)
     }
 
     /// use for user manual shutdown of a program's process
     pub fn stop_program(&mut self, program_name: &str, logger: &Logger) -> Response {
-        self.programs.get_mut(program_name).map_or(
-            Response::Error("couldn't found a program named : {program_name}".to_string()),
-            |program| match program.stop() {
-                Ok(_) => Response::Success("stopping task succeed".to_string()),
-                Err(e) => match e {
-                    super::OrderError::PartialSuccess(errors) => {
-                        let error_message = format!(
-                            "Partial success stopping program '{}'. Errors: {}",
-                            program_name,
-                            format_errors(&errors)
-                        );
-                        log_error!(logger, "{error_message}");
-                        Response::Error(error_message)
-                    }
-                    super::OrderError::TotalFailure(errors) => {
-                        let error_message = format!(
-                            "Failed to stop program '{}'. Errors: {}",
-                            program_name,
-                            format_errors(&errors)
-                        );
-                        log_error!(logger, "{error_message}");
-                        Response::Error(error_message)
-                    }
-                },
-            },
-        )
+        self.remember_manual_stop(program_name, true, logger);
+        self.operate(program_name, Operation::Stop, logger)
     }
 
     /// use for user manual restart of a program's process
     pub fn restart_program(&mut self, program_name: &str, logger: &Logger) -> Response {
-        self.programs.get_mut(program_name).map_or(
-            Response::Error("couldn't found a program named : {program_name}".to_string()),
-            |program| match program.restart(logger) {
-                Ok(_) => Response::Success("stopping task succeed".to_string()),
-                Err(e) => match e {
-                    super::OrderError::PartialSuccess(errors) => {
-                        let error_message = format!(
-                            "Partial success stopping program '{}'. Errors: {}",
-                            program_name,
-                            format_errors(&errors)
-                        );
-                        log_error!(logger, "{error_message}");
-                        Response::Error(error_message)
-                    }
-                    super::OrderError::TotalFailure(errors) => {
-                        let error_message = format!(
-                            "Failed to stop program '{}'. Errors: {}",
-                            program_name,
-                            format_errors(&errors)
-                        );
-                        log_error!(logger, "{error_message}");
-                        Response::Error(error_message)
-                    }
-                },
-            },
-        )
-    }
-
-    /// use for user manual status command
-    pub fn get_status(&mut self) -> Response {
-        self.into()
+        self.remember_manual_stop(program_name, false, logger);
+        self.operate(program_name, Operation::Restart, logger)
     }
-}
 
-fn format_errors(errors: &[ProgramError]) -> String {
-    errors
-        .iter()
-        .map(|e| match e {
-            ProgramError::Logic(msg) => format!("Logic error: {}", msg),
-            ProgramError::Process(err) => format!("Process error: {:?}", err),
+    /// use for user manual restart of a program's process one at a time, whatever its
+    /// restart strategy
+    pub fn rolling_restart_program(&mut self, program_name: &str, logger: &Logger) -> Response {
+        self.remember_manual_stop(program_name, false, logger);
+        self.operate(program_name, Operation::RollingRestart, logger)
+    }
+
+    /// use for user manual restart of a program followed by the programs depending on it,
+    /// one after the other once the previous one is restarted
+    pub fn cascade_restart_program(&mut self, program_name: &str, logger: &Logger) -> Response {
+        if let Some(cascade) = &self.cascade {
+            return Response::Error(format!(
+                "operation already in progress: cascade restart of {}",
+                cascade.current
+            ));
+        }
+        let response = self.restart_program(program_name, logger);
+        if matches!(response, Response::Error(_)) {
+            return response;
+        }
+        let dependents = self.dependents(program_name);
+        if dependents.is_empty() {
+            return response;
+        }
+        log_info!(logger, program = program_name;
+            "cascade restart, then restarting {}", dependents.join(", "));
+        let message = format!("restarting {program_name}, then {}", dependents.join(", "));
+        self.cascade = Some(Cascade {
+            current: program_name.to_owned(),
+            next: dependents.into(),
+            origin: self.order_cause.to_string(),
+        });
+        Response::Success(message)
+    }
+
+    /// the programs depending on the program directly or not, each one after the ones it
+    /// depends on
+    fn dependents(&self, program_name: &str) -> Vec<String> {
+        let mut dependents: BTreeSet<&String> = BTreeSet::new();
+        let mut frontier = vec![program_name];
+        while let Some(name) = frontier.pop() {
+            for (dependent, program) in &self.programs {
+                if program
+                    .config
+                    .depends_on
+                    .iter()
+                    .any(|dependency| dependency == name)
+                    && dependents.insert(dependent)
+                {
+                    frontier.push(dependent);
+                }
+            }
+        }
+
+        let mut ordered = Vec::new();
+        while !dependents.is_empty() {
+            let ready: Vec<&String> = dependents
+                .iter()
+                .filter(|name| {
+                    self.programs[**name]
+                        .config
+                        .depends_on
+                        .iter()
+                        .all(|dependency| !dependents.contains(dependency))
+                })
+                .copied()
+                .collect();
+            // the config has no cycle, a program is always ready
+            if ready.is_empty() {
+                break;
+            }
+            ready.iter().for_each(|name| {
+                dependents.remove(*name);
+            });
+            ordered.extend(ready.into_iter().cloned());
+        }
+        ordered
+    }
+
+    /// restart the next program of the cascade restart once the previous one took effect,
+    /// the cascade stops if it couldn't be restarted
+    fn advance_cascade(&mut self, logger: &Logger) {
+        let Some(cascade) = &mut self.cascade else {
+            return;
+        };
+        match self.programs.get(&cascade.current) {
+            Some(program) if !program.orders_done() => return,
+            Some(program) if !program.is_healthy() => {
+                log_warn!(logger, program = cascade.current;
+                    "cascade restart stopped, the program isn't running");
+                self.cascade = None;
+                return;
+            }
+            _ => {}
+        }
+        let Some(next) = cascade.next.pop_front() else {
+            self.cascade = None;
+            return;
+        };
+        cascade.current = next.to_owned();
+        let origin = cascade.origin.to_owned();
+        let cause = TransitionCause::Order(origin);
+        self.order_cause = cause.clone();
+        if let Response::Error(e) = self.restart_program(&next, logger) {
+            log_warn!(logger, program = next; "cascade restart stopped: {e}");
+            self.cascade = None;
+        }
+        self.record_transitions(&next, &cause, logger);
+    }
+
+    /// use for a user manual start of a program limited in time, it is stopped gracefully
+    /// once the duration elapsed
+    pub fn run_program(
+        &mut self,
+        program_name: &str,
+        duration: Duration,
+        logger: &Logger,
+    ) -> Response {
+        let response = self.start_program(program_name, logger);
+        let rejected = matches!(response, Response::Error(_));
+        if let (Some(program), false) = (self.programs.get_mut(program_name), rejected) {
+            log_info!(logger, program = program_name;
+                "stopped in {}s, its run time is limited", duration.as_secs());
+            program.run_deadline = Some(MonotonicTime::now() + duration);
+        }
+        response
+    }
+
+    /// give an order that takes a while to take effect, it is queued behind the one taking
+    /// effect if any (unless it is a stop) and rejected if it is already ongoing or queued
+    fn operate(&mut self, program_name: &str, operation: Operation, logger: &Logger) -> Response {
+        let cause = self.order_cause.clone();
+        let Some(program) = self.programs.get_mut(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        // a later order takes over the run time limit
+        program.run_deadline = None;
+        match program.admit(operation, cause) {
+            Admission::Apply => {
+                let result = program.apply(operation);
+                order_response(result, program, operation.action(), logger)
+            }
+            Admission::Queued(ongoing) => {
+                log_info!(logger, program = program_name;
+                    "{operation} queued behind the ongoing {ongoing}");
+                Response::Success(format!(
+                    "{operation} of {program_name} queued behind the ongoing {ongoing}"
+                ))
+            }
+            Admission::Duplicate => Response::Error(format!(
+                "operation already in progress: {operation} of {program_name}"
+            )),
+        }
+    }
+
+    /// use for user manual reload of a program, its processes are sent its reload_signal
+    pub fn reload_program(&mut self, program_name: &str, logger: &Logger) -> Response {
+        let Some(program) = self.programs.get_mut(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        let Some(signal) = program.config.reload_signal.clone() else {
+            return Response::Error(format!("{program_name} has no reload_signal"));
+        };
+        let result = program.reload(&signal);
+        order_response(result, program, "reloading", logger)
+    }
+
+    /// use for user manual pause of a program's process
+    pub fn pause_program(&mut self, program_name: &str, logger: &Logger) -> Response {
+        let Some(program) = self.programs.get_mut(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        let result = program.pause();
+        order_response(result, program, "pausing", logger)
+    }
+
+    /// use for user manual resume of a program's paused process
+    pub fn resume_program(&mut self, program_name: &str, logger: &Logger) -> Response {
+        let Some(program) = self.programs.get_mut(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        let result = program.resume();
+        order_response(result, program, "resuming", logger)
+    }
+
+    /// use for user manual maintenance command, the monitor stop reacting to the exits
+    /// of the program while its maintenance mode is on
+    pub fn set_maintenance(
+        &mut self,
+        program_name: &str,
+        enabled: bool,
+        logger: &Logger,
+    ) -> Response {
+        let Some(program) = self.programs.get_mut(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        program.set_maintenance(enabled);
+        let mode = if enabled { "on" } else { "off" };
+        log_info!(logger, program = program_name; "maintenance mode {mode}");
+        Response::Success(format!("maintenance mode {mode} for {program_name}"))
+    }
+
+    /// use for user manual pause-restarts and resume-restarts commands, the processes of the
+    /// program that exit are not restarted until resumed or the duration elapsed
+    pub fn set_restarts_paused(
+        &mut self,
+        program_name: &str,
+        paused: bool,
+        duration: Option<Duration>,
+        logger: &Logger,
+    ) -> Response {
+        let Some(program) = self.programs.get_mut(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        program.set_restarts_paused(paused, duration);
+        let change = match (paused, duration) {
+            (true, Some(duration)) => format!("restarts paused for {}s", duration.as_secs()),
+            (true, None) => "restarts paused".to_owned(),
+            (false, _) => "restarts resumed".to_owned(),
+        };
+        log_info!(logger, program = program_name; "{change}");
+        Response::Success(format!("{change} for {program_name}"))
+    }
+
+    /// the last lines written on stdout by the processes of a program
+    pub(crate) fn history(&self, program_name: &str, lines: usize) -> Response {
+        let Some(program) = self.programs.get(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        Response::History(program.history(lines))
+    }
+
+    /// the lines written on stdout by the processes of a program that match a regex,
+    /// they are filtered here so the whole history isn't sent
+    pub(crate) fn grep(&self, program_name: &str, pattern: &str) -> Response {
+        let Some(program) = self.programs.get(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        match Regex::new(pattern) {
+            Ok(pattern) => Response::Matches(program.grep(&pattern)),
+            Err(error) => Response::Error(format!("invalid pattern: {error}")),
+        }
+    }
+
+    /// return a receiver getting every line written by the processes of a program from now on,
+    /// or the response explaining why the program can't be attached to
+    pub(crate) fn attach(&self, program_name: &str) -> Result<Receiver<OutputLine>, Response> {
+        let Some(program) = self.programs.get(program_name) else {
+            return Err(self.unknown_program(program_name));
+        };
+        program.attach().ok_or_else(|| {
+            Response::Error(format!(
+                "the output of `{program_name}` can't be attached to"
+            ))
         })
-        .collect::<Vec<String>>()
-        .join(", ")
+    }
+
+    /// use for writing to the stdin of the processes of a program
+    pub(crate) fn write_stdin(
+        &mut self,
+        program_name: &str,
+        data: &str,
+        logger: &Logger,
+    ) -> Response {
+        let Some(program) = self.programs.get_mut(program_name) else {
+            return self.unknown_program(program_name);
+        };
+        let result = program.write_stdin(data.as_bytes());
+        order_response(result, program, "writing to", logger)
+    }
+
+    /// the error answering an order given to a program that doesn't exist, suggesting the
+    /// closest names and listing the available programs
+    fn unknown_program(&self, program_name: &str) -> Response {
+        let mut names: Vec<&str> = self.programs.keys().map(String::as_str).collect();
+        names.sort();
+        let mut message = format!("no program is named `{program_name}`");
+        let suggestions = suggest(program_name, &names);
+        if !suggestions.is_empty() {
+            message.push_str(&format!(", did you mean {}?", suggestions.join(" or ")));
+        }
+        if names.is_empty() {
+            message.push_str(", no program is configured");
+        } else {
+            message.push_str(&format!("\navailable programs: {}", names.join(", ")));
+        }
+        Response::Error(message)
+    }
+
+    /// use for user manual status command, the query select and order the programs
+    pub fn get_status(&mut self, query: &StatusQuery) -> Response {
+        let mut programs: Vec<ProgramStatus> = self
+            .programs
+            .iter_mut()
+            .filter(|(_, program)| query.tag.as_ref().is_none_or(|tag| program.has_tag(tag)))
+            .map(|(_, program)| {
+                let mut status = ProgramStatus::from(&mut *program);
+                if query.verbose {
+                    status.summary = Some(program.summary());
+                }
+                status
+            })
+            .collect();
+        // an invalid program has no tags
+        if query.tag.is_none() {
+            programs.extend(
+                self.invalid_programs
+                    .iter()
+                    .map(|(name, error)| ProgramStatus::invalid(name.to_owned(), error.to_owned())),
+            );
+        }
+        Response::Status(query.apply(programs))
+    }
 }
 
-pub fn new_shared_process_manager(config: &Config) -> SharedProcessManager {
-    Arc::new(RwLock::new(ProgramManager::new(config)))
+/// turn the outcome of an order given to a program into a response, detailing the
+/// outcome of every process when it didn't fully succeed
+fn order_response(
+    result: Result<(), OrderError>,
+    program: &Program,
+    action: &str,
+    logger: &Logger,
+) -> Response {
+    let (errors, total_failure) = match result {
+        Ok(_) => return Response::Success(format!("{action} task succeed")),
+        Err(OrderError::PartialSuccess(errors)) => (errors, false),
+        Err(OrderError::TotalFailure(errors)) => (errors, true),
+    };
+    let report = OrderReport {
+        program: program.name.to_owned(),
+        action: action.to_owned(),
+        total_failure,
+        replicas: (0..program.process_vec.len())
+            .map(|replica| ReplicaResult {
+                replica,
+                error: errors
+                    .iter()
+                    .find(|(index, _)| *index == replica)
+                    .map(|(_, error)| error.into()),
+            })
+            .collect(),
+    };
+    if total_failure {
+        log_error!(logger, "{}", report.summary());
+    } else {
+        log_warn!(logger, "{}", report.summary());
+    }
+    Response::OrderFailed(report)
 }
 
-/* -------------------------------------------------------------------------- */
-/*                             From Implementation                            */
-/* -------------------------------------------------------------------------- */
-impl From<&mut ProgramManager> for Response {
-    fn from(val: &mut ProgramManager) -> Self {
-        Response::Status(
-            val.programs
-                .iter_mut()
-                .map(|(_, program)| program.into())
-                .collect(),
-        )
+/// the names close enough to the given one to be a typo of it, the closest first
+fn suggest<'a>(name: &str, names: &[&'a str]) -> Vec<&'a str> {
+    // a third of the name can be wrong, at least one character
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, &str)> = names
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    close.sort();
+    close.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// the levenshtein distance between two strings, ignoring the case
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
     }
+    previous[b.len()]
+}
+
+pub fn new_shared_process_manager(
+    config: &Config,
+    event_bus: SharedEventBus,
+) -> SharedProcessManager {
+    Arc::new(RwLock::new(ProgramManager::new(config, event_bus)))
 }
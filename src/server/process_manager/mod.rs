@@ -2,15 +2,36 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
-use crate::config::ProgramConfig;
+use crate::{
+    config::{MaintenanceWindow, ProgramConfig},
+    event::SharedEventBus,
+};
+use lifecycle::LifecycleLog;
+use readiness::ReadinessProbe;
+use watch::Watcher;
 
 /* -------------------------------------------------------------------------- */
 /*                                   Module                                   */
 /* -------------------------------------------------------------------------- */
+mod cgroup;
+mod child;
+mod clock;
+mod handover;
+#[cfg(any(test, feature = "test-harness"))]
+mod harness;
+mod lifecycle;
+mod listener;
 pub(super) mod manager;
+mod output;
 mod process;
 mod program;
+mod readiness;
 mod state;
+mod state_file;
+#[cfg(test)]
+mod tests;
+mod wakeup;
+mod watch;
 
 /* -------------------------------------------------------------------------- */
 /*                                   Struct                                   */
@@ -21,14 +42,17 @@ mod state;
 #[derive(Debug, Default)]
 struct Process {
     /// the handle to the process
-    child: Option<std::process::Child>,
+    child: Option<Child>,
+
+    /// the ends of the pipes to the child kept by the daemon
+    pipes: ChildPipes,
 
     /// the time when the process was launched, used to determine the
     /// transition from starting to running
-    started_since: Option<std::time::SystemTime>,
+    started_since: Option<tcl::time::MonotonicTime>,
 
     /// use to determine when to abort the child
-    time_since_shutdown: Option<std::time::SystemTime>,
+    time_since_shutdown: Option<tcl::time::MonotonicTime>,
 
     /// store the state of a given process
     state: ProcessState,
@@ -37,12 +61,107 @@ struct Process {
     config: ProgramConfig,
 
     /// current number of restart, it increment only when the process was
-    /// restarted when it was consider to be in a starting state
+    /// restarted when it was consider to be in a starting state (backoff), it is
+    /// reset once the process reach the running state or is started manually
     number_of_restart: u32,
+
+    /// number of time the process was restarted by the autorestart policy after
+    /// exiting, this is not limited
+    number_of_autorestart: u32,
+
+    /// how the last child ended, kept after the child is cleaned
+    exit: Option<tcl::message::ProcessExit>,
+
+    /// whenever the child was suspended with SIGSTOP, a paused process keep its
+    /// state until it is resumed or exits
+    paused: bool,
+
+    /// the cgroup the children are moved to, if the daemon has a cgroup parent
+    cgroup: Option<std::path::PathBuf>,
+
+    /// the stdout history of the program, shared by its processes
+    history: SharedOutputHistory,
+
+    /// the index of the process in its program, written along its output in the history
+    replica: usize,
+
+    /// the events bus the process is served from, if it is an event listener
+    event_bus: Option<SharedEventBus>,
+
+    /// why the process last changed state, None if it never did
+    last_cause: Option<TransitionCause>,
+
+    /// how the last child spawned tells it is ready, if the program has a readiness protocol
+    readiness: Option<ReadinessProbe>,
+}
+
+/// the child of a process, spawned by the daemon or adopted from the daemon it replaced
+#[derive(Debug)]
+enum Child {
+    Spawned(std::process::Child),
+    /// a child of the daemon replaced on a reexec, only known by its pid. how it ended is
+    /// kept once it is reaped
+    Adopted {
+        pid: u32,
+        exit: Option<tcl::message::ProcessExit>,
+    },
+    /// a fake child following a script, instead of a spawned one (test-harness)
+    #[cfg(any(test, feature = "test-harness"))]
+    Scripted(harness::ScriptedChild),
+}
+
+/// the ends of the pipes to a child kept by the daemon, the output ones are duplicates of
+/// the ones read by the capture threads so they can be handed over on a reexec
+#[derive(Debug, Default)]
+struct ChildPipes {
+    stdin: Option<std::fs::File>,
+    stdout: Option<std::os::fd::OwnedFd>,
+    stderr: Option<std::os::fd::OwnedFd>,
+}
+
+/// what made a process change state, kept to tell why it stopped or restarted
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum TransitionCause {
+    /// the program was started with the daemon
+    #[default]
+    Autostart,
+
+    /// the program was added or changed by a config reload
+    Reload,
+
+    /// an order given by a client, e.g. `stop by client#1 127.0.0.1`
+    Order(String),
+
+    /// the child exited, or was killed after the stop time
+    Exit,
+
+    /// the child stayed up for the start time
+    StartTime,
+
+    /// the child told it is ready (readiness)
+    Ready,
+
+    /// the child exited while starting and is started again
+    BackoffRetry,
+
+    /// the child exited and was started again by the autorestart policy
+    Autorestart,
+
+    /// the daemon is shutting down
+    Shutdown,
+
+    /// the previous process was restarted and is running, this one is restarted next
+    RollingRestart,
+
+    /// the program was given a limited run time by a run order, which elapsed
+    RunTimeElapsed,
+
+    /// the files watched by the program changed
+    WatchedFilesChanged,
 }
 
 /// Represent the state of a given process
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 enum ProcessState {
     /// the default state, has never been started.
     #[default]
@@ -77,6 +196,40 @@ enum ProcessState {
     Unknown,
 }
 
+/// what the processes of a program should be doing, the orders edit it and the monitor
+/// conform the processes to it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum DesiredState {
+    /// every process of the program (numprocs of them) should be running,
+    /// the autorestart policy and the start retries apply
+    Running,
+
+    /// no process of the program should be running, the exits are not reacted to
+    #[default]
+    Stopped,
+}
+
+/// an order that takes a while to take effect (e.g. a stop waits for the processes to exit),
+/// the conflicting orders given meanwhile are queued behind it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Start,
+    Stop,
+    Restart,
+    RollingRestart,
+}
+
+/// how an order is taken while another one is taking effect
+#[derive(Debug)]
+enum Admission {
+    /// applied right away
+    Apply,
+    /// queued behind the given ongoing order
+    Queued(Operation),
+    /// the same order is already ongoing or queued
+    Duplicate,
+}
+
 /// represent the error that can occur while performing action on the process class
 #[derive(Debug)]
 pub enum ProcessError {
@@ -90,6 +243,14 @@ pub enum ProcessError {
     NoCommand,
     CouldNotSpawnChild(std::io::Error),
     FailedToCreateRedirection(std::io::Error),
+    /// the cgroup of the program couldn't be created, configured or joined
+    Cgroup(std::io::Error),
+    /// the stdin of the child couldn't be written to, it isn't read fast enough if it would block
+    Stdin(std::io::Error),
+    /// a secret of the env (`!file` or `!env`) couldn't be read
+    Secret(std::io::Error),
+    /// the readiness file couldn't be removed or the readiness pipe created
+    Readiness(std::io::Error),
 }
 
 /* --------------------------------- Program -------------------------------- */
@@ -99,8 +260,78 @@ struct Program {
     name: String,
     config: ProgramConfig,
     process_vec: Vec<Process>,
+    /// the processes left over by a decrease of numprocs, stopped then forgotten once exited
+    retiring: Vec<Process>,
+    /// what the processes should be doing, reconciled by the monitor
+    desired: DesiredState,
+    /// while on, the monitor keep track of the processes but doesn't react to their exits
+    /// (no autorestart, no escalation to fatal)
+    maintenance: bool,
+    /// while on, the processes that exit are left as is instead of being restarted by the
+    /// autorestart policy or a backoff retry, the running ones are untouched
+    restarts_paused: bool,
+    /// when the restarts are resumed, if their pause was given a duration
+    restarts_resume_at: Option<tcl::time::MonotonicTime>,
+    /// the maintenance window the program is in, applied to the config of its processes
+    window: Option<MaintenanceWindow>,
+    /// the supervision history of the program if it was asked for in the config,
+    /// an error to report if it couldn't be opened
+    lifecycle_log: Option<Result<LifecycleLog, std::io::Error>>,
+    /// the dedicated cgroup of the program, if the daemon has a cgroup parent
+    cgroup: Option<std::path::PathBuf>,
+    /// whenever a disk guardrail is currently crossed, it is reported once when crossed
+    guardrail_crossed: bool,
+    /// the last lines written by the processes on their stdout
+    history: SharedOutputHistory,
+    /// the state of each process as last published
+    reported_states: Vec<ProcessState>,
+    /// why the processes are started for the first time
+    start_cause: TransitionCause,
+    /// the process being restarted by a rolling restart, the next ones are restarted
+    /// once it is running
+    rolling_restart: Option<usize>,
+    /// the order still taking effect, the conflicting ones wait for it in the queue
+    ongoing: Option<Operation>,
+    /// the orders applied by the monitor one after the other once the ongoing one took
+    /// effect, along with the cause of their transitions
+    queued: std::collections::VecDeque<(Operation, TransitionCause)>,
+    /// when the program is stopped, if a run order limited its run time
+    run_deadline: Option<tcl::time::MonotonicTime>,
+    /// notice the changes of the watched files, if the program has some
+    watcher: Option<Watcher>,
+}
+
+/// the last lines written by the processes of a program on their stdout, the oldest
+/// ones are dropped once the capacity (historylines) is reached
+#[derive(Debug, Default)]
+struct OutputHistory {
+    capacity: usize,
+    lines: std::collections::VecDeque<tcl::message::OutputLine>,
+    /// the lines are also pushed to the clients attached to the program
+    attached: Option<tokio::sync::broadcast::Sender<tcl::message::OutputLine>>,
+    /// the bytes per second taken in (outputratelimit), unlimited if None
+    rate_limit: Option<usize>,
+    /// when the current rate limit window started and the bytes taken in since
+    window_start: Option<std::time::Instant>,
+    window_bytes: usize,
+    /// the lines left out of the history and of the attached clients by the rate limit
+    skipped_lines: u64,
+    /// the stderr lines waiting for the monitor to write them to the daemon log along with
+    /// their replica, None if they aren't mirrored (stderr_to_daemon_log)
+    mirrored: Option<Vec<(usize, String)>>,
+    /// when the current mirror window started and the lines mirrored since
+    mirror_window_start: Option<std::time::Instant>,
+    mirror_window_lines: usize,
+    /// the stderr lines left out of the daemon log since the mirrored lines were last taken
+    mirror_skipped: u64,
 }
 
+/// the history of a program, fed by the threads capturing the output of its processes
+type SharedOutputHistory = std::sync::Arc<std::sync::Mutex<OutputHistory>>;
+
+/// the outcome of the reconciliation of a process along with the pid of its child before it
+type ReconcileOutcome = (Option<u32>, Result<(), ProcessError>);
+
 /// Represent the error that can occur on each process when asking for manual task
 #[derive(Debug)]
 enum ProgramError {
@@ -112,11 +343,12 @@ enum ProgramError {
 #[derive(Debug)]
 enum OrderError {
     /// represent a partial success of an order given to a program it contain the
-    /// list of error that happened and garanties that at least one successful operation occurred
-    PartialSuccess(Vec<ProgramError>),
+    /// list of error that happened, along with the index of the process they happened on,
+    /// and garanties that at least one successful operation occurred
+    PartialSuccess(Vec<(usize, ProgramError)>),
 
     /// represent a total failure of the order that was given, no operation performed on the child where successful
-    TotalFailure(Vec<ProgramError>),
+    TotalFailure(Vec<(usize, ProgramError)>),
 }
 
 /* ----------------------------- ProgramManager ----------------------------- */
@@ -129,6 +361,57 @@ pub(super) struct ProgramManager {
     /// the place were programs go we they are no longer part of the config
     /// and we nee to wait for them to shutdown
     purgatory: std::collections::HashMap<String, Program>,
+
+    /// the time the monitor wait between two iterations when nothing happen
+    refresh_period: std::time::Duration,
+
+    /// wake the monitor before the end of the refresh period
+    wakeup: std::sync::Arc<MonitorWakeup>,
+
+    /// the manual stops, kept across reloads and restarts of the daemon
+    state_file: StateFile,
+
+    /// the directory the programs cgroups are created in, if any
+    cgroup_parent: Option<std::path::PathBuf>,
+
+    /// the bus the event listener programs are served from
+    event_bus: SharedEventBus,
+
+    /// the cause of the order being given, the orders it queues are attributed to it
+    order_cause: TransitionCause,
+
+    /// the programs left out of the config at startup because they are invalid, along with why
+    invalid_programs: std::collections::BTreeMap<String, String>,
+
+    /// the cascade restart going down the programs depending on the one restarted, if any
+    cascade: Option<Cascade>,
+}
+
+/// a restart of a program followed by the ones of the programs depending on it, one
+/// program after the other
+#[derive(Debug)]
+struct Cascade {
+    /// the program being restarted, the next one is restarted once it took effect
+    current: String,
+    /// the programs left to restart, each one after the ones it depends on
+    next: std::collections::VecDeque<String>,
+    /// the origin of the order, e.g. `cascade restart by client#1 127.0.0.1`
+    origin: String,
+}
+
+/// the state of the daemon that outlive it, saved to the state file of the server config
+#[derive(Debug, Default)]
+struct StateFile {
+    path: std::path::PathBuf,
+    /// the programs stopped by an operator
+    stopped: std::collections::BTreeSet<String>,
+}
+
+/// let the monitor sleep until the next refresh or until something need its attention
+/// (an order was given, a child exited), it can be notified from a signal handler
+#[derive(Debug)]
+struct MonitorWakeup {
+    pipe: tcl::mylibc::SelfPipe,
 }
 
 /// a sharable version of a process manager, it can be passe through thread safely + use in a concurrent environment without fear thank Rust !
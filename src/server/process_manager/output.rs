@@ -0,0 +1,292 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{OutputHistory, SharedOutputHistory};
+use regex::Regex;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tcl::{message::OutputLine, time::now_rfc3339};
+use tokio::sync::broadcast::{self, Receiver};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the bytes of a line kept in the history, the rest is only written to the redirection file
+const MAX_LINE_LENGTH: usize = 1024;
+
+/// how many lines a slow attached client can fall behind before missing some
+const ATTACH_BUFFER_SIZE: usize = 256;
+
+/// the period over which the output rate limit is applied
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+
+/// the stderr lines of a program written to the daemon log per rate limit window, so a
+/// failing program can't flood it
+const MIRROR_RATE_LIMIT: usize = 10;
+
+/// the syslog facility of the output of the programs (user-level messages)
+const SYSLOG_FACILITY_USER: u8 = 1;
+
+/// the syslog severities of the stdout and the stderr lines, as defined by RFC 5424
+pub(super) const SYSLOG_SEVERITY_INFO: u8 = 6;
+pub(super) const SYSLOG_SEVERITY_ERROR: u8 = 3;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// where the output lines of a process go: its redirection file, if any, and the
+/// history of its program (only kept for stdout, stderr is only pushed to the attached clients)
+pub(super) struct OutputSink {
+    pub(super) target: Option<OutputTarget>,
+    pub(super) history: SharedOutputHistory,
+    pub(super) replica: usize,
+    pub(super) kept: bool,
+    /// prefix the lines with the time they were read (logtimestamps)
+    pub(super) timestamps: bool,
+}
+
+/// where an output stream is copied to besides the history (stdout, stderr)
+pub(super) enum OutputTarget {
+    /// a redirection file the lines are appended to
+    File(File),
+
+    /// syslog, the lines are sent one by one
+    Syslog(SyslogStream),
+}
+
+/// the lines of an output stream sent to syslog as `<priority>tag: line`, tagged with the
+/// name of the executable
+pub(super) struct SyslogStream {
+    socket: UnixDatagram,
+    path: PathBuf,
+    priority: u8,
+    tag: String,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl OutputSink {
+    pub(super) fn write(&mut self, line: &[u8]) {
+        let stamped;
+        let line = match self.timestamps {
+            true => {
+                stamped = [format!("{} ", now_rfc3339()).as_bytes(), line].concat();
+                &stamped
+            }
+            false => line,
+        };
+        match self.target.as_mut() {
+            Some(OutputTarget::File(file)) => {
+                let _ = file.write_all(line);
+            }
+            Some(OutputTarget::Syslog(syslog)) => syslog.send(line),
+            None => {}
+        }
+        self.history
+            .lock()
+            .unwrap()
+            .push(self.replica, line, self.kept);
+    }
+}
+
+impl SyslogStream {
+    /// connect to the syslog socket, the lines are sent with the given severity (RFC 5424)
+    pub(super) fn connect(path: &Path, severity: u8, tag: &str) -> Result<Self, std::io::Error> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self {
+            socket,
+            path: path.to_owned(),
+            priority: SYSLOG_FACILITY_USER * 8 + severity,
+            tag: tag.to_owned(),
+        })
+    }
+
+    /// send a line, reconnecting once if the syslog daemon was restarted since the last one.
+    /// the line is lost if it still can't be sent
+    fn send(&mut self, line: &[u8]) {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let datagram = [
+            format!("<{}>{}: ", self.priority, self.tag).as_bytes(),
+            line,
+        ]
+        .concat();
+        if self.socket.send(&datagram).is_ok() {
+            return;
+        }
+        if let Ok(syslog) = Self::connect(&self.path, self.priority % 8, &self.tag) {
+            *self = syslog;
+            let _ = self.socket.send(&datagram);
+        }
+    }
+}
+
+impl OutputHistory {
+    pub(super) fn new(capacity: usize, rate_limit: Option<usize>, mirror_stderr: bool) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::with_capacity(capacity),
+            attached: Some(broadcast::channel(ATTACH_BUFFER_SIZE).0),
+            rate_limit,
+            mirrored: mirror_stderr.then(Vec::new),
+            ..Default::default()
+        }
+    }
+
+    /// start or stop mirroring the stderr lines to the daemon log, the pending ones are
+    /// dropped when it stops
+    pub(super) fn set_mirror_stderr(&mut self, mirror_stderr: bool) {
+        if mirror_stderr != self.mirrored.is_some() {
+            self.mirrored = mirror_stderr.then(Vec::new);
+            self.mirror_skipped = 0;
+        }
+    }
+
+    /// take the stderr lines waiting to be written to the daemon log along with the number of
+    /// lines left out by the rate limit since the last time
+    pub(super) fn take_mirrored(&mut self) -> (Vec<(usize, String)>, u64) {
+        let lines = self
+            .mirrored
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        (lines, std::mem::take(&mut self.mirror_skipped))
+    }
+
+    /// change the number of lines kept, the oldest ones are dropped if there are too many
+    pub(super) fn set_capacity(&mut self, capacity: usize) {
+        let excess = self.lines.len().saturating_sub(capacity);
+        self.lines.drain(..excess);
+        self.capacity = capacity;
+    }
+
+    /// push a line written by the given process to the attached clients, adding it to the
+    /// history if it is kept, the oldest one is then dropped when full. the line is skipped
+    /// if the program already wrote more than its rate limit
+    pub(super) fn push(&mut self, replica: usize, line: &[u8], kept: bool) {
+        if !self.take_in(line.len()) {
+            self.skipped_lines += 1;
+            return;
+        }
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = OutputLine {
+            time: now_rfc3339(),
+            replica,
+            line: String::from_utf8_lossy(&line[..line.len().min(MAX_LINE_LENGTH)]).into_owned(),
+            program: None,
+        };
+        // the lines that aren't kept are the stderr ones
+        if !kept {
+            self.mirror(replica, &line.line);
+        }
+        if let Some(attached) = &self.attached {
+            // an error only means that nobody is attached
+            let _ = attached.send(line.clone());
+        }
+        if !kept || self.capacity == 0 {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// queue a stderr line for the daemon log if it is mirrored and the program didn't
+    /// already mirror too many lines in the current window
+    fn mirror(&mut self, replica: usize, line: &str) {
+        let Some(mirrored) = self.mirrored.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        if self
+            .mirror_window_start
+            .is_none_or(|start| now.duration_since(start) >= RATE_LIMIT_WINDOW)
+        {
+            self.mirror_window_start = Some(now);
+            self.mirror_window_lines = 0;
+        }
+        if self.mirror_window_lines == MIRROR_RATE_LIMIT {
+            self.mirror_skipped += 1;
+            return;
+        }
+        self.mirror_window_lines += 1;
+        mirrored.push((replica, line.to_owned()));
+    }
+
+    /// count the bytes against the rate limit of the current window, false if they exceed it.
+    /// the first line of a window is always taken in, whatever its length
+    fn take_in(&mut self, bytes: usize) -> bool {
+        let Some(rate_limit) = self.rate_limit else {
+            return true;
+        };
+        let now = Instant::now();
+        if self
+            .window_start
+            .is_none_or(|start| now.duration_since(start) >= RATE_LIMIT_WINDOW)
+        {
+            self.window_start = Some(now);
+            self.window_bytes = 0;
+        }
+        if self.window_bytes > 0 && self.window_bytes + bytes > rate_limit {
+            return false;
+        }
+        self.window_bytes += bytes;
+        true
+    }
+
+    /// the lines left out by the rate limit since the program was created
+    pub(super) fn skipped_lines(&self) -> u64 {
+        self.skipped_lines
+    }
+
+    /// return a receiver getting every line written from now on
+    pub(super) fn attach(&self) -> Option<Receiver<OutputLine>> {
+        self.attached.as_ref().map(broadcast::Sender::subscribe)
+    }
+
+    /// the last lines of the history, oldest first
+    pub(super) fn last(&self, lines: usize) -> Vec<OutputLine> {
+        let skip = self.lines.len().saturating_sub(lines);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+
+    /// the lines of the history matching the pattern, oldest first
+    pub(super) fn matching(&self, pattern: &Regex) -> Vec<OutputLine> {
+        self.lines
+            .iter()
+            .filter(|line| pattern.is_match(&line.line))
+            .cloned()
+            .collect()
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// copy the output of a child line by line to its sink until the child close it,
+/// to be run on a dedicated thread
+pub(super) fn capture(pipe: impl Read, mut sink: OutputSink) {
+    let mut pipe = BufReader::new(pipe);
+    let mut line = Vec::new();
+    while let Some(line) = read_line(&mut pipe, &mut line) {
+        sink.write(line);
+    }
+}
+
+/// read the next line of the pipe, None once it is closed
+pub(super) fn read_line<'a>(pipe: &mut impl BufRead, line: &'a mut Vec<u8>) -> Option<&'a [u8]> {
+    line.clear();
+    match pipe.read_until(b'\n', line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
+}
@@ -2,63 +2,84 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
-use super::{Process, ProcessError, ProcessState};
-use crate::config::{ProgramConfig, Signal};
-use std::os::unix::process::CommandExt;
-#[cfg(unix)]
-use std::os::unix::process::ExitStatusExt;
+use super::{
+    cgroup, clock, listener,
+    output::{
+        self, OutputSink, OutputTarget, SyslogStream, SYSLOG_SEVERITY_ERROR, SYSLOG_SEVERITY_INFO,
+    },
+    Child, ChildPipes, DesiredState, Process, ProcessError, ProcessState, ReadinessProbe,
+    SharedOutputHistory, TransitionCause,
+};
+use crate::{
+    config::{ProgramConfig, Readiness, Redirection, Signal},
+    event::SharedEventBus,
+    stats::STATS,
+};
+use std::io::Write;
+use std::os::{
+    fd::{AsRawFd, OwnedFd},
+    unix::{
+        self,
+        fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
+        process::CommandExt,
+    },
+};
 use std::{
+    ffi::c_int,
     fmt::Display,
-    fs,
-    process::{Command, ExitStatus, Stdio},
-    time::SystemTime,
+    fs::{self, File},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Mutex, PoisonError},
+    thread,
 };
+use tcl::{message::ProcessExit, mylibc, time::MonotonicTime};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the mode of the directories created for the redirection files without an outputdirmode
+const DEFAULT_OUTPUT_DIR_MODE: mylibc::mode_t = 0o755;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Static                                   */
+/* -------------------------------------------------------------------------- */
+/// held while the umask of the daemon is changed
+static UMASK_LOCK: Mutex<()> = Mutex::new(());
 
 /* -------------------------------------------------------------------------- */
 /*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
 impl Process {
-    pub(super) fn new(config: ProgramConfig) -> Self {
+    pub(super) fn new(
+        config: ProgramConfig,
+        cgroup: Option<PathBuf>,
+        history: SharedOutputHistory,
+        replica: usize,
+        event_bus: Option<SharedEventBus>,
+    ) -> Self {
         Self {
             config,
+            cgroup,
+            history,
+            replica,
+            event_bus,
             ..Default::default()
         }
     }
 
-    /// Attempts to retrieve the child process's exit code.
+    /// Attempts to retrieve how the child process ended.
     ///
     /// # Returns
-    /// - `Ok(Some(i32))` if the child has exited and an exit code is available.
+    /// - `Ok(Some(ProcessExit))` if the child has exited, with its exit code or the signal
+    ///   that terminated it.
     /// - `Ok(None)` if the child is still running.
     /// - `Err(ProcessError::NoChild)` if the child process was not launched.
     /// - `Err(ProcessError::ExitStatusNotFound)` if the exit status could not be read.
-    ///
-    /// # Note
-    /// On Unix systems, if the process was terminated by a signal, the signal number is returned as the exit code.
-    pub(super) fn get_exit_code(&mut self) -> Result<Option<i32>, ProcessError> {
+    pub(super) fn get_exit(&mut self) -> Result<Option<ProcessExit>, ProcessError> {
         let child = self.child.as_mut().ok_or(ProcessError::NoChild)?;
-
-        match child.try_wait() {
-            Ok(Some(status)) => Ok(Some(Self::extract_exit_code(status))),
-            Ok(None) => Ok(None),
-            Err(e) => Err(ProcessError::ExitStatusNotFound(e)),
-        }
-    }
-
-    #[cfg(unix)]
-    fn extract_exit_code(status: ExitStatus) -> i32 {
-        status.code().unwrap_or_else(|| {
-            status
-                .signal()
-                .expect("Process terminated by signal, but no signal number found")
-        })
-    }
-
-    #[cfg(not(unix))]
-    fn extract_exit_code(status: ExitStatus) -> i32 {
-        status
-            .code()
-            .expect("Exit code should always be available on non-unix systems")
+        child.try_wait().map_err(ProcessError::ExitStatusNotFound)
     }
 
     /// Returns the child process ID if the process is active.
@@ -85,7 +106,13 @@ impl Process {
         }
     }
 
-    /// Attempts to send a SIGKILL to the child process.
+    /// Returns the id of the child process if there is one, without updating the state
+    pub(super) fn pid(&self) -> Option<u32> {
+        self.child.as_ref().map(Child::id)
+    }
+
+    /// Attempts to send a SIGKILL to the child process, it stays stopping until the child is
+    /// reaped by the monitor.
     ///
     /// # Errors
     ///
@@ -96,13 +123,8 @@ impl Process {
             .as_mut()
             .ok_or(ProcessError::NoChild)
             .and_then(|child| {
-                child
-                    .kill()
-                    .map_err(|error| {
-                        self.state = ProcessState::Stopping;
-                        ProcessError::CantKillProcess(error)
-                    })
-                    .map(|_| self.state = ProcessState::Stopped)
+                self.state = ProcessState::Stopping;
+                child.kill().map_err(ProcessError::CantKillProcess)
             })
     }
 
@@ -116,14 +138,9 @@ impl Process {
     ///
     /// * `program_config` - The configuration for the program, containing the grace period
     pub(super) fn its_time_to_kill_the_child(&self) -> bool {
-        self.time_since_shutdown
-            .map(|shutdown_time| {
-                SystemTime::now()
-                    .duration_since(shutdown_time)
-                    .map(|elapsed| elapsed.as_secs() > self.config.time_to_stop_gracefully)
-                    .unwrap_or(false)
-            })
-            .unwrap_or(false)
+        self.time_since_shutdown.is_some_and(|shutdown_time| {
+            clock::elapsed(shutdown_time).as_secs() > self.config.time_to_stop_gracefully
+        })
     }
 
     /// Determines if the program has completed its starting phase.
@@ -132,13 +149,17 @@ impl Process {
     /// - `Some(true)` if the process has started and the time elapsed since it started exceeds the configured start-up time.
     /// - `Some(false)` if the process has started but hasn't exceeded the start-up time yet.
     /// - `None` if no starting time were found (AKA the program is not running).
+    ///
+    /// A process with a readiness protocol is no longer starting once its child told it is ready.
     pub(super) fn is_no_longer_starting(&self) -> Option<bool> {
-        self.started_since.map(|start_time| {
-            SystemTime::now()
-                .duration_since(start_time)
-                .map(|elapsed| elapsed.as_secs() > self.config.time_to_start)
-                .unwrap_or(false)
-        })
+        match &self.readiness {
+            Some(probe) => self
+                .started_since
+                .map(|_| matches!(probe, ReadinessProbe::Ready)),
+            None => self
+                .started_since
+                .map(|start_time| clock::elapsed(start_time).as_secs() > self.config.time_to_start),
+        }
     }
 
     /// Send the given signal to the child, starting the graceful shutdown timer.
@@ -149,53 +170,97 @@ impl Process {
     /// - There is no child process (`ProcessError::NoChild`)
     /// - The signal sending operation fails (`ProcessError::SignalError`)
     pub(super) fn send_signal(&mut self, signal: &Signal) -> Result<(), ProcessError> {
-        let child = self.child.as_ref().ok_or(ProcessError::NoChild)?;
+        let child = self.child.as_mut().ok_or(ProcessError::NoChild)?;
         let signal_number = Self::signal_to_libc(signal);
-        let result = unsafe { libc::kill(child.id() as libc::pid_t, signal_number as libc::c_int) };
+        child.signal(signal_number).map_err(ProcessError::Signal)?;
 
-        if result == -1 {
-            return Err(ProcessError::Signal(std::io::Error::last_os_error()));
+        // a paused process couldn't handle the signal
+        if self.paused {
+            self.resume()?;
         }
 
-        self.time_since_shutdown = Some(SystemTime::now());
+        self.time_since_shutdown = Some(clock::now());
         self.started_since = None;
         self.state = ProcessState::Stopping;
         Ok(())
     }
 
+    /// Suspend the child with SIGSTOP, it keeps its state until it is resumed
+    ///
+    /// # Errors
+    ///
+    /// - `ProcessError::NoChild` if there were no child process
+    /// - `ProcessError::Signal` if the signal couldn't be sent
+    pub(super) fn pause(&mut self) -> Result<(), ProcessError> {
+        self.signal_child(mylibc::SIGSTOP)?;
+        self.paused = true;
+        Ok(())
+    }
+
+    /// Resume a child suspended by `pause` with SIGCONT
+    ///
+    /// # Errors
+    ///
+    /// - `ProcessError::NoChild` if there were no child process
+    /// - `ProcessError::Signal` if the signal couldn't be sent
+    pub(super) fn resume(&mut self) -> Result<(), ProcessError> {
+        self.signal_child(mylibc::SIGCONT)?;
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Send the reload signal of the program to the child, it keeps its state
+    ///
+    /// # Errors
+    ///
+    /// - `ProcessError::NoChild` if there were no child process
+    /// - `ProcessError::Signal` if the signal couldn't be sent
+    pub(super) fn reload(&mut self, signal: &Signal) -> Result<(), ProcessError> {
+        self.signal_child(Self::signal_to_libc(signal))
+    }
+
+    fn signal_child(&mut self, signal: c_int) -> Result<(), ProcessError> {
+        let child = self.child.as_mut().ok_or(ProcessError::NoChild)?;
+        child.signal(signal).map_err(ProcessError::Signal)
+    }
+
+    pub(super) fn is_paused(&self) -> bool {
+        self.paused
+    }
+
     /// Convert our Signal enum to libc signal constants
-    fn signal_to_libc(signal: &Signal) -> libc::c_int {
+    pub(super) fn signal_to_libc(signal: &Signal) -> c_int {
         match signal {
-            Signal::SIGABRT => libc::SIGABRT,
-            Signal::SIGALRM => libc::SIGALRM,
-            Signal::SIGBUS => libc::SIGBUS,
-            Signal::SIGCHLD => libc::SIGCHLD,
-            Signal::SIGCONT => libc::SIGCONT,
-            Signal::SIGFPE => libc::SIGFPE,
-            Signal::SIGHUP => libc::SIGHUP,
-            Signal::SIGILL => libc::SIGILL,
-            Signal::SIGINT => libc::SIGINT,
-            Signal::SIGKILL => libc::SIGKILL,
-            Signal::SIGPIPE => libc::SIGPIPE,
+            Signal::SIGABRT => mylibc::SIGABRT,
+            Signal::SIGALRM => mylibc::SIGALRM,
+            Signal::SIGBUS => mylibc::SIGBUS,
+            Signal::SIGCHLD => mylibc::SIGCHLD,
+            Signal::SIGCONT => mylibc::SIGCONT,
+            Signal::SIGFPE => mylibc::SIGFPE,
+            Signal::SIGHUP => mylibc::SIGHUP,
+            Signal::SIGILL => mylibc::SIGILL,
+            Signal::SIGINT => mylibc::SIGINT,
+            Signal::SIGKILL => mylibc::SIGKILL,
+            Signal::SIGPIPE => mylibc::SIGPIPE,
             #[cfg(target_os = "linux")]
-            Signal::SIGPOLL => libc::SIGPOLL,
-            Signal::SIGPROF => libc::SIGPROF,
-            Signal::SIGQUIT => libc::SIGQUIT,
-            Signal::SIGSEGV => libc::SIGSEGV,
-            Signal::SIGSTOP => libc::SIGSTOP,
-            Signal::SIGSYS => libc::SIGSYS,
-            Signal::SIGTERM => libc::SIGTERM,
-            Signal::SIGTRAP => libc::SIGTRAP,
-            Signal::SIGTSTP => libc::SIGTSTP,
-            Signal::SIGTTIN => libc::SIGTTIN,
-            Signal::SIGTTOU => libc::SIGTTOU,
-            Signal::SIGUSR1 => libc::SIGUSR1,
-            Signal::SIGUSR2 => libc::SIGUSR2,
-            Signal::SIGURG => libc::SIGURG,
-            Signal::SIGVTALRM => libc::SIGVTALRM,
-            Signal::SIGXCPU => libc::SIGXCPU,
-            Signal::SIGXFSZ => libc::SIGXFSZ,
-            Signal::SIGWINCH => libc::SIGWINCH,
+            Signal::SIGPOLL => mylibc::SIGPOLL,
+            Signal::SIGPROF => mylibc::SIGPROF,
+            Signal::SIGQUIT => mylibc::SIGQUIT,
+            Signal::SIGSEGV => mylibc::SIGSEGV,
+            Signal::SIGSTOP => mylibc::SIGSTOP,
+            Signal::SIGSYS => mylibc::SIGSYS,
+            Signal::SIGTERM => mylibc::SIGTERM,
+            Signal::SIGTRAP => mylibc::SIGTRAP,
+            Signal::SIGTSTP => mylibc::SIGTSTP,
+            Signal::SIGTTIN => mylibc::SIGTTIN,
+            Signal::SIGTTOU => mylibc::SIGTTOU,
+            Signal::SIGUSR1 => mylibc::SIGUSR1,
+            Signal::SIGUSR2 => mylibc::SIGUSR2,
+            Signal::SIGURG => mylibc::SIGURG,
+            Signal::SIGVTALRM => mylibc::SIGVTALRM,
+            Signal::SIGXCPU => mylibc::SIGXCPU,
+            Signal::SIGXFSZ => mylibc::SIGXFSZ,
+            Signal::SIGWINCH => mylibc::SIGWINCH,
         }
     }
 
@@ -207,8 +272,13 @@ impl Process {
     pub(super) fn update_state(&mut self) -> Result<(), ProcessError> {
         use ProcessError as PE;
         use ProcessState as PS;
-        match self.get_exit_code() {
+        match self.get_exit() {
+            // a paused process can't make progress, its state is frozen until it is resumed
+            Ok(None) if self.paused => Ok(()),
             Ok(result) => {
+                if result.is_some() {
+                    self.exit = result;
+                }
                 match self.state {
                     PS::Starting => self.update_starting(result),
                     PS::Running => self.update_running(result),
@@ -234,57 +304,152 @@ impl Process {
                 | PE::CantKillProcess(_)
                 | PE::Signal(_)
                 | PE::CouldNotSpawnChild(_)
-                | PE::FailedToCreateRedirection(_) => unreachable!(),
+                | PE::FailedToCreateRedirection(_)
+                | PE::Cgroup(_)
+                | PE::Stdin(_)
+                | PE::Secret(_)
+                | PE::Readiness(_) => unreachable!(),
             },
         }
     }
 
-    /// this function use the config to see if some cleaning or restarting need to happened
-    /// it also call the update_state function before it so that the state we are working
-    /// with are the more accurate possible
+    /// this function conform the process to the desired state of its program, using the config
+    /// to see if some cleaning or restarting need to happened. it also call the update_state
+    /// function before it so that the state we are working with are the more accurate possible
     ///
     /// Returns:
     /// - `Ok(())` if the exit_status could be acquire without issue and the state
-    ///     and change that need to be done were done.
+    ///   and change that need to be done were done.
     /// - `Err(ProcessError::ExitStatusNotFound)` if the exit status could not be read.
     /// - `Err(ProcessError::NoCommand)` if the command argument is empty.
     /// - `Err(ProcessError::FailedToCreateRedirection)` if the redirection argument couldn't be accessed found or create.
     /// - `Err(ProcessError::CouldNotSpawnChild)` if the child was not able to be spawned
+    /// - `Err(ProcessError::Secret)` if a secret of the env couldn't be read
     /// - `Err(ProcessError::NoChild)` if there were no child process
     /// - `Err(ProcessError::CantKillProcess)` if we couldn't kill the process
-    pub(super) fn react_to_program_state(
+    pub(super) fn reconcile(
         &mut self,
         program_name: &str,
+        desired: DesiredState,
     ) -> Result<(), ProcessError> {
         self.update_state()?;
+        use DesiredState as DS;
         use ProcessState as PS;
-        match self.state {
-            PS::NeverStartedYet => self.react_never_started_yet(),
-            PS::Backoff => self.react_backoff(program_name),
-            PS::Stopping => self.react_stopping(),
-            PS::ExitedExpectedly => self.react_expected_exit(),
-            PS::ExitedUnExpectedly => self.react_unexpected_exit(),
-            PS::Fatal | PS::Starting | PS::Running | PS::Stopped => Ok(()),
-            PS::Unknown => unreachable!(
+        match (desired, self.state) {
+            (_, PS::Stopping) => self.react_stopping(),
+            (DS::Running, PS::NeverStartedYet | PS::Stopped) => self.start(),
+            (DS::Running, PS::Backoff) => self.react_backoff(program_name),
+            (DS::Running, PS::ExitedExpectedly) => self.react_expected_exit(),
+            (DS::Running, PS::ExitedUnExpectedly) => self.react_unexpected_exit(),
+            (DS::Running, PS::Fatal | PS::Starting | PS::Running) => Ok(()),
+            (DS::Stopped, PS::Starting | PS::Running) => self.react_unwanted(),
+            (DS::Stopped, PS::Backoff) => {
+                self.state = PS::Stopped;
+                Ok(())
+            }
+            (
+                DS::Stopped,
+                PS::NeverStartedYet
+                | PS::Stopped
+                | PS::ExitedExpectedly
+                | PS::ExitedUnExpectedly
+                | PS::Fatal,
+            ) => Ok(()),
+            (_, PS::Unknown) => unreachable!(
                 "as long as we return the error of update_state call before this match block"
             ),
         }
     }
 
+    /// run f with the umask of the program set, for the files it creates on its behalf
+    fn with_umask<T>(&self, f: impl FnOnce(&Self) -> T) -> T {
+        let Some(umask) = self.config.umask else {
+            return f(self);
+        };
+        // the umask is shared by the processes spawned at the same time
+        let _guard = UMASK_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let original_umask = mylibc::umask(umask);
+        let result = f(self);
+        mylibc::umask(original_umask);
+        result
+    }
+
+    /// whenever reconciling the process toward the desired state may spawn a child
+    pub(super) fn may_spawn(&self, desired: DesiredState) -> bool {
+        use ProcessState as PS;
+        desired == DesiredState::Running
+            && matches!(
+                self.state,
+                PS::NeverStartedYet
+                    | PS::Stopped
+                    | PS::Backoff
+                    | PS::ExitedExpectedly
+                    | PS::ExitedUnExpectedly
+            )
+    }
+
+    /// whenever the child exited on its own and the process waits for the monitor to start
+    /// it again, by a backoff retry or the autorestart policy
+    pub(super) fn awaits_restart(&self) -> bool {
+        use ProcessState as PS;
+        matches!(
+            self.state,
+            PS::Backoff | PS::ExitedExpectedly | PS::ExitedUnExpectedly
+        )
+    }
+
+    /// stop the child with the stop signal (killing it if the signal can't be sent) for the
+    /// monitor to start it again, an inactive process is rearmed to be started right away
+    pub(super) fn restart(&mut self) -> Result<(), ProcessError> {
+        if self.is_active() {
+            let stop_signal = self.config.stop_signal.clone();
+            self.send_signal(&stop_signal).or_else(|_| self.kill())
+        } else {
+            self.rearm();
+            Ok(())
+        }
+    }
+
+    /// give the process a fresh start when it is asked for, it will be started with a new
+    /// start retries budget whatever the way it ended
+    pub(super) fn rearm(&mut self) {
+        if !self.is_active() {
+            self.state = ProcessState::Stopped;
+            self.number_of_restart = 0;
+        }
+    }
+
     /// this function attempt to spawn a child if successful it will set the appropriate state
     /// # Returns
     /// - `Ok(())` if the child was spawn successfully
     /// - `Err(ProcessError::NoCommand)` if the command argument is empty.
     /// - `Err(ProcessError::FailedToCreateRedirection)` if the redirection argument couldn't be accessed found or create.
     /// - `Err(ProcessError::CouldNotSpawnChild)` if the child was not able to be spawned
+    /// - `Err(ProcessError::Secret)` if a secret of the env couldn't be read
     pub(super) fn start(&mut self) -> Result<(), ProcessError> {
-        let mut split_command = self.config.command.split_whitespace();
-        let program = split_command.next().ok_or(ProcessError::NoCommand)?;
-        let original_umask: Option<libc::mode_t> = self.config.umask.map(Self::set_umask);
-        let mut command = Command::new(program);
+        #[cfg(any(test, feature = "test-harness"))]
+        if let Some(script) = super::harness::take_script() {
+            return self.start_scripted(script);
+        }
+        let (program, arguments) = self
+            .config
+            .argv
+            .split_first()
+            .ok_or(ProcessError::NoCommand)?;
+        // the executable found when the config was loaded, the program keeps the name it was given
+        let mut command = match &self.config.executable {
+            Some(executable) => {
+                let mut command = Command::new(executable);
+                command.arg0(program);
+                command
+            }
+            None => Command::new(program),
+        };
 
-        command.envs(&self.config.environmental_variable_to_set);
-        command.args(split_command);
+        for (name, value) in &self.config.environmental_variable_to_set {
+            command.env(name, value.resolve().map_err(ProcessError::Secret)?);
+        }
+        command.args(arguments);
         if let Some(dir) = &self.config.working_directory {
             command.current_dir(dir);
         }
@@ -293,60 +458,297 @@ impl Process {
             command.uid(user.uid);
             command.gid(user.gid);
         }
-        self.set_command_redirection(&mut command)
+        let redirections = self
+            .with_umask(Self::open_redirections)
             .map_err(ProcessError::FailedToCreateRedirection)?;
+        command.stdin(Stdio::piped());
+        command.stdout(Self::output_stdio(&self.config.stdout_redirection));
+        // the stdout is duplicated on the stderr once the child set them up (redirect_stderr)
+        if self.config.redirect_stderr {
+            command.stderr(Stdio::null());
+            unsafe {
+                command.pre_exec(|| mylibc::dup2(1, 2));
+            }
+        } else {
+            command.stderr(Self::output_stdio(&self.config.stderr_redirection));
+        }
+        // kept open until the child is spawned, the child join the cgroup before exec
+        let cgroup_procs = match &self.cgroup {
+            Some(path) => Some(cgroup::prepare(path, &self.config).map_err(ProcessError::Cgroup)?),
+            None => None,
+        };
+        if let Some(procs) = cgroup_procs.as_ref().map(AsRawFd::as_raw_fd) {
+            unsafe {
+                command.pre_exec(move || cgroup::join_from_child(procs));
+            }
+        }
+        // the child is forked with the umask of the daemon, it sets its own
+        if let Some(umask) = self.config.umask {
+            unsafe {
+                command.pre_exec(move || {
+                    mylibc::umask(umask);
+                    Ok(())
+                });
+            }
+        }
+        // the child leads its own process group so a ^C on the terminal of the daemon doesn't
+        // reach it, the daemon stops it in the order of the priorities
+        unsafe {
+            command.pre_exec(|| mylibc::setpgid(0, 0));
+        }
+        let (readiness, readiness_pipe) = match &self.config.readiness {
+            Some(readiness) => {
+                let (probe, pipe) = ReadinessProbe::new(readiness, self.replica)
+                    .map_err(ProcessError::Readiness)?;
+                (Some(probe), pipe)
+            }
+            None => (None, None),
+        };
+        if let Some(path) = readiness.as_ref().and_then(ReadinessProbe::notify_socket) {
+            command.env("NOTIFY_SOCKET", path);
+        }
+        // the child gets the write end of the readiness pipe at the configured descriptor
+        if let (Some(pipe), Some(Readiness::Fd(fd))) = (&readiness_pipe, &self.config.readiness) {
+            let (pipe, fd) = (pipe.as_raw_fd(), *fd);
+            unsafe {
+                command.pre_exec(move || {
+                    mylibc::dup2(pipe, fd)?;
+                    mylibc::set_inheritable(fd)
+                });
+            }
+        }
 
-        let child = command.spawn().map_err(ProcessError::CouldNotSpawnChild)?;
+        let mut child = command.spawn().map_err(ProcessError::CouldNotSpawnChild)?;
+        // the child holds the only write end left, so its closing is seen
+        drop(readiness_pipe);
+        self.readiness = readiness;
+        STATS.record_spawn(self.state != ProcessState::NeverStartedYet);
 
-        if let Some(umask) = original_umask {
-            Self::set_umask(umask);
-        }
+        let pipes = ChildPipes {
+            stdin: child
+                .stdin
+                .take()
+                .map(|stdin| File::from(OwnedFd::from(stdin))),
+            stdout: child.stdout.take().map(OwnedFd::from),
+            stderr: child.stderr.take().map(OwnedFd::from),
+        };
+        self.serve_pipes(pipes, redirections, self.event_bus.is_some());
 
-        self.child = Some(child);
+        self.child = Some(Child::Spawned(child));
         self.state = ProcessState::Starting;
-        self.started_since = Some(SystemTime::now());
+        self.started_since = Some(clock::now());
         self.time_since_shutdown = None;
 
         Ok(())
     }
 
-    /// Set new umask and return the previous value
-    fn set_umask(new_umask: libc::mode_t) -> libc::mode_t {
-        unsafe { libc::umask(new_umask) }
+    /// read the output of the child on dedicated threads, the stdout goes through the daemon
+    /// to feed the history of the program and an event listener also talks to the daemon
+    /// through it. a stream discarded or inherited has no pipe. duplicates of the pipes are
+    /// kept to hand them over on a reexec
+    pub(super) fn serve_pipes(
+        &mut self,
+        pipes: ChildPipes,
+        (stdout_target, stderr_target): (Option<OutputTarget>, Option<OutputTarget>),
+        serve_events: bool,
+    ) {
+        let sink = OutputSink {
+            target: stdout_target,
+            history: self.history.clone(),
+            replica: self.replica,
+            kept: true,
+            timestamps: self.config.log_timestamps,
+        };
+        if let Some(stderr) = pipes.stderr {
+            self.pipes.stderr = stderr.try_clone().ok();
+            let sink = OutputSink {
+                target: stderr_target,
+                history: self.history.clone(),
+                replica: self.replica,
+                kept: false,
+                timestamps: self.config.log_timestamps,
+            };
+            thread::spawn(move || output::capture(File::from(stderr), sink));
+        }
+        match (pipes.stdin, pipes.stdout, &self.event_bus) {
+            (Some(stdin), Some(stdout), Some(event_bus)) if serve_events => {
+                self.pipes.stdin = stdin.try_clone().ok();
+                self.pipes.stdout = stdout.try_clone().ok();
+                let events = event_bus.subscribe();
+                let listened = self.config.events.clone();
+                thread::spawn(move || {
+                    listener::serve(File::from(stdout), stdin, events, &listened, sink)
+                });
+            }
+            (stdin, stdout, _) => {
+                // a write to the stdin mustn't block the daemon if the child doesn't read it
+                if let Some(stdin) = stdin {
+                    let _ = mylibc::set_nonblocking(stdin.as_raw_fd());
+                    self.pipes.stdin = Some(stdin);
+                }
+                if let Some(stdout) = stdout {
+                    self.pipes.stdout = stdout.try_clone().ok();
+                    thread::spawn(move || output::capture(File::from(stdout), sink));
+                }
+            }
+        }
     }
 
-    fn set_command_redirection(&self, command: &mut Command) -> Result<(), std::io::Error> {
-        match self.config.stdout_redirection.as_ref() {
-            Some(stdout) => {
-                let file = fs::OpenOptions::new()
-                    .append(true)
-                    .create(true)
-                    .open(stdout)?;
-                command.stdout(file);
+    /// open the files or connect to the syslog the stdout and stderr of the child have to be
+    /// copied to, the missing directories of the files are created
+    pub(super) fn open_redirections(
+        &self,
+    ) -> Result<(Option<OutputTarget>, Option<OutputTarget>), std::io::Error> {
+        let open = |redirection: &Option<Redirection>, severity: u8| match redirection {
+            Some(file @ Redirection::File(_)) => {
+                let path = file.file_path(self.replica).unwrap_or_default();
+                self.open_redirection_file(Path::new(&path))
+                    .map(|file| Some(OutputTarget::File(file)))
             }
-            None => {
-                command.stdout(Stdio::null());
+            Some(Redirection::Syslog) => {
+                let socket = self
+                    .config
+                    .syslog_socket
+                    .as_deref()
+                    .unwrap_or(Path::new(""));
+                let executable = self.config.argv.first().map(String::as_str).unwrap_or("");
+                let tag = Path::new(executable)
+                    .file_name()
+                    .map_or(executable.into(), |name| name.to_string_lossy());
+                SyslogStream::connect(socket, severity, &tag)
+                    .map(|syslog| Some(OutputTarget::Syslog(syslog)))
             }
+            _ => Ok(None),
+        };
+        Ok((
+            open(&self.config.stdout_redirection, SYSLOG_SEVERITY_INFO)?,
+            open(&self.config.stderr_redirection, SYSLOG_SEVERITY_ERROR)?,
+        ))
+    }
+
+    /// open a redirection file to append to it, creating it and its missing directories.
+    /// the file is created with the logfile_mode (under the umask of the program) and given
+    /// the logfile_owner, an existing file is given the logfile_mode. it is opened
+    /// close-on-exec, like every file std opens, so the children only inherit their pipes
+    fn open_redirection_file(&self, path: &Path) -> Result<File, std::io::Error> {
+        self.create_parent_directories(path)?;
+        let mut options = fs::OpenOptions::new();
+        options.append(true).create_new(true);
+        if let Some(mode) = self.config.log_file_mode {
+            options.mode(mode);
         }
-        match self.config.stderr_redirection.as_ref() {
-            Some(stderr) => {
-                let file = fs::OpenOptions::new()
-                    .append(true)
-                    .create(true)
-                    .open(stderr)?;
-                command.stderr(file);
+        let file = match options.open(path) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let file = fs::OpenOptions::new().append(true).open(path)?;
+                if let Some(mode) = self.config.log_file_mode {
+                    file.set_permissions(fs::Permissions::from_mode(mode))?;
+                }
+                return Ok(file);
             }
-            None => {
-                command.stderr(Stdio::null());
+            file => file?,
+        };
+        if let Some(owner) = &self.config.log_file_owner {
+            unix::fs::fchown(&file, Some(owner.uid), Some(owner.gid))?;
+        }
+        Ok(file)
+    }
+
+    /// create the missing parent directories of a redirection file with the outputdirmode,
+    /// they are given to the outputdirowner if there is one
+    fn create_parent_directories(&self, path: &Path) -> Result<(), std::io::Error> {
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+        let missing: Vec<&Path> = parent
+            .ancestors()
+            .take_while(|directory| !directory.as_os_str().is_empty() && !directory.exists())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(
+                self.config
+                    .output_dir_mode
+                    .unwrap_or(DEFAULT_OUTPUT_DIR_MODE),
+            )
+            .create(parent)?;
+        if let Some(owner) = &self.config.output_dir_owner {
+            for directory in missing {
+                unix::fs::chown(directory, Some(owner.uid), Some(owner.gid))?;
             }
         }
         Ok(())
     }
 
+    /// how the stdout and stderr of the child are set up: read by the daemon unless they
+    /// are discarded or inherited from it
+    fn output_stdio(redirection: &Option<Redirection>) -> Stdio {
+        match redirection {
+            Some(Redirection::Discard) => Stdio::null(),
+            Some(Redirection::Inherit) => Stdio::inherit(),
+            _ => Stdio::piped(),
+        }
+    }
+
+    /// write to the stdin of the child, failing instead of blocking if the child doesn't read it
+    ///
+    /// # Errors
+    ///
+    /// - `ProcessError::NoChild` if there were no child process, or its stdin is not the daemon's
+    ///   (the one of an event listener is used by the events protocol)
+    /// - `ProcessError::Stdin` if the stdin couldn't be written to
+    pub(super) fn write_stdin(&mut self, data: &[u8]) -> Result<(), ProcessError> {
+        let stdin = self
+            .pipes
+            .stdin
+            .as_mut()
+            .filter(|_| self.event_bus.is_none())
+            .ok_or(ProcessError::NoChild)?;
+        stdin.write_all(data).map_err(ProcessError::Stdin)
+    }
+
     /// this function simply set the child to None
     /// not if this is use while the child is alive it will create a zombie process
     pub(super) fn clean_child(&mut self) {
         self.child = None;
+        self.pipes = ChildPipes::default();
+        self.paused = false;
+    }
+
+    /// tell if the process reached a state it won't leave without an order, given the desired
+    /// state of its program
+    pub(super) fn is_settled(&self, desired: DesiredState) -> bool {
+        use crate::config::AutoRestart as AR;
+        use ProcessState as PS;
+        match self.state {
+            PS::Starting | PS::Running | PS::Stopping | PS::Backoff | PS::Unknown => false,
+            PS::Fatal => true,
+            PS::NeverStartedYet | PS::Stopped => desired == DesiredState::Stopped,
+            PS::ExitedExpectedly => {
+                desired == DesiredState::Stopped || self.config.auto_restart != AR::Always
+            }
+            PS::ExitedUnExpectedly => {
+                desired == DesiredState::Stopped || self.config.auto_restart == AR::Never
+            }
+        }
+    }
+
+    /// the exit code reporting the failure of the process, if it failed
+    pub(super) fn failure_code(&self) -> Option<i32> {
+        use ProcessState as PS;
+        match self.state {
+            PS::ExitedUnExpectedly | PS::Fatal => {
+                // like a shell, a child terminated by a signal reports 128 + the signal
+                match self.exit {
+                    Some(ProcessExit::Exited(code)) if code != 0 => Some(code),
+                    Some(ProcessExit::ExitedBySignal(signal)) => Some(128 + signal),
+                    _ => Some(1),
+                }
+            }
+            _ => None,
+        }
     }
 
     /// return true if the process still have an active child that mean if his state is either:
@@ -372,6 +774,28 @@ impl Display for ProcessError {
     }
 }
 
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl Display for TransitionCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransitionCause::Autostart => write!(f, "autostart"),
+            TransitionCause::Reload => write!(f, "config reload"),
+            TransitionCause::Order(origin) => write!(f, "{origin}"),
+            TransitionCause::Exit => write!(f, "exit"),
+            TransitionCause::StartTime => write!(f, "start time elapsed"),
+            TransitionCause::Ready => write!(f, "ready"),
+            TransitionCause::BackoffRetry => write!(f, "backoff retry"),
+            TransitionCause::Autorestart => write!(f, "autorestart"),
+            TransitionCause::Shutdown => write!(f, "daemon shutdown"),
+            TransitionCause::RollingRestart => write!(f, "rolling restart"),
+            TransitionCause::RunTimeElapsed => write!(f, "run time elapsed"),
+            TransitionCause::WatchedFilesChanged => write!(f, "watched files changed"),
+        }
+    }
+}
+
 /* -------------------------------------------------------------------------- */
 /*                             From Implementation                            */
 /* -------------------------------------------------------------------------- */
@@ -399,9 +823,14 @@ impl From<&mut Process> for tcl::message::ProcessStatus {
         tcl::message::ProcessStatus {
             pid: val.get_child_id(),
             status: (&val.state).into(),
-            start_time: val.started_since,
-            shutdown_time: val.time_since_shutdown,
+            start_time: val.started_since.map(MonotonicTime::to_system_time),
+            shutdown_time: val.time_since_shutdown.map(MonotonicTime::to_system_time),
             number_of_restart: val.number_of_restart,
+            number_of_autorestart: val.number_of_autorestart,
+            paused: val.paused,
+            last_change: val.last_cause.as_ref().map(ToString::to_string),
+            pending: val.pending(),
+            last_exit: val.exit.filter(|_| !val.is_active()),
         }
     }
 }
@@ -2,60 +2,604 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
-use super::{OrderError, Process, ProcessError, Program, ProgramError};
+use super::{
+    cgroup, Admission, DesiredState, LifecycleLog, Operation, OrderError, OutputHistory, Process,
+    ProcessError, ProcessState, Program, ProgramError, ReconcileOutcome, TransitionCause, Watcher,
+};
 use crate::{
-    config::{Config, ProgramConfig},
-    log_error,
+    config::{Config, GuardrailAction, ProgramConfig, RestartStrategy, Signal},
+    event::SharedEventBus,
+    log_error, log_info, log_warn,
     logger::Logger,
 };
-use std::{error::Error, fmt::Display, thread::sleep, time::Duration};
-use tcl::message::Response;
+use regex::Regex;
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::Display,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+use tcl::{
+    message::{Event, OutputLine, ProgramSummary},
+    mylibc,
+    time::MonotonicTime,
+};
+use tokio::sync::broadcast::Receiver;
 
 /* -------------------------------------------------------------------------- */
 /*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
 impl Program {
-    pub(super) fn new(name: String, config: ProgramConfig) -> Self {
+    /// create the program, its processes are served from the event bus if it is an event listener
+    pub(super) fn new(
+        name: String,
+        config: ProgramConfig,
+        cgroup_parent: Option<&Path>,
+        event_bus: &SharedEventBus,
+        start_cause: TransitionCause,
+    ) -> Self {
+        let cgroup = cgroup_parent.map(|parent| parent.join(&name));
+        let event_bus = (!config.events.is_empty()).then(|| event_bus.clone());
+        let history = Arc::new(Mutex::new(OutputHistory::new(
+            config.history_lines,
+            config.output_rate_limit,
+            config.stderr_to_daemon_log,
+        )));
         let mut process_vec = Vec::with_capacity(config.number_of_process);
 
-        for _ in 0..config.number_of_process {
-            process_vec.push(Process::new(config.to_owned()));
+        for replica in 0..config.number_of_process {
+            process_vec.push(Process::new(
+                config.to_owned(),
+                cgroup.clone(),
+                history.clone(),
+                replica,
+                event_bus.clone(),
+            ));
         }
+        let reported_states = process_vec.iter().map(|process| process.state).collect();
+
+        let watcher = (!config.watch_paths.is_empty()).then(|| Watcher::new(&config.watch_paths));
+        let lifecycle_log = config.lifecycle_log.then(|| LifecycleLog::open(&name));
+        let desired = match config.start_at_launch {
+            true => DesiredState::Running,
+            false => DesiredState::Stopped,
+        };
 
         Self {
             name,
             config,
             process_vec,
+            retiring: Vec::new(),
+            desired,
+            maintenance: false,
+            restarts_paused: false,
+            restarts_resume_at: None,
+            window: None,
+            lifecycle_log,
+            cgroup,
+            guardrail_crossed: false,
+            history,
+            reported_states,
+            start_cause,
+            rolling_restart: None,
+            ongoing: None,
+            queued: VecDeque::new(),
+            run_deadline: None,
+            watcher,
         }
     }
 
-    /// update self state
-    pub(super) fn monitor(&mut self, logger: &Logger) {
-        self.process_vec.iter_mut().for_each(|process| {
-            if let Err(e) = process.react_to_program_state(&self.name) {
-                log_error!(logger, "{e}");
+    /// note why the processes changed state since the last call, for the given cause or for the
+    /// one inferred from the transition, logging the changes and returning them as events
+    pub(super) fn record_transitions(
+        &mut self,
+        cause: Option<&TransitionCause>,
+        logger: &Logger,
+    ) -> Vec<Event> {
+        let mut events = Vec::new();
+        for (replica, process) in self.process_vec.iter_mut().enumerate() {
+            let reported = &mut self.reported_states[replica];
+            if process.state == *reported {
+                continue;
             }
-        });
+            let from = std::mem::replace(reported, process.state);
+            let cause = match cause {
+                Some(cause) => cause.clone(),
+                None => infer_cause(from, process.state, process, &self.start_cause),
+            };
+            let pid = process.pid();
+            log_info!(logger, program = self.name, pid = pid;
+                "process {replica}: {from:?} -> {:?} ({cause})", process.state);
+            events.push(Event::ProcessState {
+                program: self.name.to_owned(),
+                replica,
+                from: (&from).into(),
+                to: (&process.state).into(),
+                pid,
+                cause: cause.to_string(),
+            });
+            process.last_cause = Some(cause);
+        }
+        events
+    }
+
+    /// the config facts telling what the daemon does with the program
+    pub(super) fn summary(&self) -> ProgramSummary {
+        ProgramSummary {
+            command: self.config.command.to_owned(),
+            numprocs: self.config.number_of_process,
+            autorestart: format!("{:?}", self.config.auto_restart).to_lowercase(),
+            exitcodes: self.config.expected_exit_code.to_owned(),
+            expected_signals: self
+                .config
+                .expected_signals
+                .iter()
+                .map(|signal| format!("{signal:?}"))
+                .collect(),
+            stopsignal: format!("{:?}", self.config.stop_signal),
+        }
+    }
+
+    /// the last lines written by the processes on their stdout, oldest first
+    pub(super) fn history(&self, lines: usize) -> Vec<OutputLine> {
+        self.history.lock().unwrap().last(lines)
+    }
+
+    /// return a receiver getting every line written by the processes from now on
+    pub(super) fn attach(&self) -> Option<Receiver<OutputLine>> {
+        self.history.lock().unwrap().attach()
+    }
+
+    /// the lines of the stdout history matching the pattern, oldest first
+    pub(super) fn grep(&self, pattern: &Regex) -> Vec<OutputLine> {
+        self.history.lock().unwrap().matching(pattern)
+    }
+
+    /// update self state, only conforming the processes to the desired state outside of maintenance,
+    /// returning the state changes as events. a stop already sent is still escalated to a
+    /// SIGKILL during maintenance
+    pub(super) fn monitor(&mut self, logger: &Logger) -> Vec<Event> {
+        self.expire_restarts_pause(logger);
+        self.conform_to_maintenance_window(logger);
+        if self.maintenance {
+            self.process_vec.iter_mut().for_each(|process| {
+                let pid = process.pid();
+                let result = process.update_state().and_then(|()| match process.state {
+                    ProcessState::Stopping => process.react_stopping(),
+                    _ => Ok(()),
+                });
+                if let Err(e) = result {
+                    log_error!(logger, program = self.name, pid = pid; "{e}");
+                }
+            });
+        } else {
+            for (pid, result) in self.reconcile() {
+                if let Err(e) = result {
+                    log_error!(logger, program = self.name, pid = pid; "{e}");
+                }
+            }
+        }
+        self.retire_surplus(logger);
+        self.log_mirrored_stderr(logger);
+        let mut events = self.record_transitions(None, logger);
+        if !self.maintenance {
+            events.extend(self.advance_rolling_restart(logger));
+            events.extend(self.advance_orders(logger));
+            events.extend(self.enforce_run_deadline(logger));
+            events.extend(self.restart_on_watched_change(logger));
+        }
+        self.record_lifecycle(logger);
+        events
+    }
+
+    /// switch the program to a new config that can be hot applied, the running processes take
+    /// it as is and the number of processes is conformed to it
+    pub(super) fn update_in_place(
+        &mut self,
+        config: &ProgramConfig,
+        event_bus: &SharedEventBus,
+        logger: &Logger,
+    ) {
+        log_info!(logger, program = self.name; "config updated in place");
+        self.process_vec
+            .iter_mut()
+            .for_each(|process| process.config = config.to_owned());
+        if config.history_lines != self.config.history_lines {
+            self.history
+                .lock()
+                .unwrap()
+                .set_capacity(config.history_lines);
+        }
+        if config.stderr_to_daemon_log != self.config.stderr_to_daemon_log {
+            self.history
+                .lock()
+                .unwrap()
+                .set_mirror_stderr(config.stderr_to_daemon_log);
+        }
+        if config.watch_paths != self.config.watch_paths {
+            self.watcher =
+                (!config.watch_paths.is_empty()).then(|| Watcher::new(&config.watch_paths));
+        }
+        self.scale(config, event_bus, logger);
+        self.config = config.to_owned();
+    }
+
+    /// conform the number of processes to the new config, the surplus processes (the last
+    /// replicas) are stopped gracefully while the others keep running untouched
+    fn scale(&mut self, config: &ProgramConfig, event_bus: &SharedEventBus, logger: &Logger) {
+        let number_of_process = config.number_of_process;
+
+        if number_of_process < self.process_vec.len() {
+            for process in self.process_vec.drain(number_of_process..) {
+                log_info!(logger, program = self.name, pid = process.pid();
+                    "process {}: retired, numprocs lowered to {number_of_process}", process.replica);
+                self.retiring.push(process);
+            }
+            self.reported_states.truncate(number_of_process);
+            self.retire_surplus(logger);
+        }
+
+        let event_bus = (!config.events.is_empty()).then(|| event_bus.clone());
+        for replica in self.process_vec.len()..number_of_process {
+            let process = Process::new(
+                config.to_owned(),
+                self.cgroup.clone(),
+                self.history.clone(),
+                replica,
+                event_bus.clone(),
+            );
+            self.reported_states.push(process.state);
+            self.process_vec.push(process);
+        }
+
+        if self
+            .rolling_restart
+            .is_some_and(|replica| replica >= number_of_process)
+        {
+            self.rolling_restart = None;
+        }
+    }
+
+    /// stop the processes left over by a decrease of numprocs, forgetting them once exited
+    fn retire_surplus(&mut self, logger: &Logger) {
+        for process in self.retiring.iter_mut() {
+            let pid = process.pid();
+            if let Err(e) = process.reconcile(&self.name, DesiredState::Stopped) {
+                log_error!(logger, program = self.name, pid = pid; "{e}");
+            }
+        }
+        self.retiring.retain(Process::is_active);
+    }
+
+    /// conform every process to the desired state, returning the outcome for each of them
+    /// along with the pid of its child before the reconciliation. the processes that may be
+    /// spawned are reconciled in parallel, spawnconcurrency at a time. while the restarts
+    /// are paused, the processes that exited are left as is
+    fn reconcile(&mut self) -> Vec<ReconcileOutcome> {
+        let (name, desired) = (self.name.as_str(), self.desired);
+        let held = self.restarts_paused && desired == DesiredState::Running;
+        let reconcile = move |process: &mut Process| {
+            let pid = process.pid();
+            // the exit is noticed before it is reacted to
+            if held {
+                if let Err(e) = process.update_state() {
+                    return (pid, Err(e));
+                }
+                if process.awaits_restart() {
+                    return (pid, Ok(()));
+                }
+            }
+            (pid, process.reconcile(name, desired))
+        };
+        if self.config.spawn_concurrency <= 1 {
+            return self.process_vec.iter_mut().map(reconcile).collect();
+        }
+
+        let mut outcomes: Vec<Option<ReconcileOutcome>> =
+            self.process_vec.iter().map(|_| None).collect();
+        let (mut spawning, others): (Vec<_>, Vec<_>) = self
+            .process_vec
+            .iter_mut()
+            .enumerate()
+            .partition(|(_, process)| process.may_spawn(desired));
+        for (index, process) in others {
+            outcomes[index] = Some(reconcile(process));
+        }
+        for batch in spawning.chunks_mut(self.config.spawn_concurrency) {
+            thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter_mut()
+                    .map(|(index, process)| {
+                        let process: &mut Process = process;
+                        let handle = scope.spawn(move || reconcile(process));
+                        (*index, handle)
+                    })
+                    .collect();
+                for (index, handle) in handles {
+                    outcomes[index] =
+                        Some(handle.join().expect("a process reconciliation panicked"));
+                }
+            });
+        }
+        outcomes.into_iter().flatten().collect()
+    }
+
+    /// the outcome of the reconciliation that follows an order, the errors of the processes
+    /// the order didn't already reject are added to the results
+    fn reconcile_order(
+        &mut self,
+        mut results: Vec<Result<(), ProgramError>>,
+    ) -> Result<(), OrderError> {
+        for (index, (_, result)) in self.reconcile().into_iter().enumerate() {
+            if let (Err(e), Ok(())) = (result, &results[index]) {
+                results[index] = Err(ProgramError::Process(e));
+            }
+        }
+        determine_order_result(results)
+    }
+
+    /// check the disk guardrails of the program, returning what was crossed when it just
+    /// happened, the program is then stopped if its config ask for it
+    pub(super) fn check_guardrails(&mut self) -> Option<String> {
+        let violations = self.guardrail_violations();
+        let newly_crossed = !violations.is_empty() && !self.guardrail_crossed;
+        self.guardrail_crossed = !violations.is_empty();
+        if !newly_crossed {
+            return None;
+        }
+        let mut message = violations.join(", ");
+        if self.config.guardrail_action == GuardrailAction::Stop
+            && self.desired == DesiredState::Running
+        {
+            message.push_str(", stopping the program");
+            let _ = self.stop();
+        }
+        Some(message)
+    }
+
+    /// describe every disk guardrail the program currently cross
+    fn guardrail_violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+        if let Some(max_bytes) = self.config.output_max_bytes {
+            let mut paths: Vec<String> = self
+                .config
+                .redirections()
+                .into_iter()
+                .flat_map(|redirection| {
+                    (0..self.process_vec.len()).filter_map(|replica| redirection.file_path(replica))
+                })
+                .collect();
+            paths.sort();
+            paths.dedup();
+            for path in paths {
+                match fs::metadata(&path) {
+                    Ok(metadata) if metadata.len() > max_bytes => violations.push(format!(
+                        "{path} is {} bytes (max {max_bytes})",
+                        metadata.len()
+                    )),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(min_free) = self.config.min_free_disk {
+            let directory = self.config.working_directory.as_deref().unwrap_or(".");
+            if let Ok(space) = mylibc::statvfs(Path::new(directory)) {
+                let free = space.available * 100 / space.total.max(1);
+                if free < min_free as u64 {
+                    violations.push(format!(
+                        "{directory} has {free}% of free space (min {min_free}%)"
+                    ));
+                }
+            }
+        }
+        violations
+    }
+
+    /// write the stderr lines mirrored since the last monitoring to the daemon log
+    /// (stderr_to_daemon_log), along with how many were left out by its rate limit
+    fn log_mirrored_stderr(&mut self, logger: &Logger) {
+        let (lines, skipped) = self.history.lock().unwrap().take_mirrored();
+        for (replica, line) in lines {
+            let pid = self.process_vec.get(replica).and_then(Process::pid);
+            log_warn!(logger, program = self.name, pid = pid; "process {replica} stderr: {line}");
+        }
+        if skipped > 0 {
+            log_warn!(logger, program = self.name, pid = None;
+                "{skipped} stderr lines left out of the daemon log, the program writes too many");
+        }
+    }
+
+    /// write the changes that happened since the last monitoring to the lifecycle log,
+    /// a lifecycle log that can't be opened or written to is reported once then dropped
+    fn record_lifecycle(&mut self, logger: &Logger) {
+        let result = match self.lifecycle_log.take() {
+            Some(Ok(mut lifecycle_log)) => lifecycle_log
+                .record(&self.process_vec)
+                .map(|()| lifecycle_log),
+            Some(Err(e)) => Err(e),
+            None => return,
+        };
+        match result {
+            Ok(lifecycle_log) => self.lifecycle_log = Some(Ok(lifecycle_log)),
+            Err(e) => log_error!(logger, program = self.name; "lifecycle log disabled: {e}"),
+        }
+    }
+
+    /// the number of processes that must be running for the program to be healthy
+    fn min_running(&self) -> usize {
+        let number_of_process = self.process_vec.len();
+        self.config
+            .min_running
+            .map_or(number_of_process, |min| min.min(number_of_process))
+    }
+
+    fn running(&self) -> usize {
+        self.process_vec
+            .iter()
+            .filter(|process| process.state == ProcessState::Running)
+            .count()
+    }
+
+    /// a program is healthy when at least min_running of its processes are running
+    pub(super) fn is_healthy(&self) -> bool {
+        self.running() >= self.min_running()
+    }
+
+    /// the program was stopped by an operator before a reload or a restart of the daemon,
+    /// it is not autostarted until an explicit start
+    pub(super) fn keep_stopped(&mut self) {
+        self.desired = DesiredState::Stopped;
+    }
+
+    pub(super) fn set_maintenance(&mut self, enabled: bool) {
+        self.maintenance = enabled;
+    }
+
+    /// pause or resume the restarts of the processes that exit, a pause given a duration is
+    /// lifted by the monitor once it elapsed
+    pub(super) fn set_restarts_paused(&mut self, paused: bool, duration: Option<Duration>) {
+        self.restarts_paused = paused;
+        self.restarts_resume_at = duration
+            .filter(|_| paused)
+            .map(|duration| MonotonicTime::now() + duration);
+    }
+
+    /// apply the maintenance window the local time is in to the config of the processes, the
+    /// autorestart policy and the fatal report of the program are back once it closed
+    fn conform_to_maintenance_window(&mut self, logger: &Logger) {
+        if self.config.maintenance_windows.is_empty() && self.window.is_none() {
+            return;
+        }
+        let window = match mylibc::localtime(SystemTime::now()) {
+            Ok(now) => self.config.maintenance_window(now).cloned(),
+            Err(e) => {
+                log_error!(logger, program = self.name; "can't read the local time: {e}");
+                return;
+            }
+        };
+        if window != self.window {
+            match (&self.window, &window) {
+                (_, Some(opened)) => {
+                    log_info!(logger, program = self.name; "maintenance window {opened} opened")
+                }
+                (Some(closed), None) => {
+                    log_info!(logger, program = self.name; "maintenance window {closed} closed")
+                }
+                (None, None) => {}
+            }
+            self.window = window;
+        }
+
+        let auto_restart = self
+            .window
+            .as_ref()
+            .and_then(|window| window.autorestart.clone())
+            .unwrap_or_else(|| self.config.auto_restart.clone());
+        let report_address = match &self.window {
+            Some(window) if !window.alerts => "",
+            _ => self.config.fatal_state_report_address.as_str(),
+        };
+        for process in self.process_vec.iter_mut() {
+            if process.config.auto_restart != auto_restart {
+                process.config.auto_restart = auto_restart.clone();
+            }
+            if process.config.fatal_state_report_address != report_address {
+                process.config.fatal_state_report_address = report_address.to_owned();
+            }
+        }
+    }
+
+    /// resume the restarts once their pause timed out
+    fn expire_restarts_pause(&mut self, logger: &Logger) {
+        if self
+            .restarts_resume_at
+            .is_some_and(|resume_at| MonotonicTime::now() >= resume_at)
+        {
+            self.set_restarts_paused(false, None);
+            log_info!(logger, program = self.name; "restarts resumed, the pause timed out");
+        }
+    }
+
+    /// tell if one of the processes of the program has the given child
+    pub(super) fn has_child(&self, pid: u32) -> bool {
+        self.process_vec
+            .iter()
+            .chain(&self.retiring)
+            .any(|process| process.pid() == Some(pid))
+    }
+
+    /// tell if the program is labeled with the given tag, ignoring the case
+    pub(super) fn has_tag(&self, tag: &str) -> bool {
+        self.config
+            .tags
+            .iter()
+            .any(|own_tag| own_tag.eq_ignore_ascii_case(tag))
     }
 
     /// in the event of a config reload this will tell if the given program should be kept as is
     pub(super) fn should_be_kept(&self, config: &Config) -> bool {
-        config
-            .get(&self.name)
-            .map_or(false, |cfg| cfg == &self.config)
+        config.get(&self.name) == Some(&self.config)
+    }
+
+    /// in the event of a config reload this will tell if the program can switch to its new
+    /// config without being replaced
+    pub(super) fn can_update_in_place(&self, config: &ProgramConfig) -> bool {
+        *config != self.config && self.config.is_hot_applicable(config)
     }
 
     pub(super) fn shutdown_all_process(&mut self, logger: &Logger) {
-        self.process_vec.iter_mut().for_each(|process| {
+        self.desired = DesiredState::Stopped;
+        let processes = self.process_vec.iter_mut().chain(&mut self.retiring);
+        processes.for_each(|process| {
+            let pid = process.pid();
             if let Err(e) = process.send_signal(&self.config.stop_signal) {
-                log_error!(logger, "{e}");
+                log_error!(logger, program = self.name, pid = pid; "{e}");
                 if let Err(e) = process.kill() {
-                    log_error!(logger, "{e}");
+                    log_error!(logger, program = self.name, pid = pid; "{e}");
                 }
             }
         });
     }
 
+    /// stop every active process with the given signal instead of the configured one,
+    /// used to forward the termination signal received by the daemon
+    pub(super) fn forward_signal(&mut self, signal: &Signal, logger: &Logger) {
+        self.desired = DesiredState::Stopped;
+        self.process_vec
+            .iter_mut()
+            .chain(&mut self.retiring)
+            .filter(|process| process.is_active())
+            .for_each(|process| {
+                let pid = process.pid();
+                if let Err(e) = process.send_signal(signal) {
+                    log_error!(logger, program = self.name, pid = pid; "{e}");
+                }
+            });
+    }
+
+    /// tell if every process reached a state it won't leave without an order
+    pub(super) fn is_settled(&self) -> bool {
+        self.process_vec
+            .iter()
+            .all(|process| process.is_settled(self.desired))
+    }
+
+    /// the exit code reporting the failure of the first process that failed, if any
+    pub(super) fn failure_code(&self) -> Option<i32> {
+        self.process_vec.iter().find_map(Process::failure_code)
+    }
+
+    pub(super) fn has_active_process(&self) -> bool {
+        self.process_vec
+            .iter()
+            .chain(&self.retiring)
+            .any(Process::is_active)
+    }
+
     pub(super) fn clean_inactive_process(&mut self) {
         use super::ProcessState as PS;
         self.process_vec.retain(|process| match process.state {
@@ -71,10 +615,19 @@ impl Program {
     }
 
     pub(super) fn is_clean(&self) -> bool {
-        self.process_vec.is_empty()
+        self.process_vec.is_empty() && self.retiring.is_empty()
+    }
+
+    /// remove the cgroup of a program that is gone, unless it is used again by the program
+    /// that replaced it
+    pub(super) fn remove_cgroup(&self, replaced: bool) {
+        if let (Some(path), false) = (&self.cgroup, replaced) {
+            cgroup::remove(path);
+        }
     }
 
-    /// Attempts to start all processes of this program.
+    /// Wants all processes of this program running, the inactive ones are started right away
+    /// with a new start retries budget.
     ///
     /// # Returns
     /// - `Ok(())` if all processes were started successfully or were already active.
@@ -83,6 +636,7 @@ impl Program {
     /// - `Err(OrderError::TotalFailure(errors))` if all attempts to start processes failed due to
     ///   process errors (no successes and no active processes).
     pub(super) fn start(&mut self) -> Result<(), OrderError> {
+        self.desired = DesiredState::Running;
         let results: Vec<Result<(), ProgramError>> = self
             .process_vec
             .iter_mut()
@@ -90,15 +644,17 @@ impl Program {
                 if process.is_active() {
                     Err(ProgramError::Logic("Process is already active".to_string()))
                 } else {
-                    process.start().map_err(ProgramError::Process)
+                    process.rearm();
+                    Ok(())
                 }
             })
             .collect();
 
-        determine_order_result(results)
+        self.reconcile_order(results)
     }
 
-    /// Attempts to stop all processes of this program.
+    /// Wants all processes of this program stopped, the active ones are sent the stop signal
+    /// right away and none of them is restarted until the next start.
     ///
     /// # Returns
     /// - `Ok(())` if all processes were stopped successfully or were already inactive.
@@ -107,19 +663,65 @@ impl Program {
     /// - `Err(OrderError::TotalFailure(errors))` if all attempts to stop processes failed due to
     ///   process errors (no successes and no inactive processes).
     pub(super) fn stop(&mut self) -> Result<(), OrderError> {
+        self.desired = DesiredState::Stopped;
+        let results: Vec<Result<(), ProgramError>> = self
+            .process_vec
+            .iter()
+            .map(|process| match process.is_active() {
+                true => Ok(()),
+                false => Err(ProgramError::Logic(
+                    "Process is already inactive".to_string(),
+                )),
+            })
+            .collect();
+
+        self.reconcile_order(results)
+    }
+
+    /// Sends the given reload signal to all the active processes of this program, which
+    /// keep their state.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the signal was sent to all processes.
+    /// - `Err(OrderError::PartialSuccess(errors))` if at least one process was signaled,
+    ///   or some were skipped because they are inactive or paused.
+    /// - `Err(OrderError::TotalFailure(errors))` if every attempt to signal a process failed.
+    pub(super) fn reload(&mut self, signal: &Signal) -> Result<(), OrderError> {
+        let results: Vec<Result<(), ProgramError>> = self
+            .process_vec
+            .iter_mut()
+            .map(|process| {
+                if !process.is_active() {
+                    Err(ProgramError::Logic("Process is inactive".to_string()))
+                } else if process.is_paused() {
+                    Err(ProgramError::Logic("Process is paused".to_string()))
+                } else {
+                    process.reload(signal).map_err(ProgramError::Process)
+                }
+            })
+            .collect();
+
+        determine_order_result(results)
+    }
+
+    /// Suspends all the active processes of this program with SIGSTOP.
+    ///
+    /// # Returns
+    /// - `Ok(())` if all processes were paused successfully.
+    /// - `Err(OrderError::PartialSuccess(errors))` if at least one process was paused,
+    ///   or some were skipped because they are inactive or already paused.
+    /// - `Err(OrderError::TotalFailure(errors))` if every attempt to pause a process failed.
+    pub(super) fn pause(&mut self) -> Result<(), OrderError> {
         let results: Vec<Result<(), ProgramError>> = self
             .process_vec
             .iter_mut()
             .map(|process| {
                 if !process.is_active() {
-                    Err(ProgramError::Logic(
-                        "Process is already inactive".to_string(),
-                    ))
+                    Err(ProgramError::Logic("Process is inactive".to_string()))
+                } else if process.is_paused() {
+                    Err(ProgramError::Logic("Process is already paused".to_string()))
                 } else {
-                    process
-                        .send_signal(&self.config.stop_signal)
-                        .or_else(|_| process.kill())
-                        .map_err(ProgramError::Process)
+                    process.pause().map_err(ProgramError::Process)
                 }
             })
             .collect();
@@ -127,63 +729,298 @@ impl Program {
         determine_order_result(results)
     }
 
-    /// Restarts the program by stopping all processes, waiting briefly, monitoring, and then starting processes.
+    /// Resumes all the paused processes of this program with SIGCONT.
     ///
     /// # Returns
-    /// - `Ok(())` if all processes were successfully restarted.
+    /// - `Ok(())` if all processes were resumed successfully.
+    /// - `Err(OrderError::PartialSuccess(errors))` if at least one process was resumed,
+    ///   or some were skipped because they are not paused.
+    /// - `Err(OrderError::TotalFailure(errors))` if every attempt to resume a process failed.
+    pub(super) fn resume(&mut self) -> Result<(), OrderError> {
+        let results: Vec<Result<(), ProgramError>> = self
+            .process_vec
+            .iter_mut()
+            .map(|process| {
+                if !process.is_paused() {
+                    Err(ProgramError::Logic("Process is not paused".to_string()))
+                } else {
+                    process.resume().map_err(ProgramError::Process)
+                }
+            })
+            .collect();
+
+        determine_order_result(results)
+    }
+
+    /// Writes the data to the stdin of every active process of this program.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the data was written to every process.
+    /// - `Err(OrderError::PartialSuccess(errors))` if it was written to at least one process,
+    ///   or some were skipped because they are inactive.
+    /// - `Err(OrderError::TotalFailure(errors))` if every attempt to write failed.
+    pub(super) fn write_stdin(&mut self, data: &[u8]) -> Result<(), OrderError> {
+        let results: Vec<Result<(), ProgramError>> = self
+            .process_vec
+            .iter_mut()
+            .map(|process| {
+                if !process.is_active() {
+                    Err(ProgramError::Logic("Process is inactive".to_string()))
+                } else {
+                    process.write_stdin(data).map_err(ProgramError::Process)
+                }
+            })
+            .collect();
+
+        determine_order_result(results)
+    }
+
+    /// Restarts the program: the active processes are sent the stop signal and are started
+    /// again by the monitor once they stopped, the inactive ones are started right away.
+    ///
+    /// # Returns
+    /// - `Ok(())` if all processes were successfully stopped or started.
     /// - `Err(OrderError::PartialSuccess(errors))` if some processes were restarted successfully, but errors occurred.
     /// - `Err(OrderError::TotalFailure(errors))` if all restart attempts failed.
+    pub(super) fn restart(&mut self) -> Result<(), OrderError> {
+        self.desired = DesiredState::Running;
+        self.rolling_restart = None;
+        let results: Vec<Result<(), ProgramError>> = self
+            .process_vec
+            .iter_mut()
+            .map(|process| process.restart().map_err(ProgramError::Process))
+            .collect();
+
+        self.reconcile_order(results)
+    }
+
+    /// Restarts the processes one at a time: the first one is restarted now, the monitor
+    /// restart the next one once the previous one is running and abort on a fatal one.
     ///
-    /// # Note
-    /// This function includes a 1-second delay between stop and start operations.
-    pub(super) fn restart(&mut self, logger: &Logger) -> Result<(), OrderError> {
-        let stop_results = self.stop();
-        sleep(Duration::from_secs(1));
-        self.monitor(logger);
-        let start_results = self.start();
+    /// # Returns
+    /// - `Ok(())` if the first process was successfully stopped or started.
+    /// - `Err(OrderError::TotalFailure(errors))` if it couldn't be.
+    pub(super) fn rolling_restart(&mut self) -> Result<(), OrderError> {
+        self.desired = DesiredState::Running;
+        let mut results: Vec<Result<(), ProgramError>> =
+            self.process_vec.iter().map(|_| Ok(())).collect();
+        if let Some(first) = self.process_vec.first_mut() {
+            self.rolling_restart = Some(0);
+            results[0] = first.restart().map_err(ProgramError::Process);
+        }
 
-        squish_order_result(stop_results, start_results)
+        self.reconcile_order(results)
     }
-}
 
-/// Combines the results of stopping and starting operations on processes.
-///
-/// # Parameters
-/// - `stop_results`: The result of the stop operation.
-/// - `start_results`: The result of the start operation.
-///
-/// # Returns
-/// - `Ok(())` if both operations succeeded.
-/// - `Err(OrderError::PartialSuccess(errors))` if at least one operation was partially successful or had errors.
-/// - `Err(OrderError::TotalFailure(errors))` if both operations failed completely with no successes.
-fn squish_order_result(
-    stop_results: Result<(), OrderError>,
-    start_results: Result<(), OrderError>,
-) -> Result<(), OrderError> {
-    match (stop_results, start_results) {
-        (Ok(()), Ok(())) => Ok(()),
-        (res1, res2) => {
-            let mut all_errors = Vec::new();
-            let mut all_total_failure = true;
-
-            for res in [res1, res2].into_iter() {
-                match res {
-                    Ok(()) => all_total_failure = false,
-                    Err(OrderError::PartialSuccess(errors)) => {
-                        all_total_failure = false;
-                        all_errors.extend(errors);
-                    }
-                    Err(OrderError::TotalFailure(errors)) => {
-                        all_errors.extend(errors);
-                    }
+    /// carry on the rolling restart once the process being restarted is running, it is
+    /// aborted if the process settled in another state (fatal, exited or stopped) or if
+    /// the program should no longer run
+    fn advance_rolling_restart(&mut self, logger: &Logger) -> Vec<Event> {
+        let Some(replica) = self.rolling_restart else {
+            return Vec::new();
+        };
+        if self.desired != DesiredState::Running {
+            self.rolling_restart = None;
+            return Vec::new();
+        }
+        match self.process_vec[replica].state {
+            ProcessState::Running => {}
+            ProcessState::Starting | ProcessState::Backoff | ProcessState::Stopping => {
+                return Vec::new()
+            }
+            settled => {
+                log_error!(logger, program = self.name;
+                    "rolling restart aborted, process {replica} is {settled:?}");
+                self.rolling_restart = None;
+                return Vec::new();
+            }
+        }
+        // the next process is only taken down once the program is back to a healthy capacity
+        if !self.is_healthy() {
+            return Vec::new();
+        }
+        let next = replica + 1;
+        if next == self.process_vec.len() {
+            log_info!(logger, program = self.name; "rolling restart done");
+            self.rolling_restart = None;
+            return Vec::new();
+        }
+        self.rolling_restart = Some(next);
+        let pid = self.process_vec[next].pid();
+        if let Err(e) = self.process_vec[next].restart() {
+            log_error!(logger, program = self.name, pid = pid; "{e}");
+        }
+        self.record_transitions(Some(&TransitionCause::RollingRestart), logger)
+    }
+
+    /// take an order that takes a while to take effect: it is applied right away if no other
+    /// one is taking effect, queued behind it otherwise. a stop is always applied right away,
+    /// dropping the queued orders, so a program can't be kept from stopping
+    pub(super) fn admit(&mut self, operation: Operation, cause: TransitionCause) -> Admission {
+        // the monitor may not have noticed it took effect yet
+        if self.queued.is_empty() && !self.is_taking_effect() {
+            self.ongoing = None;
+        }
+        let last = self.queued.back().map(|(operation, _)| *operation);
+        match (self.ongoing, last) {
+            (Some(ongoing), None) | (_, Some(ongoing)) if ongoing == operation => {
+                Admission::Duplicate
+            }
+            (Some(ongoing), _) if operation != Operation::Stop => {
+                self.queued.push_back((operation, cause));
+                Admission::Queued(ongoing)
+            }
+            _ => {
+                self.queued.clear();
+                self.ongoing = Some(operation);
+                Admission::Apply
+            }
+        }
+    }
+
+    /// apply an order, a restart follows the restart strategy of the program
+    pub(super) fn apply(&mut self, operation: Operation) -> Result<(), OrderError> {
+        match (operation, self.config.restart_strategy) {
+            (Operation::Start, _) => self.start(),
+            (Operation::Stop, _) => self.stop(),
+            (Operation::Restart, RestartStrategy::All) => self.restart(),
+            (Operation::Restart, RestartStrategy::Rolling) | (Operation::RollingRestart, _) => {
+                self.rolling_restart()
+            }
+        }
+    }
+
+    /// tell if the orders given to the program took effect, none being left in the queue
+    pub(super) fn orders_done(&self) -> bool {
+        self.ongoing.is_none() && self.queued.is_empty()
+    }
+
+    /// tell if the ongoing order is still taking effect: a process is still starting or
+    /// stopping, or a rolling restart isn't done. a process in backoff doesn't count while
+    /// the restarts are paused, it isn't retried before they are resumed
+    fn is_taking_effect(&self) -> bool {
+        use ProcessState as PS;
+        let held = self.restarts_paused;
+        self.rolling_restart.is_some()
+            || self.process_vec.iter().any(|process| match process.state {
+                PS::Starting | PS::Stopping => true,
+                PS::Backoff => !held,
+                _ => false,
+            })
+    }
+
+    /// stop the program gracefully once the run time given by a run order elapsed
+    fn enforce_run_deadline(&mut self, logger: &Logger) -> Vec<Event> {
+        if self
+            .run_deadline
+            .is_none_or(|deadline| MonotonicTime::now() < deadline)
+        {
+            return Vec::new();
+        }
+        self.run_deadline = None;
+        log_info!(logger, program = self.name; "run time elapsed, stopping");
+        let cause = TransitionCause::RunTimeElapsed;
+        if let Admission::Apply = self.admit(Operation::Stop, cause.clone()) {
+            if let Err(e) = self.apply(Operation::Stop) {
+                log_error!(logger, program = self.name; "stop: {e}");
+            }
+        }
+        self.record_transitions(Some(&cause), logger)
+    }
+
+    /// restart the program once its watched files changed, if it should be running
+    fn restart_on_watched_change(&mut self, logger: &Logger) -> Vec<Event> {
+        let changed = self.watcher.as_mut().is_some_and(Watcher::poll);
+        if !changed || self.desired != DesiredState::Running {
+            return Vec::new();
+        }
+        log_info!(logger, program = self.name; "watched files changed, restarting");
+        let cause = TransitionCause::WatchedFilesChanged;
+        match self.admit(Operation::Restart, cause.clone()) {
+            Admission::Apply => {
+                if let Err(e) = self.apply(Operation::Restart) {
+                    log_error!(logger, program = self.name; "restart: {e}");
                 }
             }
+            Admission::Queued(ongoing) => {
+                log_info!(logger, program = self.name; "restart queued behind the ongoing {ongoing}")
+            }
+            Admission::Duplicate => {}
+        }
+        self.record_transitions(Some(&cause), logger)
+    }
 
-            if all_total_failure {
-                Err(OrderError::TotalFailure(all_errors))
-            } else {
-                Err(OrderError::PartialSuccess(all_errors))
+    /// the orders of the program still to take effect on a process: its turn in the rolling
+    /// restart and the orders queued behind the ongoing one
+    fn pending_orders(&self, replica: usize) -> Vec<String> {
+        let mut pending = Vec::new();
+        match self.rolling_restart {
+            Some(restarting) if replica > restarting => pending.push(format!(
+                "rolling restart: restarted once process {restarting} is running"
+            )),
+            Some(restarting) if replica == restarting => {
+                pending.push("rolling restart: being restarted".to_owned())
             }
+            _ => {}
+        }
+        if let Some(deadline) = self.run_deadline {
+            let left = deadline.saturating_duration_since(MonotonicTime::now());
+            pending.push(format!("run: stopped in {}s", left.as_secs()));
+        }
+        if let (Some(ongoing), false) = (self.ongoing, self.queued.is_empty()) {
+            let queued: Vec<String> = self
+                .queued
+                .iter()
+                .map(|(operation, _)| operation.to_string())
+                .collect();
+            pending.push(format!(
+                "queued: {} behind the ongoing {ongoing}",
+                queued.join(" then ")
+            ));
+        }
+        pending
+    }
+
+    /// once the ongoing order took effect, apply the next queued one
+    fn advance_orders(&mut self, logger: &Logger) -> Vec<Event> {
+        let Some(ongoing) = self.ongoing else {
+            return Vec::new();
+        };
+        if self.is_taking_effect() {
+            return Vec::new();
+        }
+        self.ongoing = None;
+        let Some((operation, cause)) = self.queued.pop_front() else {
+            return Vec::new();
+        };
+        log_info!(logger, program = self.name; "{ongoing} done, applying the queued {operation}");
+        self.ongoing = Some(operation);
+        if let Err(e) = self.apply(operation) {
+            log_error!(logger, program = self.name; "queued {operation}: {e}");
+        }
+        self.record_transitions(Some(&cause), logger)
+    }
+}
+
+impl Operation {
+    /// what is being done, as reported to the client
+    pub(super) fn action(&self) -> &'static str {
+        match self {
+            Operation::Start => "starting",
+            Operation::Stop => "stopping",
+            Operation::Restart => "restarting",
+            Operation::RollingRestart => "rolling restarting",
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Start => write!(f, "start"),
+            Operation::Stop => write!(f, "stop"),
+            Operation::Restart => write!(f, "restart"),
+            Operation::RollingRestart => write!(f, "rolling restart"),
         }
     }
 }
@@ -198,25 +1035,51 @@ fn squish_order_result(
 /// - `Err(OrderError::PartialSuccess(errors))` if there were any logic errors or at least one success.
 /// - `Err(OrderError::TotalFailure(errors))` if all errors were process errors and no successes.
 fn determine_order_result(results: Vec<Result<(), ProgramError>>) -> Result<(), OrderError> {
-    let (successes, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+    let total = results.len();
+    let errors: Vec<(usize, ProgramError)> = results
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, result)| result.err().map(|error| (index, error)))
+        .collect();
 
     if errors.is_empty() {
         // the case were there was no error at all
         return Ok(());
     }
 
-    let (logic_errors, process_errors): (Vec<_>, Vec<_>) = errors
-        .into_iter()
-        .map(Result::unwrap_err)
-        .partition(|error| matches!(error, ProgramError::Logic(_)));
-
-    if logic_errors.is_empty() && successes.is_empty() {
+    let no_success = errors.len() == total;
+    let no_skip = errors
+        .iter()
+        .all(|(_, error)| matches!(error, ProgramError::Process(_)));
+    if no_success && no_skip {
         // if no success and no skip(AKA logic error)
-        Err(OrderError::TotalFailure(process_errors))
+        Err(OrderError::TotalFailure(errors))
     } else {
-        Err(OrderError::PartialSuccess(
-            logic_errors.into_iter().chain(process_errors).collect(),
-        ))
+        Err(OrderError::PartialSuccess(errors))
+    }
+}
+
+/// the cause of a transition that no order explains: the monitor reacting to the child,
+/// or carrying on what the previous cause started (e.g. a stop order once the child stopped)
+fn infer_cause(
+    from: ProcessState,
+    to: ProcessState,
+    process: &Process,
+    start_cause: &TransitionCause,
+) -> TransitionCause {
+    use ProcessState as PS;
+    use TransitionCause as TC;
+    match (from, to) {
+        (_, PS::Running) if process.readiness.is_some() => TC::Ready,
+        (_, PS::Running) => TC::StartTime,
+        (PS::Backoff, PS::Starting) => TC::BackoffRetry,
+        (PS::ExitedExpectedly | PS::ExitedUnExpectedly, PS::Starting) => TC::Autorestart,
+        (PS::NeverStartedYet, PS::Starting) => start_cause.clone(),
+        (
+            _,
+            PS::Backoff | PS::ExitedExpectedly | PS::ExitedUnExpectedly | PS::Fatal | PS::Unknown,
+        ) => TC::Exit,
+        _ => process.last_cause.clone().unwrap_or(start_cause.clone()),
     }
 }
 
@@ -250,19 +1113,46 @@ impl Display for OrderError {
 /* -------------------------------------------------------------------------- */
 impl From<&mut Program> for tcl::message::ProgramStatus {
     fn from(value: &mut Program) -> Self {
+        let mut status: Vec<tcl::message::ProcessStatus> = value
+            .process_vec
+            .iter_mut()
+            .map(|process| process.into())
+            .collect();
+        for (replica, status) in status.iter_mut().enumerate() {
+            let pending: Vec<String> = (status.pending.take().into_iter())
+                .chain(value.pending_orders(replica))
+                .collect();
+            status.pending = (!pending.is_empty()).then(|| pending.join("; "));
+        }
         tcl::message::ProgramStatus {
             name: value.name.to_owned(),
-            status: value
-                .process_vec
-                .iter_mut()
-                .map(|process| process.into())
-                .collect(),
+            maintenance: value.maintenance,
+            restarts_paused: value.restarts_paused,
+            maintenance_window: value.window.as_ref().map(ToString::to_string),
+            degraded: value.desired == DesiredState::Running && !value.is_healthy(),
+            running: value.running(),
+            skipped_output_lines: value.history.lock().unwrap().skipped_lines(),
+            summary: None,
+            min_running: value.min_running(),
+            cgroup: value.cgroup.as_deref().and_then(cgroup::usage),
+            config_error: None,
+            status,
         }
     }
 }
 
-impl From<OrderError> for Response {
-    fn from(value: OrderError) -> Self {
-        Response::Error(value.to_string())
+impl From<&ProgramError> for tcl::message::ReplicaError {
+    fn from(value: &ProgramError) -> Self {
+        use tcl::message::ReplicaErrorKind as Kind;
+        match value {
+            ProgramError::Logic(message) => Self {
+                kind: Kind::Skipped,
+                message: message.to_owned(),
+            },
+            ProgramError::Process(error) => Self {
+                kind: Kind::Failed,
+                message: error.to_string(),
+            },
+        }
     }
 }
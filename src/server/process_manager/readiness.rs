@@ -0,0 +1,154 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use crate::config::Readiness;
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Read},
+    os::{
+        fd::{AsRawFd, OwnedFd},
+        unix::net::UnixDatagram,
+    },
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tcl::mylibc;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the message of the sd_notify protocol a child sends once ready, a datagram holds one
+/// assignment per line
+const NOTIFY_READY: &str = "READY=1";
+
+/// numbers the notify sockets of the daemon so each child gets its own
+static NOTIFY_SOCKET_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// how the monitor learns that a starting child is ready, for a program with a readiness
+/// protocol (readiness)
+#[derive(Debug)]
+pub(super) enum ReadinessProbe {
+    /// the file the child creates once ready
+    File(PathBuf),
+    /// the read end of the pipe the child writes to or closes once ready
+    Pipe(File),
+    /// the socket the child sends READY=1 to once ready
+    Notify(NotifySocket),
+    /// the child closed the pipe without writing to it, it is ready if it is still running
+    /// at the next poll since a child that crashes closes it as well
+    Closed,
+    /// the child told it is ready
+    Ready,
+}
+
+/// the datagram socket given to a child in NOTIFY_SOCKET, its file is removed when dropped
+#[derive(Debug)]
+pub(super) struct NotifySocket {
+    socket: UnixDatagram,
+    path: PathBuf,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl ReadinessProbe {
+    /// prepare the probe of the given process about to be spawned, returning the write end
+    /// of the pipe the child has to inherit if it tells it through a pipe. a file left by a
+    /// previous child is removed
+    pub(super) fn new(
+        readiness: &Readiness,
+        replica: usize,
+    ) -> io::Result<(Self, Option<OwnedFd>)> {
+        match readiness {
+            Readiness::File(_) => {
+                let path = readiness.file_path(replica).unwrap_or_default();
+                match fs::remove_file(&path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+                Ok((ReadinessProbe::File(PathBuf::from(path)), None))
+            }
+            Readiness::Fd(_) => {
+                let (read, write) = mylibc::pipe()?;
+                mylibc::set_nonblocking(read.as_raw_fd())?;
+                Ok((ReadinessProbe::Pipe(File::from(read)), Some(write)))
+            }
+            Readiness::Notify => Ok((ReadinessProbe::Notify(NotifySocket::bind()?), None)),
+        }
+    }
+
+    /// the path the child has to find in NOTIFY_SOCKET, if it tells it is ready through it
+    pub(super) fn notify_socket(&self) -> Option<&Path> {
+        match self {
+            ReadinessProbe::Notify(socket) => Some(&socket.path),
+            _ => None,
+        }
+    }
+
+    /// tell if the child is ready, which is remembered. the daemon doesn't hold the write
+    /// end of the pipe so its closing is read as the end of the file. to be called only while
+    /// the child is running
+    pub(super) fn poll(&mut self) -> bool {
+        let ready = match self {
+            ReadinessProbe::File(path) => path.exists(),
+            ReadinessProbe::Pipe(pipe) => match pipe.read(&mut [0; 64]) {
+                Ok(0) => {
+                    *self = ReadinessProbe::Closed;
+                    false
+                }
+                Ok(_) => true,
+                Err(_) => false,
+            },
+            ReadinessProbe::Notify(socket) => socket.received_ready(),
+            ReadinessProbe::Closed | ReadinessProbe::Ready => true,
+        };
+        if ready {
+            *self = ReadinessProbe::Ready;
+        }
+        ready
+    }
+}
+
+impl NotifySocket {
+    /// bind a new socket in the temporary directory, the path must fit in a sockaddr_un
+    fn bind() -> io::Result<Self> {
+        let path = env::temp_dir().join(format!(
+            "taskmasterd-{}-{}.notify",
+            mylibc::getpid(),
+            NOTIFY_SOCKET_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let socket = UnixDatagram::bind(&path)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, path })
+    }
+
+    /// read the datagrams sent so far, telling if one of them holds READY=1. the other
+    /// assignments (STATUS=, MAINPID=...) are ignored
+    fn received_ready(&self) -> bool {
+        let mut buffer = [0; 4096];
+        let mut ready = false;
+        while let Ok(length) = self.socket.recv(&mut buffer) {
+            let message = String::from_utf8_lossy(&buffer[..length]);
+            ready |= message.lines().any(|line| line == NOTIFY_READY);
+        }
+        ready
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl Drop for NotifySocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
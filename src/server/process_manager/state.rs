@@ -4,49 +4,80 @@
 
 use crate::better_logs::send_http_message;
 
-use super::{Process, ProcessError, ProcessState};
+use super::{clock, Process, ProcessError, ProcessState};
+use std::time::Duration;
+use tcl::{message::ProcessExit, time::MonotonicTime};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the minimum time between two starts of a process in backoff, the monitor being woken up
+/// as soon as a child exits, the retries would otherwise be spent in a few milliseconds
+const BACKOFF_DELAY: Duration = Duration::from_secs(1);
 
 /* -------------------------------------------------------------------------- */
 /*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
 impl Process {
-    pub(super) fn update_starting(&mut self, code: Option<i32>) {
-        match code {
-            // the program is no longer running
-            Some(code) => {
+    pub(super) fn update_starting(&mut self, exit: Option<ProcessExit>) {
+        match exit {
+            // the program is no longer running, a child that exits before it told it is ready
+            // failed to start
+            Some(exit) => {
                 match self.is_no_longer_starting() {
-                    Some(true) => {
-                        match self.config.expected_exit_code.contains(&code) {
-                            true => self.state = ProcessState::ExitedExpectedly,
-                            false => self.state = ProcessState::ExitedUnExpectedly,
-                        };
-                    }
+                    Some(true) => self.enter_exited(exit),
                     Some(false) => self.state = ProcessState::Backoff,
                     None => unreachable!(),
                 };
                 self.clean_child();
             }
             // the program is still running
-            None => match self.is_no_longer_starting() {
-                Some(true) => self.state = ProcessState::Running,
-                Some(false) => {}
-                None => unreachable!(),
-            },
+            None => {
+                self.poll_readiness();
+                match self.is_no_longer_starting() {
+                    Some(true) => self.enter_running(),
+                    Some(false) => {}
+                    None => unreachable!(),
+                }
+            }
+        };
+    }
+
+    fn enter_exited(&mut self, exit: ProcessExit) {
+        self.state = match self.is_expected(exit) {
+            true => ProcessState::ExitedExpectedly,
+            false => ProcessState::ExitedUnExpectedly,
         };
     }
 
-    pub(super) fn update_running(&mut self, code: Option<i32>) {
-        if let Some(code) = code {
-            match self.config.expected_exit_code.contains(&code) {
-                true => self.state = ProcessState::ExitedExpectedly,
-                false => self.state = ProcessState::ExitedUnExpectedly,
-            };
+    /// tell if the exit code is listed in exitcodes, or the signal that terminated the child
+    /// in expected_signals
+    fn is_expected(&self, exit: ProcessExit) -> bool {
+        match exit {
+            ProcessExit::Exited(code) => self.config.expected_exit_code.contains(&code),
+            ProcessExit::ExitedBySignal(signal) => self
+                .config
+                .expected_signals
+                .iter()
+                .any(|expected| Self::signal_to_libc(expected) == signal),
+        }
+    }
+
+    /// the process started successfully, so the start retries budget is available again
+    fn enter_running(&mut self) {
+        self.state = ProcessState::Running;
+        self.number_of_restart = 0;
+    }
+
+    pub(super) fn update_running(&mut self, exit: Option<ProcessExit>) {
+        if let Some(exit) = exit {
+            self.enter_exited(exit);
             self.clean_child();
         }
     }
 
-    pub(super) fn update_stopping(&mut self, code: Option<i32>) {
-        match code {
+    pub(super) fn update_stopping(&mut self, exit: Option<ProcessExit>) {
+        match exit {
             Some(_) => {
                 // the program is not running anymore
                 self.state = ProcessState::Stopped;
@@ -58,29 +89,40 @@ impl Process {
         };
     }
 
-    pub(super) fn update_unknown(&mut self, code: Option<i32>) {
-        match code {
-            Some(code) => {
-                match self.config.expected_exit_code.contains(&code) {
-                    true => self.state = ProcessState::ExitedExpectedly,
-                    false => self.state = ProcessState::ExitedUnExpectedly,
-                };
+    pub(super) fn update_unknown(&mut self, exit: Option<ProcessExit>) {
+        match exit {
+            Some(exit) => {
+                self.enter_exited(exit);
                 self.clean_child();
             }
-            None => match self.is_no_longer_starting() {
-                Some(true) => self.state = ProcessState::Running,
-                Some(false) => self.state = ProcessState::Starting,
-                None => unreachable!(),
-            },
+            None => {
+                self.poll_readiness();
+                match self.is_no_longer_starting() {
+                    Some(true) => self.enter_running(),
+                    Some(false) => self.state = ProcessState::Starting,
+                    None => unreachable!(),
+                }
+            }
         }
     }
 
-    pub(super) fn react_never_started_yet(&mut self) -> Result<(), ProcessError> {
-        if self.config.start_at_launch {
-            self.start()?;
+    /// look if the child of a program with a readiness protocol told it is ready. with a
+    /// starttime, a child still not ready once it elapsed is killed, which fails the start.
+    /// only called while the child is still running
+    fn poll_readiness(&mut self) {
+        let Some(probe) = self.readiness.as_mut() else {
+            return;
+        };
+        if probe.poll() || self.config.time_to_start == 0 {
+            return;
+        }
+        let late = self
+            .started_since
+            .is_some_and(|since| clock::elapsed(since).as_secs() > self.config.time_to_start);
+        if let (true, Some(child)) = (late, self.child.as_mut()) {
+            // the exit is reaped by a later update, the process still starting goes in backoff
+            let _ = child.kill();
         }
-
-        Ok(())
     }
 
     pub(super) fn react_backoff(&mut self, program_name: &str) -> Result<(), ProcessError> {
@@ -89,6 +131,7 @@ impl Process {
             .number_of_restart
             .cmp(&self.config.max_number_of_restart)
         {
+            O::Less if !self.backoff_elapsed() => {}
             O::Less => match self.start() {
                 Ok(_) => self.number_of_restart += 1,
                 Err(e) => {
@@ -110,6 +153,69 @@ impl Process {
         Ok(())
     }
 
+    /// what the monitor is about to do with the process, if it is on its way to another state
+    /// (e.g. stopping: SIGTERM sent 4s ago, SIGKILL in 6s)
+    pub(super) fn pending(&self) -> Option<String> {
+        let seconds_left =
+            |since: MonotonicTime, time: u64| time.saturating_sub(clock::elapsed(since).as_secs());
+        match self.state {
+            ProcessState::Stopping => Some(match self.time_since_shutdown {
+                Some(_) if self.its_time_to_kill_the_child() => "stopping: SIGKILL sent".to_owned(),
+                Some(since) => format!(
+                    "stopping: {:?} sent {}s ago, SIGKILL in {}s",
+                    self.config.stop_signal,
+                    clock::elapsed(since).as_secs(),
+                    seconds_left(since, self.config.time_to_stop_gracefully)
+                ),
+                None => "stopping: SIGKILL sent".to_owned(),
+            }),
+            ProcessState::Starting if self.readiness.is_some() => {
+                self.started_since
+                    .map(|since| match self.config.time_to_start {
+                        0 => "starting: waiting to be ready".to_owned(),
+                        time => format!(
+                            "starting: waiting to be ready, killed in {}s",
+                            seconds_left(since, time)
+                        ),
+                    })
+            }
+            ProcessState::Starting => self.started_since.map(|since| {
+                format!(
+                    "starting: running in {}s",
+                    seconds_left(since, self.config.time_to_start)
+                )
+            }),
+            ProcessState::Backoff if self.number_of_restart < self.config.max_number_of_restart => {
+                Some(format!(
+                    "backoff: retry {}/{} in {:.1}s",
+                    self.number_of_restart + 1,
+                    self.config.max_number_of_restart,
+                    // the delay is a second at most, it is shown to the tenth
+                    self.started_since.map_or(0.0, |since| {
+                        BACKOFF_DELAY
+                            .saturating_sub(clock::elapsed(since))
+                            .as_secs_f32()
+                    })
+                ))
+            }
+            ProcessState::Backoff => Some("backoff: no retry left, fatal next".to_owned()),
+            _ => None,
+        }
+    }
+
+    /// tell if the last start is old enough for the process to be retried
+    fn backoff_elapsed(&self) -> bool {
+        self.started_since
+            .is_none_or(|start_time| clock::elapsed(start_time) >= BACKOFF_DELAY)
+    }
+
+    /// a process that runs while its program is wanted stopped is sent the stop signal,
+    /// or killed if the signal couldn't be sent
+    pub(super) fn react_unwanted(&mut self) -> Result<(), ProcessError> {
+        let stop_signal = self.config.stop_signal.clone();
+        self.send_signal(&stop_signal).or_else(|_| self.kill())
+    }
+
     pub(super) fn react_stopping(&mut self) -> Result<(), ProcessError> {
         if self.its_time_to_kill_the_child() {
             self.kill()?;
@@ -121,7 +227,7 @@ impl Process {
     pub(super) fn react_expected_exit(&mut self) -> Result<(), ProcessError> {
         use crate::config::AutoRestart as AR;
         match self.config.auto_restart {
-            AR::Always => self.start(),
+            AR::Always => self.autorestart(),
             AR::Unexpected | AR::Never => Ok(()),
         }
     }
@@ -129,8 +235,14 @@ impl Process {
     pub(super) fn react_unexpected_exit(&mut self) -> Result<(), ProcessError> {
         use crate::config::AutoRestart as AR;
         match self.config.auto_restart {
-            AR::Always | AR::Unexpected => self.start(),
+            AR::Always | AR::Unexpected => self.autorestart(),
             AR::Never => Ok(()),
         }
     }
+
+    /// restart a process that exited from the running state, this doesn't use the start retries
+    fn autorestart(&mut self) -> Result<(), ProcessError> {
+        self.number_of_autorestart += 1;
+        self.start()
+    }
 }
@@ -0,0 +1,110 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{handover::Handover, StateFile};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fs, path::PathBuf};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// what is written to the state file
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PersistedState {
+    /// the programs an operator stopped, they are not autostarted until an explicit start
+    stopped: BTreeSet<String>,
+    /// what a daemon replaced on a reexec handed over, until the new one takes it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    handover: Option<Handover>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl StateFile {
+    /// read the state file at the given path, a missing or unreadable file is an empty state
+    pub(super) fn load(path: PathBuf) -> Self {
+        let persisted: PersistedState = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_yaml::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            stopped: persisted.stopped,
+        }
+    }
+
+    /// tell if the program was stopped by an operator
+    pub(super) fn is_stopped(&self, program_name: &str) -> bool {
+        self.stopped.contains(program_name)
+    }
+
+    /// remember that the program was stopped or started by an operator, saving the state
+    /// file only if it changed
+    pub(super) fn set_stopped(
+        &mut self,
+        program_name: &str,
+        stopped: bool,
+    ) -> Result<(), std::io::Error> {
+        let changed = match stopped {
+            true => self.stopped.insert(program_name.to_owned()),
+            false => self.stopped.remove(program_name),
+        };
+        match changed {
+            true => self.save(),
+            false => Ok(()),
+        }
+    }
+
+    /// forget the programs that are no longer part of the config, and use the new path
+    pub(super) fn reload(
+        &mut self,
+        path: PathBuf,
+        mut is_known: impl FnMut(&str) -> bool,
+    ) -> Result<(), std::io::Error> {
+        let previous_count = self.stopped.len();
+        self.stopped.retain(|program_name| is_known(program_name));
+        if path != self.path || self.stopped.len() != previous_count {
+            self.path = path;
+            return self.save();
+        }
+        Ok(())
+    }
+
+    /// write the state file along with what is handed over to the daemon replacing this one
+    /// on a reexec
+    pub(super) fn save_handover(&self, handover: Handover) -> Result<(), std::io::Error> {
+        self.write(Some(handover))
+    }
+
+    /// take what the daemon replaced on a reexec handed over, it is removed from the state file
+    pub(super) fn take_handover(&self) -> Result<Option<Handover>, std::io::Error> {
+        let persisted: PersistedState = serde_yaml::from_str(&fs::read_to_string(&self.path)?)
+            .map_err(std::io::Error::other)?;
+        self.save()?;
+        Ok(persisted.handover)
+    }
+
+    /// write the state file, creating its directory if needed
+    fn save(&self) -> Result<(), std::io::Error> {
+        self.write(None)
+    }
+
+    fn write(&self, handover: Option<Handover>) -> Result<(), std::io::Error> {
+        if let Some(directory) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(directory)?;
+        }
+        let persisted = PersistedState {
+            stopped: self.stopped.clone(),
+            handover,
+        };
+        let contents = serde_yaml::to_string(&persisted).map_err(std::io::Error::other)?;
+        fs::write(&self.path, contents)
+    }
+}
@@ -0,0 +1,335 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{
+    harness::{self, Script},
+    Admission, DesiredState, Operation, OutputHistory, Process, ProcessState, Program,
+    TransitionCause,
+};
+use crate::{
+    config::{AutoRestart, ProgramConfig, ServerConfig},
+    event::new_shared_event_bus,
+    logger::{new_shared_logger, SharedLogger},
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tcl::{message::ProcessExit, mylibc};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Helper                                   */
+/* -------------------------------------------------------------------------- */
+const SECOND: Duration = Duration::from_secs(1);
+
+fn program_config(time_to_start: u64, max_number_of_restart: u32) -> ProgramConfig {
+    ProgramConfig {
+        number_of_process: 1,
+        spawn_concurrency: 1,
+        time_to_start,
+        max_number_of_restart,
+        time_to_stop_gracefully: 3,
+        expected_exit_code: vec![0],
+        ..Default::default()
+    }
+}
+
+/// a process of the config on the mock clock of the thread
+fn process(config: ProgramConfig) -> Process {
+    harness::mock_clock();
+    let history = Arc::new(Mutex::new(OutputHistory::new(10, None, false)));
+    Process::new(config, None, history, 0, None)
+}
+
+fn reconcile(process: &mut Process, desired: DesiredState) -> ProcessState {
+    process
+        .reconcile("test", desired)
+        .expect("the reconciliation failed");
+    process.state
+}
+
+/// a logger writing to a file of its own in the temporary directory
+fn logger(name: &str) -> SharedLogger {
+    let log_file =
+        std::env::temp_dir().join(format!("taskmasterd-{}-{name}.log", mylibc::getpid()));
+    let config = ServerConfig {
+        log_file: log_file.display().to_string(),
+        ..Default::default()
+    };
+    new_shared_logger(&config, false).expect("can't create the logger")
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[test]
+fn starting_process_runs_once_the_starttime_elapsed() {
+    let mut process = process(program_config(2, 0));
+    harness::script_next_child(Script::default());
+
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Starting
+    );
+    harness::advance(2 * SECOND);
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Starting
+    );
+    harness::advance(SECOND);
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Running
+    );
+}
+
+#[test]
+fn failed_starts_are_retried_after_the_backoff_delay_then_fatal() {
+    let mut process = process(program_config(5, 2));
+    for _ in 0..3 {
+        harness::script_next_child(Script::exits_after(Duration::ZERO, ProcessExit::Exited(1)));
+    }
+
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Starting
+    );
+    for retry in 1..=2 {
+        // the exit is noticed, the retry waits for the backoff delay
+        assert_eq!(
+            reconcile(&mut process, DesiredState::Running),
+            ProcessState::Backoff
+        );
+        assert_eq!(
+            reconcile(&mut process, DesiredState::Running),
+            ProcessState::Backoff
+        );
+        harness::advance(SECOND);
+        assert_eq!(
+            reconcile(&mut process, DesiredState::Running),
+            ProcessState::Starting
+        );
+        assert_eq!(process.number_of_restart, retry);
+    }
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Fatal
+    );
+    assert!(harness::take_script().is_none());
+}
+
+#[test]
+fn stop_escalates_to_sigkill_after_the_stoptime() {
+    let mut process = process(program_config(1, 0));
+    harness::script_next_child(Script::default().ignoring(mylibc::SIGTERM));
+    reconcile(&mut process, DesiredState::Running);
+    harness::advance(2 * SECOND);
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Running
+    );
+
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Stopped),
+        ProcessState::Stopping
+    );
+    harness::advance(3 * SECOND);
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Stopped),
+        ProcessState::Stopping
+    );
+    harness::advance(SECOND);
+    reconcile(&mut process, DesiredState::Stopped);
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Stopped),
+        ProcessState::Stopped
+    );
+    assert_eq!(
+        process.exit,
+        Some(ProcessExit::ExitedBySignal(mylibc::SIGKILL))
+    );
+}
+
+#[test]
+fn stop_signal_honored_within_the_stoptime() {
+    let mut process = process(program_config(1, 0));
+    harness::script_next_child(Script::default());
+    reconcile(&mut process, DesiredState::Running);
+
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Stopped),
+        ProcessState::Stopping
+    );
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Stopped),
+        ProcessState::Stopped
+    );
+    assert_eq!(
+        process.exit,
+        Some(ProcessExit::ExitedBySignal(mylibc::SIGTERM))
+    );
+}
+
+#[test]
+fn unexpected_exit_is_autorestarted() {
+    let mut process = process(ProgramConfig {
+        auto_restart: AutoRestart::Unexpected,
+        ..program_config(1, 0)
+    });
+    harness::script_next_child(Script::exits_after(10 * SECOND, ProcessExit::Exited(1)));
+    harness::script_next_child(Script::default());
+    reconcile(&mut process, DesiredState::Running);
+    harness::advance(2 * SECOND);
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Running
+    );
+
+    harness::advance(10 * SECOND);
+    assert_eq!(
+        reconcile(&mut process, DesiredState::Running),
+        ProcessState::Starting
+    );
+    assert_eq!(process.number_of_autorestart, 1);
+    assert_eq!(process.number_of_restart, 0);
+}
+
+#[test]
+fn reload_lowering_numprocs_retires_the_last_replicas_only() {
+    let logger = logger("reload");
+    let event_bus = new_shared_event_bus(&ServerConfig::default());
+    let config = ProgramConfig {
+        number_of_process: 2,
+        start_at_launch: true,
+        ..program_config(1, 0)
+    };
+    harness::mock_clock();
+    let mut program = Program::new(
+        "web".to_owned(),
+        config.clone(),
+        None,
+        &event_bus,
+        TransitionCause::Autostart,
+    );
+    harness::script_next_child(Script::default());
+    harness::script_next_child(Script::default());
+    program.monitor(&logger);
+    harness::advance(2 * SECOND);
+    program.monitor(&logger);
+    let kept = program.process_vec[0].pid();
+    assert!(program
+        .process_vec
+        .iter()
+        .all(|process| process.state == ProcessState::Running));
+
+    let lowered = ProgramConfig {
+        number_of_process: 1,
+        ..config
+    };
+    assert!(program.can_update_in_place(&lowered));
+    program.update_in_place(&lowered, &event_bus, &logger);
+    assert_eq!(program.process_vec.len(), 1);
+    assert_eq!(program.retiring.len(), 1);
+    program.monitor(&logger);
+    assert!(program.retiring.is_empty());
+    assert_eq!(program.process_vec[0].pid(), kept);
+    assert_eq!(program.process_vec[0].state, ProcessState::Running);
+}
+
+#[test]
+fn rolling_restart_is_aborted_when_the_restarted_process_exits() {
+    let logger = logger("rolling");
+    let event_bus = new_shared_event_bus(&ServerConfig::default());
+    let config = ProgramConfig {
+        start_at_launch: true,
+        ..program_config(1, 0)
+    };
+    harness::mock_clock();
+    let mut program = Program::new(
+        "batch".to_owned(),
+        config,
+        None,
+        &event_bus,
+        TransitionCause::Autostart,
+    );
+    harness::script_next_child(Script::default());
+    program.monitor(&logger);
+    harness::advance(2 * SECOND);
+    program.monitor(&logger);
+    assert_eq!(program.process_vec[0].state, ProcessState::Running);
+
+    // the new child exits on its own once started, and isn't autorestarted
+    harness::script_next_child(Script::exits_after(2 * SECOND, ProcessExit::Exited(0)));
+    program.rolling_restart().unwrap();
+    program.monitor(&logger);
+    assert_eq!(program.process_vec[0].state, ProcessState::Starting);
+    assert_eq!(program.rolling_restart, Some(0));
+    harness::advance(3 * SECOND);
+    program.monitor(&logger);
+    assert_eq!(program.process_vec[0].state, ProcessState::ExitedExpectedly);
+    assert_eq!(program.rolling_restart, None);
+}
+
+#[test]
+fn backoff_held_by_paused_restarts_doesnt_queue_the_orders() {
+    let logger = logger("paused");
+    let event_bus = new_shared_event_bus(&ServerConfig::default());
+    harness::mock_clock();
+    let mut program = Program::new(
+        "flaky".to_owned(),
+        program_config(5, 2),
+        None,
+        &event_bus,
+        TransitionCause::Autostart,
+    );
+    harness::script_next_child(Script::exits_after(Duration::ZERO, ProcessExit::Exited(1)));
+    assert!(matches!(
+        program.admit(Operation::Start, TransitionCause::Autostart),
+        Admission::Apply
+    ));
+    program.apply(Operation::Start).unwrap();
+    program.monitor(&logger);
+    program.set_restarts_paused(true, None);
+    program.monitor(&logger);
+    assert_eq!(program.process_vec[0].state, ProcessState::Backoff);
+
+    assert!(matches!(
+        program.admit(
+            Operation::Restart,
+            TransitionCause::Order("operator".to_owned())
+        ),
+        Admission::Apply
+    ));
+}
+
+#[test]
+fn stop_escalates_to_sigkill_during_maintenance() {
+    let logger = logger("maintenance");
+    let event_bus = new_shared_event_bus(&ServerConfig::default());
+    harness::mock_clock();
+    let mut program = Program::new(
+        "stubborn".to_owned(),
+        program_config(1, 0),
+        None,
+        &event_bus,
+        TransitionCause::Autostart,
+    );
+    harness::script_next_child(Script::default().ignoring(mylibc::SIGTERM));
+    program.apply(Operation::Start).unwrap();
+    program.monitor(&logger);
+    harness::advance(2 * SECOND);
+    program.monitor(&logger);
+    assert_eq!(program.process_vec[0].state, ProcessState::Running);
+
+    program.apply(Operation::Stop).unwrap();
+    program.set_maintenance(true);
+    harness::advance(4 * SECOND);
+    program.monitor(&logger);
+    program.monitor(&logger);
+    assert_eq!(program.process_vec[0].state, ProcessState::Stopped);
+    assert_eq!(
+        program.process_vec[0].exit,
+        Some(ProcessExit::ExitedBySignal(mylibc::SIGKILL))
+    );
+}
@@ -0,0 +1,32 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::MonitorWakeup;
+use std::{thread, time::Duration};
+use tcl::mylibc::SelfPipe;
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl MonitorWakeup {
+    pub(super) fn new() -> Self {
+        MonitorWakeup {
+            pipe: SelfPipe::new().expect("can't create the pipe waking the monitor up"),
+        }
+    }
+
+    /// ask the monitor for an iteration as soon as possible, it is async signal safe
+    pub(super) fn notify(&self) {
+        self.pipe.notify();
+    }
+
+    /// block until notified or until the timeout elapsed, a notification that
+    /// happened while the monitor was busy is not lost
+    pub(super) fn wait(&self, timeout: Duration) {
+        // without a working pipe the monitor still refreshes periodically
+        if self.pipe.wait(timeout).is_err() {
+            thread::sleep(timeout);
+        }
+    }
+}
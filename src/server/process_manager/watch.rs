@@ -0,0 +1,94 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the time the watched files must stay untouched before the program is restarted, a
+/// build or a checkout writes a lot of files in a row
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// notice the changes of the files and directories a program watches (watchpaths), they are
+/// polled by the monitor since the daemon has no file notification
+#[derive(Debug)]
+pub(super) struct Watcher {
+    paths: Vec<PathBuf>,
+    /// identify the metadata of every watched file as last seen
+    fingerprint: u64,
+    /// when the last change was seen, until the changes settle
+    changed_at: Option<Instant>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Watcher {
+    /// start watching the given paths as they are now, a directory is watched recursively
+    pub(super) fn new(paths: &[String]) -> Self {
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        Self {
+            fingerprint: fingerprint(&paths),
+            paths,
+            changed_at: None,
+        }
+    }
+
+    /// tell if the watched files changed and then stayed untouched for the debounce time,
+    /// the change is only reported once
+    pub(super) fn poll(&mut self) -> bool {
+        let fingerprint = fingerprint(&self.paths);
+        if fingerprint != self.fingerprint {
+            self.fingerprint = fingerprint;
+            self.changed_at = Some(Instant::now());
+            return false;
+        }
+        match self.changed_at {
+            Some(changed_at) if changed_at.elapsed() >= WATCH_DEBOUNCE => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// hash the path, modification time and size of every watched file, a missing one is hashed
+/// as such so its creation is noticed
+fn fingerprint(paths: &[PathBuf]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    paths.iter().for_each(|path| hash_tree(path, &mut hasher));
+    hasher.finish()
+}
+
+fn hash_tree(path: &Path, hasher: &mut DefaultHasher) {
+    path.hash(hasher);
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    metadata.modified().ok().hash(hasher);
+    metadata.len().hash(hasher);
+    if !metadata.is_dir() {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    entries.iter().for_each(|entry| hash_tree(entry, hasher));
+}
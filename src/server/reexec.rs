@@ -0,0 +1,95 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use crate::{
+    args::Arguments, config::SharedConfig, log_info, logger::SharedLogger,
+    process_manager::SharedProcessManager,
+};
+use std::{
+    os::{
+        fd::{AsRawFd, FromRawFd, RawFd},
+        unix::process::CommandExt,
+    },
+    path::Path,
+    process::Command,
+    time::Duration,
+};
+use tcl::{error::TaskmasterError, mylibc};
+use tokio::net::TcpListener;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the time left to the client that asked for the reexec to be answered
+pub(super) const REEXEC_DELAY: Duration = Duration::from_millis(100);
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// tell why the daemon can't be replaced right now, if it can't. the new daemon loads the
+/// config file, it has to be the one running
+pub(super) fn check_reexec(
+    shared_config: &SharedConfig,
+    shared_process_manager: &SharedProcessManager,
+) -> Result<(), TaskmasterError> {
+    if shared_config.read().unwrap().changed_on_disk() {
+        return Err(TaskmasterError::Custom(
+            "the config changed on disk, reload it first".to_owned(),
+        ));
+    }
+    shared_process_manager.read().unwrap().check_handover()
+}
+
+/// ask the daemon to replace itself, the same as sending it SIGUSR2. it is done by the main
+/// task, leaving the time to answer the client that asked for it
+pub(super) fn request_reexec() -> Result<(), TaskmasterError> {
+    mylibc::kill(mylibc::getpid(), mylibc::SIGUSR2)?;
+    Ok(())
+}
+
+/// replace the daemon by a new instance of the executable (e.g. an upgraded one) that
+/// inherits the listening socket and adopts the children, handed over through the state
+/// file. it only returns if the daemon couldn't be replaced, which then goes on as before
+pub(super) fn reexec(
+    executable: &Path,
+    arguments: &Arguments,
+    listener: &TcpListener,
+    shared_config: &SharedConfig,
+    shared_logger: &SharedLogger,
+    shared_process_manager: &SharedProcessManager,
+) -> TaskmasterError {
+    if let Err(e) = check_reexec(shared_config, shared_process_manager) {
+        return e;
+    }
+    // the programs are left untouched until the daemon is replaced
+    let mut manager = shared_process_manager.write().unwrap();
+    let inherited = match manager.hand_over() {
+        Ok(inherited) => inherited,
+        Err(e) => return e,
+    };
+    let listener = listener.as_raw_fd();
+    if let Err(e) = mylibc::set_inheritable(listener) {
+        manager.cancel_handover(&inherited);
+        return e.into();
+    }
+
+    log_info!(shared_logger, "Re-executing {}", executable.display());
+    shared_logger.flush();
+    let error = Command::new(executable)
+        .args(arguments.reexec_args(listener))
+        .exec();
+
+    let _ = mylibc::set_cloexec(listener);
+    manager.cancel_handover(&inherited);
+    error.into()
+}
+
+/// take the listening socket inherited from the daemon this one replaced on a reexec
+pub(super) fn inherit_listener(fd: RawFd) -> Result<TcpListener, TaskmasterError> {
+    mylibc::set_cloexec(fd)?;
+    // the previous daemon handed the fd over and nothing else owns it
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    listener.set_nonblocking(true)?;
+    Ok(TcpListener::from_std(listener)?)
+}
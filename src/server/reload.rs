@@ -0,0 +1,118 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use crate::{
+    config::{Config, SharedConfig},
+    event::SharedEventBus,
+    log_error, log_info,
+    logger::SharedLogger,
+    process_manager::SharedProcessManager,
+};
+use std::{
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+use tcl::{error::TaskmasterError, message::Event};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time::timeout,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the quiet time after a SIGHUP before reloading, the SIGHUPs received meanwhile (e.g. a
+/// config management run touching several files) are coalesced into one reload
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/* -------------------------------------------------------------------------- */
+/*                                   Static                                   */
+/* -------------------------------------------------------------------------- */
+/// held during a reload so a SIGHUP and a client can't reload at the same time
+static RELOAD_LOCK: Mutex<()> = Mutex::new(());
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// load the config file again from the path it was first loaded from and conform the daemon
+/// to it, a config that can't be loaded is rejected and the previous one kept. the outcome
+/// is logged and published. a reload waits for the one in flight, if any
+pub(super) fn reload_config(
+    shared_config: &SharedConfig,
+    shared_logger: &SharedLogger,
+    shared_process_manager: &SharedProcessManager,
+    shared_event_bus: &SharedEventBus,
+) -> Result<(), TaskmasterError> {
+    let _guard = RELOAD_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let (path, profile, log_level) = {
+        let config = shared_config.read().unwrap();
+        (
+            config.path.clone(),
+            config.profile.clone(),
+            config.forced_log_level,
+        )
+    };
+    match Config::load(&path, profile.as_deref())
+        .map(|config| config.force_log_level(log_level))
+        .and_then(Config::reject_invalid_programs)
+    {
+        Ok(config) => {
+            shared_logger.configure(&config.server);
+            shared_event_bus.configure(&config.server);
+            *shared_config.write().unwrap() = config;
+            shared_process_manager
+                .write()
+                .unwrap()
+                .reload_config(&shared_config.read().unwrap(), shared_logger);
+            log_info!(shared_logger, "Config reloaded");
+            shared_event_bus.publish(Event::ConfigReloaded);
+            Ok(())
+        }
+        Err(error) => {
+            log_error!(
+                shared_logger,
+                "Config reload rejected, keeping the previous one: {error}"
+            );
+            shared_event_bus.publish(Event::ConfigReloadFailed(error.to_string()));
+            Err(error)
+        }
+    }
+}
+
+/// reload the config each time the daemon receive SIGHUP, for ever. a burst of SIGHUPs is
+/// debounced into one reload, and the ones received during a reload into one more
+pub(super) async fn start_sighup_monitor(
+    shared_config: SharedConfig,
+    shared_logger: SharedLogger,
+    shared_process_manager: SharedProcessManager,
+    shared_event_bus: SharedEventBus,
+) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(error) => {
+            log_error!(shared_logger, "Can't listen to SIGHUP: {error}");
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        let mut received = 1;
+        while let Ok(Some(())) = timeout(RELOAD_DEBOUNCE, hangup.recv()).await {
+            received += 1;
+        }
+        match received {
+            1 => log_info!(shared_logger, "SIGHUP received, reloading the config"),
+            _ => log_info!(
+                shared_logger,
+                "{received} SIGHUP received, reloading the config once"
+            ),
+        }
+        // the outcome is already logged and published
+        let _ = reload_config(
+            &shared_config,
+            &shared_logger,
+            &shared_process_manager,
+            &shared_event_bus,
+        );
+    }
+}
@@ -2,65 +2,238 @@
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
 
+use args::Arguments;
 use client_handler::ClientHandler;
+use config::{SharedConfig, Signal};
+use event::{new_shared_event_bus, SharedEventBus};
 use logger::{new_shared_logger, SharedLogger};
 use process_manager::{manager::new_shared_process_manager, ProgramManager, SharedProcessManager};
 use std::{
+    io::IsTerminal,
+    path::PathBuf,
     thread::{sleep, JoinHandle},
     time::Duration,
 };
-use tokio::net::TcpListener;
+use tcl::message::{COMMIT, PROTOCOL_VERSION, VERSION};
+use tokio::{
+    net::TcpListener,
+    signal::unix::{signal, SignalKind},
+};
 
 /* -------------------------------------------------------------------------- */
 /*                                   Module                                   */
 /* -------------------------------------------------------------------------- */
+mod args;
 mod better_logs;
+mod check;
 mod client_handler;
 mod config;
+mod event;
 mod logger;
 pub mod process_manager;
+mod reexec;
+mod reload;
+mod stats;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// how often the children are checked while waiting for them to be done
+const CHILDREN_POLL_PERIOD: Duration = Duration::from_millis(100);
 
 /* -------------------------------------------------------------------------- */
 /*                                    Main                                    */
 /* -------------------------------------------------------------------------- */
 #[tokio::main]
 async fn main() {
-    // create a logger instance
-    let shared_logger = new_shared_logger().expect("Can't create the logger");
-    log_info!(shared_logger, "Starting a new server instance");
+    stats::STATS.start();
+    // the threads spawned from now on inherit the name
+    #[cfg(target_os = "linux")]
+    let _ = tcl::mylibc::set_thread_name("taskmasterd");
 
-    // load the config
-    let shared_config = config::new_shared_config()
-        .expect("please provide a file named 'config.yaml' at the root of this rust project");
+    // parse the command line
+    let arguments = match Arguments::parse() {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!("{}", Arguments::usage());
+            std::process::exit(2);
+        }
+    };
+    if arguments.help {
+        println!("{}", Arguments::usage());
+        return;
+    }
+    if arguments.version {
+        println!("server {VERSION} ({COMMIT}), protocol {PROTOCOL_VERSION}");
+        return;
+    }
+    if arguments.check {
+        std::process::exit(check::run(&arguments));
+    }
+    // resolved now since a new version may replace the file before a reexec
+    let executable = std::env::current_exe()
+        .unwrap_or_else(|_| PathBuf::from(std::env::args_os().next().unwrap_or_default()));
+
+    // load the config, it tells where to log
+    let shared_config = config::new_shared_config(
+        &arguments.config,
+        arguments.profile.as_deref(),
+        arguments.log_level,
+    )
+    .unwrap_or_else(|error| {
+        eprintln!(
+            "Can't load the config {}: {error}",
+            arguments.config.display()
+        );
+        std::process::exit(1);
+    });
+
+    // create a logger instance, also logging to the console when run by hand
+    let console = arguments.foreground || std::io::stderr().is_terminal();
+
+    // exit with the outcome of the children like an init when asked to or when run as pid 1
+    // (a container entrypoint)
+    let init_mode = arguments.init || std::process::id() == 1;
+    let exit_when_done = arguments.exit_when_done;
+    let shared_logger = new_shared_logger(&shared_config.read().unwrap().server, console)
+        .expect("Can't create the logger");
+    log_info!(
+        shared_logger,
+        "Starting taskmasterd {VERSION} ({COMMIT}), protocol {PROTOCOL_VERSION}, config {}",
+        arguments.config.display()
+    );
     log_info!(shared_logger, "Loading Config: {shared_config:?}");
+    // the orphans of the children are reaped by the daemon rather than by init, as pid 1 does
+    #[cfg(target_os = "linux")]
+    if let Err(error) = tcl::mylibc::set_child_subreaper() {
+        log_warn!(
+            shared_logger,
+            "The orphans of the children won't be reaped: {error}"
+        );
+    }
+    for (name, error) in &shared_config.read().unwrap().invalid_programs {
+        log_error!(
+            shared_logger,
+            "The program `{name}` is left out, it is invalid: {error}"
+        );
+    }
 
-    // launch the process manager
-    let shared_process_manager = new_shared_process_manager(&shared_config.read().unwrap());
+    // create the bus dispatching the daemon events
+    let shared_event_bus = new_shared_event_bus(&shared_config.read().unwrap().server);
+
+    // launch the process manager, the event listener programs are served from the bus
+    let shared_process_manager =
+        new_shared_process_manager(&shared_config.read().unwrap(), shared_event_bus.clone());
     log_info!(shared_logger, "Process Manager created");
-    log_debug!(shared_logger, "{shared_process_manager:?}");
 
-    // start the listener
+    // start the listener, or take the one of the daemon this one replaced along with its
+    // children before the monitor starts
     log_info!(shared_logger, "Starting Taskmaster Daemon");
-    let listener = TcpListener::bind(tcl::SOCKET_ADDRESS)
-        .await
-        .expect("Failed to bind tcp listener");
+    let listener = match arguments.reexec {
+        Some(fd) => {
+            shared_process_manager
+                .write()
+                .unwrap()
+                .adopt_handover(&shared_logger);
+            reexec::inherit_listener(fd).expect("Failed to inherit the tcp listener")
+        }
+        None => TcpListener::bind(arguments.address)
+            .await
+            .expect("Failed to bind tcp listener"),
+    };
+    log_debug!(shared_logger, "{shared_process_manager:?}");
 
     // start the process monitoring
-    let _monitoring_handle =
-        start_monitor(shared_process_manager.clone(), shared_logger.clone()).await; // in case we need it
+    let _monitoring_handle = start_monitor(
+        shared_process_manager.clone(),
+        shared_logger.clone(),
+        shared_event_bus.clone(),
+    )
+    .await; // in case we need it
+
+    // check the processes as soon as a child exits
+    tokio::spawn(start_sigchld_monitor(
+        shared_process_manager.clone(),
+        shared_logger.clone(),
+    ));
 
-    // handle the client connection
+    // reload the config on SIGHUP
+    tokio::spawn(reload::start_sighup_monitor(
+        shared_config.clone(),
+        shared_logger.clone(),
+        shared_process_manager.clone(),
+        shared_event_bus.clone(),
+    ));
+
+    // replace the daemon by a new instance of its executable on SIGUSR2 (or a reexec request)
+    let mut reexec_requests = signal(SignalKind::user_defined2()).expect("Can't listen to SIGUSR2");
+
+    // handle the client connection until the daemon is asked to stop or, for a oneshot
+    // workload, until every program is done
+    let exit_code = loop {
+        tokio::select! {
+            _ = accept_clients(
+                &listener,
+                shared_logger.clone(),
+                shared_config.clone(),
+                shared_process_manager.clone(),
+                shared_event_bus.clone(),
+            ) => break 0,
+            signal = wait_for_termination() => {
+                log_info!(shared_logger, "{signal:?} received, shutting down");
+                // each child leads its own process group, nothing else would stop them
+                forward_termination(&shared_process_manager, &shared_logger, &signal).await;
+                match init_mode {
+                    true => break shared_process_manager.read().unwrap().exit_code(),
+                    false => break 0,
+                }
+            }
+            _ = wait_until_done(&shared_process_manager, exit_when_done) => {
+                log_info!(shared_logger, "Every program is done, shutting down");
+                break shared_process_manager.read().unwrap().exit_code();
+            }
+            _ = reexec_requests.recv() => {
+                tokio::time::sleep(reexec::REEXEC_DELAY).await;
+                let error = reexec::reexec(
+                    &executable,
+                    &arguments,
+                    &listener,
+                    &shared_config,
+                    &shared_logger,
+                    &shared_process_manager,
+                );
+                log_error!(shared_logger, "Can't reexec, going on: {error}");
+            }
+        }
+    };
+    shared_logger.shutdown();
+    std::process::exit(exit_code);
+}
+
+/// accept the clients for ever, each one being handled in its own task
+async fn accept_clients(
+    listener: &TcpListener,
+    shared_logger: SharedLogger,
+    shared_config: SharedConfig,
+    shared_process_manager: SharedProcessManager,
+    shared_event_bus: SharedEventBus,
+) {
+    let mut next_client_id = 0;
     loop {
-        log_info!(shared_logger, "Waiting for Client To arrive");
+        log_debug!(shared_logger, "Waiting for Client To arrive");
         match listener.accept().await {
-            Ok((socket, _)) => {
-                tokio::spawn(ClientHandler::handle_client(
-                    socket,
+            Ok((socket, address)) => {
+                next_client_id += 1;
+                let client = ClientHandler::new(
+                    next_client_id,
+                    address,
                     shared_logger.clone(),
                     shared_config.clone(),
                     shared_process_manager.clone(),
-                ));
-                log_info!(shared_logger, "Client Accepted");
+                    shared_event_bus.clone(),
+                );
+                tokio::spawn(client.handle_client(socket));
             }
             Err(error) => {
                 log_error!(shared_logger, "{}", format!("Accepting Client: {error}"));
@@ -69,15 +242,78 @@ async fn main() {
     }
 }
 
+/// wait until the daemon receive SIGINT or SIGTERM, returning the signal
+async fn wait_for_termination() -> Signal {
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Can't listen to SIGINT");
+    let mut terminate = signal(SignalKind::terminate()).expect("Can't listen to SIGTERM");
+    tokio::select! {
+        _ = interrupt.recv() => Signal::SIGINT,
+        _ = terminate.recv() => Signal::SIGTERM,
+    }
+}
+
+/// send the termination signal to every managed child and wait for them to exit, the
+/// programs with the highest priority first. the monitor kill the ones still alive after
+/// their stoptime
+async fn forward_termination(
+    shared_process_manager: &SharedProcessManager,
+    shared_logger: &SharedLogger,
+    signal: &Signal,
+) {
+    let shutdown_order = shared_process_manager.read().unwrap().shutdown_order();
+    for priority in shutdown_order {
+        shared_process_manager
+            .write()
+            .unwrap()
+            .forward_signal(signal, priority, shared_logger);
+        while shared_process_manager
+            .read()
+            .unwrap()
+            .has_active_children(priority)
+        {
+            tokio::time::sleep(CHILDREN_POLL_PERIOD).await;
+        }
+    }
+}
+
+/// wait until every program reached a state it won't leave without an order,
+/// for ever if the daemon wasn't asked to exit when done
+async fn wait_until_done(shared_process_manager: &SharedProcessManager, enabled: bool) {
+    if !enabled {
+        return std::future::pending().await;
+    }
+    while !shared_process_manager.read().unwrap().is_done() {
+        tokio::time::sleep(CHILDREN_POLL_PERIOD).await;
+    }
+}
+
+/// wake the monitor each time the daemon receive SIGCHLD, for ever
+async fn start_sigchld_monitor(
+    shared_process_manager: SharedProcessManager,
+    shared_logger: SharedLogger,
+) {
+    let mut child = match signal(SignalKind::child()) {
+        Ok(child) => child,
+        Err(error) => {
+            log_error!(shared_logger, "Can't listen to SIGCHLD: {error}");
+            return;
+        }
+    };
+    while child.recv().await.is_some() {
+        shared_process_manager.read().unwrap().wake_monitor();
+    }
+}
+
 async fn start_monitor(
     shared_process_manager: SharedProcessManager,
     shared_logger: SharedLogger,
+    shared_event_bus: SharedEventBus,
 ) -> JoinHandle<()> {
     loop {
         match ProgramManager::monitor(
             shared_process_manager.clone(),
             shared_logger.clone(),
-            Duration::from_secs(1),
+            shared_event_bus.clone(),
         )
         .await
         {
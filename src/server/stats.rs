@@ -0,0 +1,242 @@
+/*!
+ * Counters about the daemon itself, updated from wherever the measured things
+ * happen and read by the `stats` request
+ */
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, SystemTime},
+};
+use tcl::{
+    message::{ConnectionStats, DaemonStats, RequestStats, LATENCY_BUCKETS},
+    mylibc,
+    time::{format_rfc3339, MonotonicTime},
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Static                                   */
+/* -------------------------------------------------------------------------- */
+pub(super) static STATS: Stats = Stats {
+    identity: OnceLock::new(),
+    monitor_iterations: AtomicU64::new(0),
+    monitor_total_nanos: AtomicU64::new(0),
+    monitor_max_nanos: AtomicU64::new(0),
+    children_spawned: AtomicU64::new(0),
+    restarts: AtomicU64::new(0),
+    bytes_streamed: AtomicU64::new(0),
+    bytes_received: AtomicU64::new(0),
+    requests: Mutex::new(BTreeMap::new()),
+    connections: Mutex::new(BTreeMap::new()),
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+pub(super) struct Stats {
+    identity: OnceLock<Identity>,
+    monitor_iterations: AtomicU64,
+    /// the time spent in the monitor loop, to compute the average iteration time
+    monitor_total_nanos: AtomicU64,
+    monitor_max_nanos: AtomicU64,
+    children_spawned: AtomicU64,
+    /// the spawns of a process that already had a child, whatever the reason
+    restarts: AtomicU64,
+    /// the bytes sent to the clients
+    bytes_streamed: AtomicU64,
+    /// the bytes received from the clients
+    bytes_received: AtomicU64,
+    /// the requests handled, by kind
+    requests: Mutex<BTreeMap<&'static str, RequestCounter>>,
+    /// the traffic of the connected clients, by client id
+    connections: Mutex<BTreeMap<u64, ConnectionTraffic>>,
+}
+
+/// the requests of a kind handled and the time it took
+#[derive(Debug, Default)]
+struct RequestCounter {
+    count: u64,
+    total_nanos: u64,
+    /// the requests handled within each of the LATENCY_BUCKETS, the last one counts the others
+    latency: [u64; LATENCY_BUCKETS.len() + 1],
+}
+
+/// the bytes exchanged with a connected client
+#[derive(Debug)]
+struct ConnectionTraffic {
+    client: String,
+    bytes_in: u64,
+    bytes_out: u64,
+}
+
+/// who the daemon is, captured at its start
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Identity {
+    pub(super) pid: mylibc::pid_t,
+    /// the process that launched the daemon (e.g. a shell, systemd or a container runtime)
+    pub(super) ppid: mylibc::pid_t,
+    /// the start on the monotonic clock, to count the uptime
+    pub(super) started: MonotonicTime,
+    /// the start on the system clock, for display
+    pub(super) start_time: SystemTime,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl Stats {
+    /// mark the start of the daemon, the uptime is counted from the first call
+    pub(super) fn start(&self) {
+        self.identity.get_or_init(Identity::current);
+    }
+
+    /// who the daemon is, None before its start was marked
+    pub(super) fn identity(&self) -> Option<Identity> {
+        self.identity.get().copied()
+    }
+
+    pub(super) fn record_monitor_iteration(&self, duration: Duration) {
+        let nanos = duration.as_nanos() as u64;
+        self.monitor_iterations.fetch_add(1, Ordering::Relaxed);
+        self.monitor_total_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.monitor_max_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_spawn(&self, restart: bool) {
+        self.children_spawned.fetch_add(1, Ordering::Relaxed);
+        if restart {
+            self.restarts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// count the bytes sent to the given client
+    pub(super) fn record_bytes_streamed(&self, client_id: u64, bytes: usize) {
+        self.bytes_streamed
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        if let Some(traffic) = self.connections.lock().unwrap().get_mut(&client_id) {
+            traffic.bytes_out += bytes as u64;
+        }
+    }
+
+    /// count the bytes received from the given client
+    pub(super) fn record_bytes_received(&self, client_id: u64, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        if let Some(traffic) = self.connections.lock().unwrap().get_mut(&client_id) {
+            traffic.bytes_in += bytes as u64;
+        }
+    }
+
+    /// count a request of the given kind handled in the given time
+    pub(super) fn record_request(&self, kind: &'static str, duration: Duration) {
+        let mut requests = self.requests.lock().unwrap();
+        let counter = requests.entry(kind).or_default();
+        counter.count += 1;
+        counter.total_nanos += duration.as_nanos() as u64;
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| duration < *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        counter.latency[bucket] += 1;
+    }
+
+    /// follow the traffic of a client from its connection
+    pub(super) fn record_connection(&self, client_id: u64, client: String) {
+        let traffic = ConnectionTraffic {
+            client,
+            bytes_in: 0,
+            bytes_out: 0,
+        };
+        self.connections.lock().unwrap().insert(client_id, traffic);
+    }
+
+    /// forget the traffic of a client once it disconnected
+    pub(super) fn record_disconnection(&self, client_id: u64) {
+        self.connections.lock().unwrap().remove(&client_id);
+    }
+
+    /// gather the counters, must be called from within the tokio runtime
+    pub(super) fn snapshot(&self) -> DaemonStats {
+        let monitor_iterations = self.monitor_iterations.load(Ordering::Relaxed);
+        let monitor_total_nanos = self.monitor_total_nanos.load(Ordering::Relaxed);
+        let identity = self.identity().unwrap_or_else(Identity::current);
+        DaemonStats {
+            pid: identity.pid,
+            ppid: identity.ppid,
+            start_time: format_rfc3339(identity.start_time),
+            uptime: identity.started.elapsed(),
+            monitor_iterations,
+            monitor_average: Duration::from_nanos(
+                monitor_total_nanos
+                    .checked_div(monitor_iterations)
+                    .unwrap_or_default(),
+            ),
+            monitor_max: Duration::from_nanos(self.monitor_max_nanos.load(Ordering::Relaxed)),
+            active_tasks: tokio::runtime::Handle::current()
+                .metrics()
+                .num_alive_tasks(),
+            children_spawned: self.children_spawned.load(Ordering::Relaxed),
+            restarts: self.restarts.load(Ordering::Relaxed),
+            bytes_streamed: self.bytes_streamed.load(Ordering::Relaxed),
+            memory: resident_memory(),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            requests: self
+                .requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(kind, counter)| RequestStats {
+                    kind: kind.to_string(),
+                    count: counter.count,
+                    average: Duration::from_nanos(
+                        counter
+                            .total_nanos
+                            .checked_div(counter.count)
+                            .unwrap_or_default(),
+                    ),
+                    latency: counter.latency.to_vec(),
+                })
+                .collect(),
+            connections: self
+                .connections
+                .lock()
+                .unwrap()
+                .values()
+                .map(|traffic| ConnectionStats {
+                    client: traffic.client.to_owned(),
+                    bytes_in: traffic.bytes_in,
+                    bytes_out: traffic.bytes_out,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Identity {
+    fn current() -> Self {
+        Identity {
+            pid: mylibc::getpid(),
+            ppid: mylibc::getppid(),
+            started: MonotonicTime::now(),
+            start_time: SystemTime::now(),
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// read the resident memory of the daemon in bytes from procfs
+fn resident_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
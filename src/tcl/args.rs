@@ -0,0 +1,118 @@
+/*!
+ * The reading of the command line shared by the binaries: an option is given as
+ * `--name value`, `--name=value` or `-n value`, `--` ends the options and the other
+ * words are positional. What each option means is left to the binaries
+ */
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use crate::error::TaskmasterError;
+use std::str::FromStr;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Module                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// the arguments of a command line, read one by one
+#[derive(Debug)]
+pub struct ArgParser<I> {
+    args: I,
+    /// the value given to the last option with `=`, it must be taken
+    attached_value: Option<(String, String)>,
+    /// whether `--` was read, the arguments after it are all positional
+    options_ended: bool,
+}
+
+/// an argument of the command line
+#[derive(Debug, PartialEq, Eq)]
+pub enum Arg {
+    /// an option (`--name` or `-n`), its value if it takes one is read with `value`
+    Option(String),
+    Positional(String),
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl ArgParser<std::iter::Skip<std::env::Args>> {
+    /// read the arguments of the process, without the name of its executable
+    pub fn from_env() -> Self {
+        Self::new(std::env::args().skip(1))
+    }
+}
+
+impl<I: Iterator<Item = String>> ArgParser<I> {
+    pub fn new(args: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            args: args.into_iter(),
+            attached_value: None,
+            options_ended: false,
+        }
+    }
+
+    /// the next argument, None once they are all read
+    pub fn next_arg(&mut self) -> Result<Option<Arg>, TaskmasterError> {
+        if let Some((option, _)) = self.attached_value.take() {
+            return Err(TaskmasterError::Custom(format!(
+                "`{option}` doesn't take a value"
+            )));
+        }
+        let Some(arg) = self.args.next() else {
+            return Ok(None);
+        };
+        if self.options_ended || arg == "-" || !arg.starts_with('-') {
+            return Ok(Some(Arg::Positional(arg)));
+        }
+        if arg == "--" {
+            self.options_ended = true;
+            return self.next_arg();
+        }
+        match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                self.attached_value = Some((option.to_owned(), value.to_owned()));
+                Ok(Some(Arg::Option(option.to_owned())))
+            }
+            _ => Ok(Some(Arg::Option(arg))),
+        }
+    }
+
+    /// the value of the option just read
+    pub fn value(&mut self, option: &str) -> Result<String, TaskmasterError> {
+        if let Some((_, value)) = self.attached_value.take() {
+            return Ok(value);
+        }
+        self.args
+            .next()
+            .ok_or_else(|| TaskmasterError::Custom(format!("`{option}` expect a value")))
+    }
+
+    /// the value of the option just read, parsed. `what` completes the error, e.g. "a port"
+    pub fn parse_value<T: FromStr>(
+        &mut self,
+        option: &str,
+        what: &str,
+    ) -> Result<T, TaskmasterError> {
+        let value = self.value(option)?;
+        value
+            .parse()
+            .map_err(|_| TaskmasterError::Custom(format!("`{value}` is not {what}")))
+    }
+
+    /// the arguments not read yet, as they are given
+    pub fn rest(&mut self) -> Vec<String> {
+        self.args.by_ref().collect()
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// the error for an argument the binary doesn't know
+pub fn unknown_argument(arg: &str) -> TaskmasterError {
+    TaskmasterError::Custom(format!("unknown argument: `{arg}`"))
+}
@@ -0,0 +1,73 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{Arg, ArgParser};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Helper                                   */
+/* -------------------------------------------------------------------------- */
+fn parser(args: &[&str]) -> ArgParser<std::vec::IntoIter<String>> {
+    ArgParser::new(args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+}
+
+fn option(name: &str) -> Option<Arg> {
+    Some(Arg::Option(name.to_owned()))
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[test]
+fn values_are_given_after_the_option_or_with_an_equal_sign() {
+    let mut args = parser(&["--config", "a.yaml", "--port=4242", "-l", "debug", "status"]);
+
+    assert_eq!(args.next_arg().unwrap(), option("--config"));
+    assert_eq!(args.value("--config").unwrap(), "a.yaml");
+    assert_eq!(args.next_arg().unwrap(), option("--port"));
+    assert_eq!(args.parse_value::<u16>("--port", "a port").unwrap(), 4242);
+    assert_eq!(args.next_arg().unwrap(), option("-l"));
+    assert_eq!(args.value("-l").unwrap(), "debug");
+    assert_eq!(
+        args.next_arg().unwrap(),
+        Some(Arg::Positional("status".to_owned()))
+    );
+    assert_eq!(args.next_arg().unwrap(), None);
+}
+
+#[test]
+fn missing_unexpected_or_invalid_values_are_errors() {
+    let mut args = parser(&["--port"]);
+    args.next_arg().unwrap();
+    assert_eq!(
+        args.value("--port").unwrap_err().to_string(),
+        "`--port` expect a value"
+    );
+
+    let mut args = parser(&["--json=yes"]);
+    args.next_arg().unwrap();
+    assert_eq!(
+        args.next_arg().unwrap_err().to_string(),
+        "`--json` doesn't take a value"
+    );
+
+    let mut args = parser(&["--port", "http"]);
+    args.next_arg().unwrap();
+    assert_eq!(
+        args.parse_value::<u16>("--port", "a port")
+            .unwrap_err()
+            .to_string(),
+        "`http` is not a port"
+    );
+}
+
+#[test]
+fn arguments_after_the_double_dash_are_positional() {
+    let mut args = parser(&["--", "--help", "-"]);
+
+    assert_eq!(
+        args.next_arg().unwrap(),
+        Some(Arg::Positional("--help".to_owned()))
+    );
+    assert_eq!(args.rest(), ["-"]);
+}
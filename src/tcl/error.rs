@@ -15,6 +15,8 @@ pub enum TaskmasterError {
     StringConversionError(std::string::FromUtf8Error),
     Custom(String), // this will disappear over time
     MessageTooLong,
+    /// no response was received in the given time
+    Timeout(std::time::Duration),
 }
 
 /* -------------------------------------------------------------------------- */
@@ -29,6 +31,9 @@ impl std::fmt::Display for TaskmasterError {
             TE::IoError(e) => write!(f, "IO error: {}", e),
             TE::SerdeError(e) => write!(f, "Serialization error: {e}"),
             TE::MessageTooLong => write!(f, "Message exceeds maximum length"),
+            TE::Timeout(duration) => {
+                write!(f, "No response received after {}s", duration.as_secs())
+            }
             TE::Custom(e) => write!(f, "{e}"),
             TE::StringConversionError(e) => write!(f, "String Conversion Error: {e}"),
         }
@@ -7,13 +7,16 @@ use std::net::Ipv4Addr;
 /* -------------------------------------------------------------------------- */
 /*                                   Module                                   */
 /* -------------------------------------------------------------------------- */
+pub mod args;
 pub mod error;
 pub mod message;
+pub mod mylibc;
+pub mod time;
 
 /* -------------------------------------------------------------------------- */
 /*                                  Constant                                  */
 /* -------------------------------------------------------------------------- */
-const PORT: u16 = 8042;
+pub const PORT: u16 = 8042;
 pub const ADDRESS: Ipv4Addr = Ipv4Addr::LOCALHOST;
 pub const SOCKET_ADDRESS: SocketAddrV4 = SocketAddrV4::new(ADDRESS, PORT);
 pub const MAX_MESSAGE_SIZE: u32 = 1024 * 1024;
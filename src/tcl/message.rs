@@ -1,7 +1,7 @@
 /*!
  * This Module is responsible for the transport of message (Serialization and deserialization)
- * and provide a unify interface for all binary needing to use it with two generic function
- * send and receive, it use it's own protocol to control the length of a given message,
+ * and provide a unify interface for all binary needing to use it with the Framed codec, and the
+ * send and receive functions for a single message, it use it's own protocol to control the length of a given message,
  * those should not exceed 1 MB. This module also provide a unify place for the common used struct
  * during message exchange. it was decided that the protocol expect a response after a request no matter what
  * so a client should expect to receive a response after a request
@@ -9,16 +9,49 @@
 /* -------------------------------------------------------------------------- */
 /*                                   Import                                   */
 /* -------------------------------------------------------------------------- */
-use crate::{error::TaskmasterError, MAX_MESSAGE_SIZE};
-use serde::{Deserialize, Serialize};
+use crate::error::TaskmasterError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     fmt::Display,
+    str::FromStr,
     time::{Duration, SystemTime},
 };
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
-};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Module                                   */
+/* -------------------------------------------------------------------------- */
+mod framed;
+#[cfg(test)]
+mod tests;
+
+pub use framed::Framed;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// identifier of the messages sent by the server without being asked, requests
+/// must therefore never use it
+pub const UNSOLICITED_ID: u64 = 0;
+
+/// the version of the messages exchanged by the client and the daemon, bumped whenever
+/// a change breaks their compatibility
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// the version of the package the binaries are built from
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// the commit the binaries are built from, "unknown" if it isn't a git checkout
+pub const COMMIT: &str = env!("TASKMASTER_COMMIT");
+
+/// the upper bounds of the buckets the handling times of the requests are counted in,
+/// the last bucket counts the longer ones
+pub const LATENCY_BUCKETS: [Duration; 4] = [
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+];
 
 /* -------------------------------------------------------------------------- */
 /*                               Message Struct                               */
@@ -29,34 +62,392 @@ pub enum Response {
     Success(String),
     Error(String),
     Status(Vec<ProgramStatus>),
+    /// something that happened in the daemon, only sent to the clients that subscribed
+    Event(Event),
+    Stats(Box<DaemonStats>),
+    /// an order given to a program didn't fully succeed, detailed process by process
+    OrderFailed(OrderReport),
+    /// the last lines written on stdout by the processes of a program, oldest first
+    History(Vec<OutputLine>),
+    /// the lines of the stdout history matching a pattern, oldest first
+    Matches(Vec<OutputLine>),
+    /// a line written by a process of the attached program, pushed as it is written
+    Output(OutputLine),
+    /// the attached client couldn't keep up, this many output lines were skipped
+    OutputSkipped(u64),
+    /// no more output of the program will be pushed, it follows the last line of a detach
+    /// or of a program removed by a reload
+    StreamEnd(String),
+    /// what the daemon is and the config it runs
+    Version(VersionInfo),
+    /// a response along with something the operator should know about the daemon (e.g. its
+    /// config file changed since it was loaded)
+    Warned {
+        warning: String,
+        response: Box<Response>,
+    },
+}
+
+/// a line written on stdout by a process, as kept in the history of its program
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OutputLine {
+    /// when the daemon read the line (RFC3339)
+    pub time: String,
+    /// the index of the process in its program
+    pub replica: usize,
+    pub line: String,
+    /// the program that wrote the line, only given in a merged feed
+    #[serde(default)]
+    pub program: Option<String>,
+}
+
+/// the outcome of an order given to a program, replica (process) by replica
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrderReport {
+    pub program: String,
+    /// what was ordered (e.g. "stopping")
+    pub action: String,
+    /// whenever the order failed on every replica
+    pub total_failure: bool,
+    pub replicas: Vec<ReplicaResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReplicaResult {
+    /// the index of the process in the program
+    pub replica: usize,
+    /// why the order didn't apply to this replica, None if it did
+    pub error: Option<ReplicaError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReplicaError {
+    pub kind: ReplicaErrorKind,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicaErrorKind {
+    /// the order made no sense for the replica (e.g. stopping a stopped process)
+    Skipped,
+    /// the order was attempted and failed
+    Failed,
 }
 
 /// Represent what can be send to the server as request
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Request {
-    Status,
+    Status(StatusQuery),
     Start(String),
     Stop(String),
     Restart(String),
+    /// restart the processes of a program one at a time
+    RollingRestart(String),
+    /// restart a program, then the programs depending on it one after the other
+    CascadeRestart(String),
+    /// start a program, it is stopped gracefully once the duration elapsed
+    Run {
+        program: String,
+        duration: std::time::Duration,
+    },
     Reload,
+    /// replace the daemon by a new instance of its executable, the programs keep running
+    Reexec,
+    /// get the minimum level of the daemon logs, or set it if a level is given
+    LogLevel(Option<LogLevel>),
+    /// send its reload signal to the processes of a program, without restarting them
+    ReloadProgram(String),
+    /// suspend the processes of a program with SIGSTOP
+    Pause(String),
+    /// resume the processes of a program paused with SIGCONT
+    Resume(String),
+    /// turn the maintenance mode of a program on or off
+    Maintenance(String, bool),
+    /// stop restarting the processes of a program that exit, until resumed or the duration
+    /// elapsed
+    PauseRestarts {
+        program: String,
+        duration: Option<std::time::Duration>,
+    },
+    /// restart again the processes of a program that exit
+    ResumeRestarts(String),
+    /// ask the server to push its events on this connection
+    Subscribe,
+    /// get the statistics of the daemon itself
+    Stats,
+    /// get the version of the daemon
+    Version,
+    /// get the last lines written on stdout by the processes of a program
+    History {
+        program: String,
+        lines: usize,
+    },
+    /// get the lines of the stdout history of a program matching a regex
+    Grep {
+        program: String,
+        pattern: String,
+    },
+    /// stream the output of a program on this connection, replacing the previous one
+    Attach {
+        program: String,
+        /// what to do when the client can't keep up with the output
+        #[serde(default)]
+        on_lag: LagPolicy,
+    },
+    /// stream the output of several programs merged into one feed on this connection, each
+    /// line tagged with its program, replacing the program attached to
+    Tail {
+        programs: Vec<String>,
+    },
+    /// stop streaming the output of the attached program, or of the tailed ones
+    Detach,
+    /// write to the stdin of the running processes of a program
+    Stdin {
+        program: String,
+        data: String,
+    },
+}
+
+/// Represent something that happened in the daemon, pushed to the subscribed clients
+/// and to the events webhook. it is tagged as a map since yaml can't nest enums
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "detail")]
+pub enum Event {
+    /// the config was reloaded and applied
+    ConfigReloaded,
+    /// the config reload was rejected, the previous config is still in use
+    ConfigReloadFailed(String),
+    /// a program crossed one of its disk guardrails (output size or free disk space)
+    DiskGuardrail { program: String, message: String },
+    /// a process of a program changed state
+    ProcessState {
+        program: String,
+        replica: usize,
+        from: ProcessState,
+        to: ProcessState,
+        pid: Option<u32>,
+        /// why the process changed state (e.g. autorestart)
+        #[serde(default)]
+        cause: String,
+    },
+}
+
+/// the severity of a log entry, ordered from the most to the least verbose
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+/// Wrap a request or a response with an identifier, the server answer a request
+/// with the identifier it was sent with so the client can pair them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Identified<T> {
+    pub id: u64,
+    pub message: T,
+}
+
+/// the filter and order of a status request, resolved by the server
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatusQuery {
+    /// only the processes in one of these states are shown, every process if empty
+    pub states: Vec<ProcessState>,
+    /// only the programs with this tag are shown, every program if none
+    pub tag: Option<String>,
+    pub sort: StatusSort,
+    /// whenever the config of the programs is summarized along their status
+    #[serde(default)]
+    pub verbose: bool,
+}
+
+/// the order in which the programs are shown by a status request
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusSort {
+    #[default]
+    Name,
+    /// the program running for the longest time first
+    Uptime,
+    /// the program with the most restarts first
+    Restarts,
+}
+
+/// what the daemon does when an attached client can't keep up with the output of the
+/// program, the program itself is never blocked by a slow client
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LagPolicy {
+    /// skip the lines the client missed, telling it how many
+    #[default]
+    Drop,
+    /// end the stream, telling the client how many lines it missed
+    Disconnect,
+}
+
+/// the version of the daemon, for the client to tell if they can understand each other
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub commit: String,
+    pub protocol: u32,
+    /// the config file the daemon loaded
+    pub config_path: String,
+}
+
+/// the statistics of the daemon itself, for capacity planning and debugging slowdowns
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DaemonStats {
+    #[serde(default)]
+    pub pid: i32,
+    /// the process that launched the daemon
+    #[serde(default)]
+    pub ppid: i32,
+    /// when the daemon started (RFC3339)
+    #[serde(default)]
+    pub start_time: String,
+    pub uptime: Duration,
+    pub monitor_iterations: u64,
+    /// the average time taken by an iteration of the monitor loop
+    pub monitor_average: Duration,
+    /// the longest time taken by an iteration of the monitor loop
+    pub monitor_max: Duration,
+    /// the number of tasks alive in the daemon runtime
+    pub active_tasks: usize,
+    pub children_spawned: u64,
+    pub restarts: u64,
+    /// the number of bytes sent to the clients
+    pub bytes_streamed: u64,
+    /// the resident memory of the daemon in bytes, if it could be read
+    pub memory: Option<u64>,
+    /// the number of bytes received from the clients
+    #[serde(default)]
+    pub bytes_received: u64,
+    /// the requests handled by kind, sorted by name
+    #[serde(default)]
+    pub requests: Vec<RequestStats>,
+    /// the traffic of the clients connected
+    #[serde(default)]
+    pub connections: Vec<ConnectionStats>,
+}
+
+/// how many requests of a kind were handled and how long it took
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RequestStats {
+    /// the name of the request (e.g. Status)
+    pub kind: String,
+    pub count: u64,
+    pub average: Duration,
+    /// the number of requests handled within each of the LATENCY_BUCKETS, and beyond
+    pub latency: Vec<u64>,
+}
+
+/// the bytes exchanged with a connected client
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConnectionStats {
+    /// the client as shown in the daemon log (e.g. client#1 127.0.0.1:51234)
+    pub client: String,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProgramStatus {
     pub name: String,
+    /// whenever the exits of the program are ignored by the monitor
+    #[serde(default)]
+    pub maintenance: bool,
+    /// whenever the processes that exit are left as is instead of being restarted
+    #[serde(default)]
+    pub restarts_paused: bool,
+    /// the maintenance window the program is in (e.g. sat,sun 02:00-04:00)
+    #[serde(default)]
+    pub maintenance_window: Option<String>,
+    /// whenever the program should run but less than min_running of its processes are running
+    #[serde(default)]
+    pub degraded: bool,
+    /// the number of processes running, whatever the processes kept by a status query
+    #[serde(default)]
+    pub running: usize,
+    /// the number of processes that must be running for the program to be healthy
+    #[serde(default)]
+    pub min_running: usize,
+    /// the output lines left out of the history and of the attached clients by outputratelimit
+    #[serde(default)]
+    pub skipped_output_lines: u64,
+    /// what the daemon does with the program, only sent for a verbose status
+    #[serde(default)]
+    pub summary: Option<ProgramSummary>,
+    /// what the processes of the program use, if it has a cgroup
+    #[serde(default)]
+    pub cgroup: Option<CgroupUsage>,
+    /// why the definition of the program is invalid, it then has no processes
+    #[serde(default)]
+    pub config_error: Option<String>,
     pub status: Vec<ProcessStatus>,
 }
 
+/// the config facts telling what the daemon does with a program
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProgramSummary {
+    pub command: String,
+    pub numprocs: usize,
+    /// when a process is restarted after it exited (always, unexpected or never)
+    pub autorestart: String,
+    /// the exit codes that are expected, the others are unexpected
+    pub exitcodes: Vec<i32>,
+    /// the signals the processes can be terminated by expectedly
+    #[serde(default)]
+    pub expected_signals: Vec<String>,
+    pub stopsignal: String,
+}
+
+/// the usage read from the cgroup of a program, a value is missing when its controller isn't enabled
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct CgroupUsage {
+    pub cpu_usage_usec: Option<u64>,
+    pub memory_bytes: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ProcessStatus {
     pub status: ProcessState,
     pub pid: Option<u32>,
     pub start_time: Option<SystemTime>,
     pub shutdown_time: Option<SystemTime>,
+    /// the restarts attempted while starting, limited by startretries
     pub number_of_restart: u32,
+    /// the restarts done by the autorestart policy after an exit from the running state
+    #[serde(default)]
+    pub number_of_autorestart: u32,
+    /// whenever the process was suspended by a pause request
+    #[serde(default)]
+    pub paused: bool,
+    /// why the process last changed state (e.g. autorestart, stop by client#1 127.0.0.1)
+    #[serde(default)]
+    pub last_change: Option<String>,
+    /// what the daemon is about to do with the process (e.g. stopping: SIGTERM sent 4s ago,
+    /// SIGKILL in 6s), along with the orders of its program still to take effect
+    #[serde(default)]
+    pub pending: Option<String>,
+    /// how the last child of the process ended, kept while it is not running again
+    #[serde(default)]
+    pub last_exit: Option<ProcessExit>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// how a child ended, a signal number is never mistaken for an exit code
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessExit {
+    /// the child exited with the code
+    Exited(i32),
+    /// the child was terminated by the signal
+    ExitedBySignal(i32),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessState {
     /// the default state, has never been started.
     NeverStartedYet,
@@ -91,52 +482,152 @@ pub enum ProcessState {
 }
 
 /* -------------------------------------------------------------------------- */
-/*                                  Function                                  */
+/*                            Struct Implementation                           */
 /* -------------------------------------------------------------------------- */
-/// write the message to the socket returning an error if it fails
-pub async fn send<'a, T: Serialize>(
-    stream: &mut TcpStream,
-    message: &T,
-) -> Result<(), TaskmasterError> {
-    // serialize the message
-    let serialized_message = serde_yaml::to_string(message)?;
+impl StatusQuery {
+    /// keep the processes in the requested states, dropping the programs left without any,
+    /// and sort the programs
+    pub fn apply(&self, mut programs: Vec<ProgramStatus>) -> Vec<ProgramStatus> {
+        if !self.states.is_empty() {
+            programs.iter_mut().for_each(|program| {
+                program
+                    .status
+                    .retain(|process| self.states.contains(&process.status))
+            });
+            programs.retain(|program| !program.status.is_empty());
+        }
 
-    // check the message length and transform the length to send it with the message
-    let length = serialized_message.len();
-    if length as u32 > MAX_MESSAGE_SIZE {
-        return Err(TaskmasterError::MessageTooLong);
+        programs.sort_by(|a, b| a.name.cmp(&b.name));
+        match self.sort {
+            StatusSort::Name => {}
+            // a program that is not running has no start time and goes last
+            StatusSort::Uptime => programs.sort_by_key(|program| {
+                let started = program
+                    .status
+                    .iter()
+                    .filter_map(|process| process.start_time)
+                    .min();
+                (started.is_none(), started)
+            }),
+            StatusSort::Restarts => programs.sort_by_key(|program| {
+                std::cmp::Reverse(
+                    program
+                        .status
+                        .iter()
+                        .map(|process| process.number_of_restart + process.number_of_autorestart)
+                        .sum::<u32>(),
+                )
+            }),
+        }
+        programs
     }
-    let length_in_byte = (length as u32).to_be_bytes();
+}
 
-    // write the message to the socket preceded by it's length
-    stream.write_all(&length_in_byte).await?;
-    stream.write_all(serialized_message.as_bytes()).await?;
+impl ProgramStatus {
+    /// the status of a program left out of the config because its definition is invalid
+    pub fn invalid(name: String, error: String) -> Self {
+        Self {
+            name,
+            maintenance: false,
+            restarts_paused: false,
+            maintenance_window: None,
+            degraded: false,
+            running: 0,
+            min_running: 0,
+            skipped_output_lines: 0,
+            summary: None,
+            cgroup: None,
+            config_error: Some(error),
+            status: Vec::new(),
+        }
+    }
+}
 
-    Ok(())
+impl Request {
+    /// the name of the kind of request, for the statistics
+    pub fn name(&self) -> &'static str {
+        match self {
+            Request::Status(_) => "Status",
+            Request::Start(_) => "Start",
+            Request::Stop(_) => "Stop",
+            Request::Restart(_) => "Restart",
+            Request::RollingRestart(_) => "RollingRestart",
+            Request::CascadeRestart(_) => "CascadeRestart",
+            Request::Run { .. } => "Run",
+            Request::Reload => "Reload",
+            Request::Reexec => "Reexec",
+            Request::LogLevel(_) => "LogLevel",
+            Request::ReloadProgram(_) => "ReloadProgram",
+            Request::Pause(_) => "Pause",
+            Request::Resume(_) => "Resume",
+            Request::Maintenance(..) => "Maintenance",
+            Request::PauseRestarts { .. } => "PauseRestarts",
+            Request::ResumeRestarts(_) => "ResumeRestarts",
+            Request::Subscribe => "Subscribe",
+            Request::Stats => "Stats",
+            Request::Version => "Version",
+            Request::History { .. } => "History",
+            Request::Grep { .. } => "Grep",
+            Request::Attach { .. } => "Attach",
+            Request::Tail { .. } => "Tail",
+            Request::Detach => "Detach",
+            Request::Stdin { .. } => "Stdin",
+        }
+    }
 }
 
-/// receive a message and try to deserialize it into the type T
-pub async fn receive<T: for<'a> Deserialize<'a>>(
-    stream: &mut TcpStream,
-) -> Result<T, TaskmasterError> {
-    // get the length of the incoming message and check if the message can be received
-    let mut length_bytes = [0u8; 4];
-    stream.read_exact(&mut length_bytes).await?;
-    let message_length = u32::from_be_bytes(length_bytes) as usize;
-    if message_length as u32 > MAX_MESSAGE_SIZE {
-        return Err(TaskmasterError::MessageTooLong);
+impl VersionInfo {
+    /// the version of this binary, running the given config file
+    pub fn current(config_path: String) -> Self {
+        Self {
+            version: VERSION.to_owned(),
+            commit: COMMIT.to_owned(),
+            protocol: PROTOCOL_VERSION,
+            config_path,
+        }
     }
 
-    // read the rest of the message
-    let mut buffer = vec![0u8; message_length];
-    stream.read_exact(&mut buffer).await?;
+    /// tell how the version of the daemon differs from the one of this binary if it may not
+    /// understand it: another protocol, or another major or minor version
+    pub fn mismatch(&self) -> Option<String> {
+        let release = |version: &str| version.split('.').take(2).collect::<Vec<_>>().join(".");
+        if self.protocol != PROTOCOL_VERSION {
+            Some(format!(
+                "the daemon speaks the protocol {} and the client the protocol {PROTOCOL_VERSION}",
+                self.protocol
+            ))
+        } else if release(&self.version) != release(VERSION) {
+            Some(format!(
+                "the daemon is in version {} and the client in version {VERSION}",
+                self.version
+            ))
+        } else {
+            None
+        }
+    }
+}
 
-    // deserialize the message into the demanded struct
-    let yaml_string = String::from_utf8(buffer)?;
-    let received_message: T = serde_yaml::from_str(&yaml_string)?;
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// write the message to the stream returning the number of bytes written or an error if it fails,
+/// a send that is cancelled may leave a part of the message on the stream unlike Framed::send
+pub async fn send<S: AsyncWrite + Unpin, T: Serialize>(
+    stream: &mut S,
+    message: &T,
+) -> Result<usize, TaskmasterError> {
+    Framed::new(stream).send(message).await
+}
 
-    // return the message if everything went right
-    Ok(received_message)
+/// receive a message and try to deserialize it into the type T, a receive that is cancelled
+/// may leave a part of the message on the stream unlike Framed::receive
+pub async fn receive<S: AsyncRead + Unpin, T: DeserializeOwned>(
+    stream: &mut S,
+) -> Result<T, TaskmasterError> {
+    Framed::new(stream)
+        .receive()
+        .await
+        .map(|(message, _)| message)
 }
 
 /* -------------------------------------------------------------------------- */
@@ -150,6 +641,213 @@ fn format_duration(duration: Duration) -> String {
     format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
 }
 
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Debug => write!(f, "DEBUG"),
+            LogLevel::Info => write!(f, "INFO"),
+            LogLevel::Warn => write!(f, "WARN"),
+            LogLevel::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = TaskmasterError;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level.to_ascii_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(TaskmasterError::Custom(format!(
+                "`{level}` is not a log level (debug, info, warn, error)"
+            ))),
+        }
+    }
+}
+
+impl Display for VersionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "┌─ Daemon Version ───────────────────────────────────")?;
+        writeln!(f, "│ {:20} {} ({})", "Version:", self.version, self.commit)?;
+        writeln!(f, "│ {:20} {}", "Protocol:", self.protocol)?;
+        writeln!(f, "│ {:20} {}", "Config file:", self.config_path)?;
+        writeln!(f, "└────────────────────────────────────────────────────")
+    }
+}
+
+impl Display for RequestStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:20} {} (avg {:?})",
+            self.kind, self.count, self.average
+        )?;
+        for (index, count) in self.latency.iter().enumerate() {
+            match (LATENCY_BUCKETS.get(index), LATENCY_BUCKETS.last()) {
+                (Some(bound), _) => write!(f, " <{bound:?}: {count}")?,
+                (None, Some(last)) => write!(f, " ≥{last:?}: {count}")?,
+                (None, None) => write!(f, " {count}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for DaemonStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "┌─ Daemon Stats ─────────────────────────────────────")?;
+        writeln!(f, "│ {:20} {} (parent {})", "PID:", self.pid, self.ppid)?;
+        writeln!(f, "│ {:20} {}", "Started:", self.start_time)?;
+        writeln!(f, "│ {:20} {}", "Uptime:", format_duration(self.uptime))?;
+        writeln!(
+            f,
+            "│ {:20} {} (avg {:?}, max {:?})",
+            "Monitor iterations:", self.monitor_iterations, self.monitor_average, self.monitor_max
+        )?;
+        writeln!(f, "│ {:20} {}", "Active tasks:", self.active_tasks)?;
+        writeln!(f, "│ {:20} {}", "Children spawned:", self.children_spawned)?;
+        writeln!(f, "│ {:20} {}", "Restarts:", self.restarts)?;
+        writeln!(f, "│ {:20} {}", "Bytes streamed:", self.bytes_streamed)?;
+        writeln!(f, "│ {:20} {}", "Bytes received:", self.bytes_received)?;
+        writeln!(
+            f,
+            "│ {:20} {}",
+            "Memory:",
+            self.memory.map_or("Unknown".to_string(), |bytes| format!(
+                "{} KiB",
+                bytes / 1024
+            ))
+        )?;
+        if !self.requests.is_empty() {
+            writeln!(f, "├─ Requests ─────────────────────────────────────────")?;
+            for request in &self.requests {
+                writeln!(f, "│ {request}")?;
+            }
+        }
+        if !self.connections.is_empty() {
+            writeln!(f, "├─ Connections ──────────────────────────────────────")?;
+            for connection in &self.connections {
+                writeln!(
+                    f,
+                    "│ {:40} in {} B, out {} B",
+                    connection.client, connection.bytes_in, connection.bytes_out
+                )?;
+            }
+        }
+        writeln!(f, "└────────────────────────────────────────────────────")
+    }
+}
+
+impl Event {
+    /// the name of the kind of event, as listened to by the event listeners
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::ConfigReloaded => "ConfigReloaded",
+            Event::ConfigReloadFailed(_) => "ConfigReloadFailed",
+            Event::DiskGuardrail { .. } => "DiskGuardrail",
+            Event::ProcessState { .. } => "ProcessState",
+        }
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::ConfigReloaded => write!(f, "config reloaded"),
+            Event::ConfigReloadFailed(error) => write!(f, "config reload rejected: {error}"),
+            Event::DiskGuardrail { program, message } => {
+                write!(f, "{program} crossed a disk guardrail: {message}")
+            }
+            Event::ProcessState {
+                program,
+                replica,
+                from,
+                to,
+                pid,
+                cause,
+            } => {
+                write!(f, "{program} process {replica}: {from} -> {to}")?;
+                if let Some(pid) = pid {
+                    write!(f, ", pid {pid}")?;
+                }
+                write!(f, " ({cause})")
+            }
+        }
+    }
+}
+
+impl FromStr for StatusSort {
+    type Err = TaskmasterError;
+
+    fn from_str(sort: &str) -> Result<Self, Self::Err> {
+        match sort.to_ascii_lowercase().as_str() {
+            "name" => Ok(StatusSort::Name),
+            "uptime" => Ok(StatusSort::Uptime),
+            "restarts" => Ok(StatusSort::Restarts),
+            _ => Err(TaskmasterError::Custom(format!(
+                "`{sort}` is not a status order (name, uptime, restarts)"
+            ))),
+        }
+    }
+}
+
+impl FromStr for LagPolicy {
+    type Err = TaskmasterError;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.to_ascii_lowercase().as_str() {
+            "drop" => Ok(LagPolicy::Drop),
+            "disconnect" => Ok(LagPolicy::Disconnect),
+            _ => Err(TaskmasterError::Custom(format!(
+                "`{policy}` is not a lag policy (drop, disconnect)"
+            ))),
+        }
+    }
+}
+
+/// the state names are case insensitive and can be written with `_` or `-` (e.g. `exited_unexpectedly`)
+impl FromStr for ProcessState {
+    type Err = TaskmasterError;
+
+    fn from_str(state: &str) -> Result<Self, Self::Err> {
+        let normalized: String = state
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match normalized.as_str() {
+            "neverstartedyet" => Ok(ProcessState::NeverStartedYet),
+            "stopped" => Ok(ProcessState::Stopped),
+            "starting" => Ok(ProcessState::Starting),
+            "running" => Ok(ProcessState::Running),
+            "backoff" => Ok(ProcessState::Backoff),
+            "stopping" => Ok(ProcessState::Stopping),
+            "exitedexpectedly" => Ok(ProcessState::ExitedExpectedly),
+            "exitedunexpectedly" => Ok(ProcessState::ExitedUnExpectedly),
+            "fatal" => Ok(ProcessState::Fatal),
+            "unknown" => Ok(ProcessState::Unknown),
+            _ => Err(TaskmasterError::Custom(format!(
+                "`{state}` is not a process state"
+            ))),
+        }
+    }
+}
+
+impl Display for ProcessExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessExit::Exited(code) => write!(f, "exit code {code}"),
+            ProcessExit::ExitedBySignal(signal) => match crate::mylibc::signal_name(*signal) {
+                Some(name) => write!(f, "killed by {name}"),
+                None => write!(f, "killed by signal {signal}"),
+            },
+        }
+    }
+}
+
 impl Display for ProcessState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self:#10?}")
@@ -159,7 +857,11 @@ impl Display for ProcessState {
 impl Display for ProcessStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "┌─ Process Status ───────────────────────────────────")?;
-        writeln!(f, "│ {:20} {}", "State:", self.status)?;
+        if self.paused {
+            writeln!(f, "│ {:20} {} (paused)", "State:", self.status)?;
+        } else {
+            writeln!(f, "│ {:20} {}", "State:", self.status)?;
+        }
         writeln!(
             f,
             "│ {:20} {}",
@@ -185,14 +887,57 @@ impl Display for ProcessStatus {
                     SystemTime::now().duration_since(time).unwrap()
                 ))
         )?;
-        writeln!(f, "│ {:20} {}", "Restarts:", self.number_of_restart)?;
+        writeln!(f, "│ {:20} {}", "Start retries:", self.number_of_restart)?;
+        writeln!(f, "│ {:20} {}", "Autorestarts:", self.number_of_autorestart)?;
+        if let Some(last_change) = &self.last_change {
+            writeln!(f, "│ {:20} {}", "Last change:", last_change)?;
+        }
+        if let Some(pending) = &self.pending {
+            writeln!(f, "│ {:20} {}", "Pending:", pending)?;
+        }
+        if let Some(last_exit) = &self.last_exit {
+            writeln!(f, "│ {:20} {}", "Last exit:", last_exit)?;
+        }
         writeln!(f, "└────────────────────────────────────────────────────")
     }
 }
 
 impl Display for ProgramStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Program: {}", self.name)?;
+        write!(f, "Program: {}", self.name)?;
+        if self.maintenance {
+            write!(f, " 🔧 Maintenance")?;
+        }
+        if self.restarts_paused {
+            write!(f, " ⏸️ Restarts paused")?;
+        }
+        if let Some(window) = &self.maintenance_window {
+            write!(f, " 🕑 Maintenance window {window}")?;
+        }
+        if self.degraded {
+            write!(
+                f,
+                " ⚠️ Degraded ({}/{} running)",
+                self.running, self.min_running
+            )?;
+        }
+        if let Some(error) = &self.config_error {
+            write!(f, " ❌ ConfigError: {error}")?;
+        }
+        writeln!(f)?;
+        if let Some(summary) = &self.summary {
+            writeln!(f, "{summary}")?;
+        }
+        if let Some(usage) = &self.cgroup {
+            writeln!(f, "{usage}")?;
+        }
+        if self.skipped_output_lines > 0 {
+            writeln!(
+                f,
+                "  output: {} lines skipped by the rate limit",
+                self.skipped_output_lines
+            )?;
+        }
         for (index, process) in self.status.iter().enumerate() {
             if index > 0 {
                 writeln!(f)?;
@@ -203,11 +948,130 @@ impl Display for ProgramStatus {
     }
 }
 
+impl Display for ProgramSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "  command: {} ({} processes)",
+            self.command, self.numprocs
+        )?;
+        write!(
+            f,
+            "  on exit: autorestart {} (expected exit codes {:?}",
+            self.autorestart, self.exitcodes
+        )?;
+        if !self.expected_signals.is_empty() {
+            write!(f, ", signals {}", self.expected_signals.join(", "))?;
+        }
+        write!(f, "), stopped with {}", self.stopsignal)
+    }
+}
+
+impl Display for CgroupUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "  cgroup:")?;
+        match self.cpu_usage_usec {
+            Some(usec) => write!(f, " cpu {:.2}s", usec as f64 / 1_000_000.0)?,
+            None => write!(f, " cpu n/a")?,
+        }
+        match self.memory_bytes {
+            Some(bytes) => write!(f, ", memory {:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+            None => write!(f, ", memory n/a"),
+        }
+    }
+}
+
+impl Response {
+    /// whenever the response report a failure
+    pub fn is_error(&self) -> bool {
+        match self {
+            Response::Warned { response, .. } => response.is_error(),
+            response => matches!(response, Response::Error(_) | Response::OrderFailed(_)),
+        }
+    }
+}
+
+impl OrderReport {
+    /// a one line description of the failed replicas, e.g. for a log entry
+    pub fn summary(&self) -> String {
+        let failed: Vec<String> = self
+            .replicas
+            .iter()
+            .filter_map(|replica| {
+                let error = replica.error.as_ref()?;
+                Some(format!(
+                    "replica {} {:?}: {}",
+                    replica.replica, error.kind, error.message
+                ))
+            })
+            .collect();
+        let outcome = match self.total_failure {
+            true => "Failed",
+            false => "Partial success",
+        };
+        format!(
+            "{outcome} {} program '{}': {}",
+            self.action,
+            self.program,
+            failed.join(", ")
+        )
+    }
+}
+
+impl Display for OrderReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match self.total_failure {
+            true => "Failed",
+            false => "Partial success",
+        };
+        writeln!(
+            f,
+            "❌ {:15} {outcome} {} program '{}'",
+            "Error:", self.action, self.program
+        )?;
+        for replica in &self.replicas {
+            match &replica.error {
+                None => writeln!(f, "   replica {}: ok", replica.replica)?,
+                Some(error) => writeln!(
+                    f,
+                    "   replica {}: {:?}, {}",
+                    replica.replica, error.kind, error.message
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Display for Response {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Response::Success(_) => writeln!(f, "✅ {:15}", "Success"),
+            Response::Success(message) => writeln!(f, "✅ {:15} {}", "Success:", message),
             Response::Error(e) => writeln!(f, "❌ {:15} {}", "Error:", e),
+            Response::Event(event) => writeln!(f, "🔔 {:15} {}", "Event:", event),
+            Response::Stats(stats) => write!(f, "{stats}"),
+            Response::Version(version) => write!(f, "{version}"),
+            Response::OrderFailed(report) => write!(f, "{report}"),
+            Response::History(lines) => lines
+                .iter()
+                .try_for_each(|line| writeln!(f, "{}", line.line)),
+            Response::Output(OutputLine {
+                program: Some(program),
+                line,
+                ..
+            }) => writeln!(f, "[{program}] {line}"),
+            Response::Output(line) => writeln!(f, "{}", line.line),
+            Response::OutputSkipped(missed) => {
+                writeln!(f, "⚠️  {:15} {missed} lines", "Skipped output:")
+            }
+            Response::StreamEnd(program) => writeln!(f, "⏹️  {:15} {}", "End of output:", program),
+            Response::Warned { warning, response } => {
+                writeln!(f, "⚠️  {:15} {}", "Warning:", warning)?;
+                write!(f, "{response}")
+            }
+            Response::Matches(lines) => lines
+                .iter()
+                .try_for_each(|line| writeln!(f, "{} [{}] {}", line.time, line.replica, line.line)),
             Response::Status(vec) => {
                 writeln!(f, "📊 Programs Status:")?;
                 writeln!(f)?;
@@ -0,0 +1,133 @@
+/*!
+ * The framing of the messages on a stream: each message is the big endian u32 length of its
+ * yaml followed by the yaml. The same codec is used by the client and the daemon, in both
+ * directions, whatever the stream (a tcp one or the in-memory pipe of the tests)
+ */
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use crate::{error::TaskmasterError, MAX_MESSAGE_SIZE};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the size of the length in front of each frame
+const LENGTH_SIZE: usize = size_of::<u32>();
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// a stream the messages are framed on. the frames longer than the max size are refused
+/// before anything is allocated for them, and a cancelled read or write doesn't leave
+/// the stream in the middle of a frame
+#[derive(Debug)]
+pub struct Framed<S> {
+    stream: S,
+    max_size: u32,
+    /// the part of the frame being read, kept when the read is cancelled
+    reading: Vec<u8>,
+    /// the frames not fully written yet, written before the next one
+    writing: Vec<u8>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl<S> Framed<S> {
+    /// frame the messages on the stream, up to MAX_MESSAGE_SIZE
+    pub fn new(stream: S) -> Self {
+        Self::with_max_size(stream, MAX_MESSAGE_SIZE)
+    }
+
+    pub fn with_max_size(stream: S, max_size: u32) -> Self {
+        Self {
+            stream,
+            max_size,
+            reading: Vec::new(),
+            writing: Vec::new(),
+        }
+    }
+
+    /// give the stream back, the part of a frame read or not written yet is lost
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncRead + Unpin> Framed<S> {
+    /// read the next frame and deserialize it, along with the number of bytes it took.
+    /// this is cancel safe, what a cancelled call read is kept for the next one.
+    /// a frame that can't be deserialized is consumed so the next one can still be read,
+    /// but the stream can't be read anymore after a frame longer than the max size
+    pub async fn receive<T: DeserializeOwned>(&mut self) -> Result<(T, usize), TaskmasterError> {
+        let frame_size = loop {
+            let Some(length) = self.reading.first_chunk::<LENGTH_SIZE>() else {
+                self.read_until(LENGTH_SIZE).await?;
+                continue;
+            };
+            let length = u32::from_be_bytes(*length);
+            if length > self.max_size {
+                return Err(TaskmasterError::MessageTooLong);
+            }
+            let frame_size = LENGTH_SIZE + length as usize;
+            if self.reading.len() == frame_size {
+                break frame_size;
+            }
+            self.read_until(frame_size).await?;
+        };
+
+        let mut frame = std::mem::take(&mut self.reading);
+        let yaml = String::from_utf8(frame.split_off(LENGTH_SIZE))?;
+        Ok((serde_yaml::from_str(&yaml)?, frame_size))
+    }
+
+    /// read the frame further, never beyond the given size so the stream can be given back
+    /// between two frames. the end of the stream is an UnexpectedEof error
+    async fn read_until(&mut self, size: usize) -> Result<(), TaskmasterError> {
+        let missing = size - self.reading.len();
+        let read = (&mut self.stream)
+            .take(missing as u64)
+            .read_buf(&mut self.reading)
+            .await?;
+        if read == 0 {
+            let error = match self.reading.is_empty() {
+                true => "the stream is closed",
+                false => "the stream is closed in the middle of a frame",
+            };
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, error).into());
+        }
+        Ok(())
+    }
+}
+
+impl<S: AsyncWrite + Unpin> Framed<S> {
+    /// serialize the message and write it as a frame, returning the number of bytes it took.
+    /// this is cancel safe, what a cancelled call didn't write is written by the next one
+    pub async fn send<T: Serialize>(&mut self, message: &T) -> Result<usize, TaskmasterError> {
+        let yaml = serde_yaml::to_string(message)?;
+        if yaml.len() > self.max_size as usize {
+            return Err(TaskmasterError::MessageTooLong);
+        }
+        self.writing
+            .extend_from_slice(&(yaml.len() as u32).to_be_bytes());
+        self.writing.extend_from_slice(yaml.as_bytes());
+        self.flush().await?;
+        Ok(LENGTH_SIZE + yaml.len())
+    }
+
+    /// write what is left of the frames of the cancelled sends
+    pub async fn flush(&mut self) -> Result<(), TaskmasterError> {
+        while !self.writing.is_empty() {
+            let written = self.stream.write(&self.writing).await?;
+            if written == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+            }
+            self.writing.drain(..written);
+        }
+        self.stream.flush().await?;
+        Ok(())
+    }
+}
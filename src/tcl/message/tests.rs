@@ -0,0 +1,158 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+
+use super::{Framed, Identified, Request, Response};
+use crate::error::TaskmasterError;
+use std::time::Duration;
+use tokio::{
+    io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream},
+    time::timeout,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Helper                                   */
+/* -------------------------------------------------------------------------- */
+/// how long a call that can't complete runs before it is cancelled
+const DELAY: Duration = Duration::from_millis(50);
+
+/// a frame of the bytes, with a length that may lie
+fn frame(length: u32, body: &[u8]) -> Vec<u8> {
+    [&length.to_be_bytes(), body].concat()
+}
+
+fn request(id: u64) -> Identified<Request> {
+    Identified {
+        id,
+        message: Request::Start(format!("program_{id}")),
+    }
+}
+
+async fn receive_request(
+    framed: &mut Framed<DuplexStream>,
+) -> Result<Identified<Request>, TaskmasterError> {
+    framed.receive().await.map(|(request, _)| request)
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[tokio::test]
+async fn frames_cross_a_pipe_smaller_than_them_both_ways() {
+    let (client, daemon) = duplex(8);
+    let (mut client, mut daemon) = (Framed::new(client), Framed::new(daemon));
+
+    let reader = tokio::spawn(async move {
+        let (request, size) = daemon.receive::<Identified<Request>>().await.unwrap();
+        daemon
+            .send(&Identified {
+                id: request.id,
+                message: Response::Success("started".to_owned()),
+            })
+            .await
+            .unwrap();
+        (request, size)
+    });
+    let sent = client.send(&request(1)).await.unwrap();
+    let (response, _) = client.receive::<Identified<Response>>().await.unwrap();
+    let (request, received) = reader.await.unwrap();
+
+    assert_eq!(request.id, 1);
+    assert_eq!(sent, received);
+    assert_eq!(response.id, 1);
+    assert!(matches!(response.message, Response::Success(message) if message == "started"));
+}
+
+#[tokio::test]
+async fn stream_closed_between_or_inside_frames_is_a_disconnection() {
+    for truncated in [vec![], vec![0, 0], frame(10, b"Start")] {
+        let (mut peer, stream) = duplex(64);
+        peer.write_all(&truncated).await.unwrap();
+        drop(peer);
+
+        let error = receive_request(&mut Framed::new(stream)).await.unwrap_err();
+        assert!(error.client_disconnected(), "{error}");
+    }
+}
+
+#[tokio::test]
+async fn oversized_frame_is_refused_without_reading_it() {
+    let (mut peer, stream) = duplex(64);
+    let mut framed = Framed::with_max_size(stream, 16);
+    peer.write_all(&frame(17, b"")).await.unwrap();
+
+    assert!(matches!(
+        receive_request(&mut framed).await,
+        Err(TaskmasterError::MessageTooLong)
+    ));
+    assert!(matches!(
+        framed.send(&request(1)).await,
+        Err(TaskmasterError::MessageTooLong)
+    ));
+    // nothing is written by the refused send
+    drop(framed);
+    let mut written = Vec::new();
+    peer.read_to_end(&mut written).await.unwrap();
+    assert!(written.is_empty());
+}
+
+#[tokio::test]
+async fn garbage_frame_is_an_error_and_the_next_one_is_read() {
+    let (mut peer, stream) = duplex(256);
+    let mut framed = Framed::new(stream);
+    let mut valid = Vec::new();
+    Framed::new(&mut valid).send(&request(3)).await.unwrap();
+    for garbage in [
+        frame(2, &[0xff, 0xfe]),
+        frame(5, b"- : ]"),
+        frame(4, b"1234"),
+    ] {
+        peer.write_all(&garbage).await.unwrap();
+    }
+    peer.write_all(&valid).await.unwrap();
+
+    assert!(matches!(
+        receive_request(&mut framed).await,
+        Err(TaskmasterError::StringConversionError(_))
+    ));
+    for _ in 0..2 {
+        assert!(matches!(
+            receive_request(&mut framed).await,
+            Err(TaskmasterError::SerdeError(_))
+        ));
+    }
+    assert_eq!(receive_request(&mut framed).await.unwrap().id, 3);
+}
+
+#[tokio::test]
+async fn cancelled_receive_keeps_the_partial_frame() {
+    let (mut peer, stream) = duplex(256);
+    let mut framed = Framed::new(stream);
+    let mut valid = Vec::new();
+    Framed::new(&mut valid).send(&request(4)).await.unwrap();
+
+    let (first_part, rest) = valid.split_at(6);
+    peer.write_all(first_part).await.unwrap();
+    assert!(timeout(DELAY, receive_request(&mut framed)).await.is_err());
+    peer.write_all(rest).await.unwrap();
+
+    assert_eq!(receive_request(&mut framed).await.unwrap().id, 4);
+}
+
+#[tokio::test]
+async fn cancelled_send_is_finished_before_the_next_frame() {
+    let (peer, stream) = duplex(8);
+    let mut framed = Framed::new(stream);
+
+    // nobody reads, the frame doesn't fit in the pipe
+    assert!(timeout(DELAY, framed.send(&request(5))).await.is_err());
+    let reader = tokio::spawn(async move {
+        let mut peer = Framed::new(peer);
+        let first = receive_request(&mut peer).await.unwrap();
+        let second = receive_request(&mut peer).await.unwrap();
+        (first.id, second.id)
+    });
+    framed.send(&request(6)).await.unwrap();
+
+    assert_eq!(reader.await.unwrap(), (5, 6));
+}
@@ -0,0 +1,749 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+use std::{
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Module                                   */
+/* -------------------------------------------------------------------------- */
+/// the raw bindings, private so the pointers into the memory of the libc they hold never
+/// leave the safe wrappers below
+mod raw;
+
+#[cfg(target_os = "linux")]
+pub use raw::SIGPOLL;
+pub use raw::{
+    gid_t, mode_t, pid_t, siginfo_t, termios, uid_t, ECHO, ICANON, ISIG, WCONTINUED, WNOHANG,
+    WUNTRACED,
+};
+pub use raw::{SA_NOCLDSTOP, SA_NOCLDWAIT, SA_NODEFER, SA_ONSTACK, SA_RESETHAND, SA_RESTART};
+pub use raw::{
+    SIGABRT, SIGALRM, SIGBUS, SIGCHLD, SIGCONT, SIGFPE, SIGHUP, SIGILL, SIGINT, SIGKILL, SIGPIPE,
+    SIGPROF, SIGQUIT, SIGSEGV, SIGSTOP, SIGSYS, SIGTERM, SIGTRAP, SIGTSTP, SIGTTIN, SIGTTOU,
+    SIGURG, SIGUSR1, SIGUSR2, SIGVTALRM, SIGWINCH, SIGXCPU, SIGXFSZ,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// the space of a filesystem, in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct FsSpace {
+    pub total: u64,
+    /// what an unprivileged user can still use
+    pub available: u64,
+}
+
+/// how a child changed state, decoded from the status filled by waitpid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    /// the child exited with the code (WIFEXITED)
+    Exited(c_int),
+    /// the child was killed by the signal, dumping a core or not (WIFSIGNALED)
+    Signaled(c_int, bool),
+    /// the child was stopped by the signal, only reported with WUNTRACED (WIFSTOPPED)
+    Stopped(c_int),
+    /// the child was resumed by SIGCONT, only reported with WCONTINUED (WIFCONTINUED)
+    Continued,
+}
+
+/// what is done when a signal is received
+#[derive(Debug, Clone, Copy)]
+pub enum SigHandler {
+    /// the default action of the signal
+    Default,
+    Ignore,
+    /// call the function with the signal number, it must be async signal safe
+    Handler(extern "C" fn(c_int)),
+    /// call the function with the signal number, the siginfo_t describing the signal and
+    /// the context of the interrupted thread (SA_SIGINFO), it must be async signal safe
+    SigInfo(extern "C" fn(c_int, *mut siginfo_t, *mut c_void)),
+}
+
+/// a set of signals, e.g. the ones blocked while a handler runs
+#[derive(Clone, Copy)]
+pub struct SigSet(raw::sigset_t);
+
+/// a resource limited by getrlimit and setrlimit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// the cpu time, in seconds
+    Cpu,
+    /// the size of the files created, in bytes
+    FileSize,
+    /// the size of the data segment, in bytes
+    Data,
+    /// the size of the stack, in bytes
+    Stack,
+    /// the size of the core dumps, in bytes
+    Core,
+    /// the number of processes of the real user
+    NProc,
+    /// the number of open file descriptors, plus one
+    NoFile,
+    /// the memory locked in ram, in bytes
+    MemLock,
+    /// the size of the address space, in bytes
+    As,
+}
+
+/// the limits of a resource, None being unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rlimit {
+    /// the limit enforced, that the process can raise up to the hard one
+    pub soft: Option<u64>,
+    /// the ceiling of the soft limit, only a privileged process can raise it
+    pub hard: Option<u64>,
+}
+
+/// a time broken down in the local timezone, down to the minute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    /// the day of the week, sunday being 0
+    pub weekday: u32,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+/// a user of the user database
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    pub uid: uid_t,
+    pub gid: gid_t,
+}
+
+/// a group of the group database
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub name: String,
+    pub gid: gid_t,
+    /// the names of the users having it as a supplementary group
+    pub members: Vec<String>,
+}
+
+/// a pipe used to wake a thread up from anywhere, a signal handler included: the waiting
+/// side polls the read end and the notifying side writes a byte to the other one
+#[derive(Debug)]
+pub struct SelfPipe {
+    read: OwnedFd,
+    write: OwnedFd,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// the size of the buffer first given to the reentrant lookups, doubled while too small
+const LOOKUP_BUFFER_SIZE: usize = 1024;
+/// past this size the entry is considered invalid
+const LOOKUP_BUFFER_MAX_SIZE: usize = 1024 * 1024;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Static                                   */
+/* -------------------------------------------------------------------------- */
+/// getpwent iterates a cursor shared by the whole process and returns an entry overwritten
+/// by the next call, one iteration at a time must go through it
+static PASSWD_LOCK: Mutex<()> = Mutex::new(());
+
+/* -------------------------------------------------------------------------- */
+/*                            Struct Implementation                           */
+/* -------------------------------------------------------------------------- */
+impl WaitStatus {
+    /// decode the status the way the WIFEXITED, WIFSIGNALED, WIFSTOPPED and WIFCONTINUED
+    /// macros do
+    pub fn from_raw(status: c_int) -> Self {
+        let signal = status & 0x7f;
+        if status == raw::W_CONTINUED_STATUS {
+            WaitStatus::Continued
+        } else if signal == 0 {
+            WaitStatus::Exited((status >> 8) & 0xff)
+        } else if signal == 0x7f {
+            WaitStatus::Stopped((status >> 8) & 0xff)
+        } else {
+            WaitStatus::Signaled(signal, status & 0x80 != 0)
+        }
+    }
+}
+
+impl Resource {
+    fn as_raw(self) -> c_int {
+        match self {
+            Resource::Cpu => raw::RLIMIT_CPU,
+            Resource::FileSize => raw::RLIMIT_FSIZE,
+            Resource::Data => raw::RLIMIT_DATA,
+            Resource::Stack => raw::RLIMIT_STACK,
+            Resource::Core => raw::RLIMIT_CORE,
+            Resource::NProc => raw::RLIMIT_NPROC,
+            Resource::NoFile => raw::RLIMIT_NOFILE,
+            Resource::MemLock => raw::RLIMIT_MEMLOCK,
+            Resource::As => raw::RLIMIT_AS,
+        }
+    }
+}
+
+impl SelfPipe {
+    /// create the pipe, both ends are closed on exec and never block
+    pub fn new() -> io::Result<Self> {
+        let (read, write) = cloexec_pipe(true)?;
+        Ok(SelfPipe { read, write })
+    }
+
+    /// wake the waiting thread up, the notifications received while it wasn't waiting are
+    /// merged into one. it is async signal safe
+    pub fn notify(&self) {
+        // a full pipe already holds a notification
+        let _ = write(self.write.as_raw_fd(), &[1]);
+    }
+
+    /// the end to write to, for a signal handler that can only reach a raw file descriptor
+    pub fn notify_fd(&self) -> c_int {
+        self.write.as_raw_fd()
+    }
+
+    /// block until notified or until the timeout elapsed, returning whether it was notified.
+    /// the pending notifications are consumed
+    pub fn wait(&self, timeout: Duration) -> io::Result<bool> {
+        let mut fd = raw::pollfd {
+            fd: self.read.as_raw_fd(),
+            events: raw::POLLIN,
+            revents: 0,
+        };
+        let timeout = c_int::try_from(timeout.as_millis()).unwrap_or(c_int::MAX);
+        match unsafe { raw::poll(&mut fd, 1, timeout) } {
+            -1 => {
+                let error = io::Error::last_os_error();
+                match error.kind() {
+                    io::ErrorKind::Interrupted => Ok(false),
+                    _ => Err(error),
+                }
+            }
+            0 => Ok(false),
+            _ => {
+                let mut buffer = [0u8; 64];
+                while unsafe { raw::read(fd.fd, buffer.as_mut_ptr().cast(), buffer.len()) } > 0 {}
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl SigSet {
+    pub fn empty() -> Self {
+        let mut set = std::mem::MaybeUninit::uninit();
+        unsafe {
+            raw::sigemptyset(set.as_mut_ptr());
+            SigSet(set.assume_init())
+        }
+    }
+
+    /// add the signal to the set, failing if it isn't a valid signal
+    pub fn insert(mut self, signal: c_int) -> io::Result<Self> {
+        if unsafe { raw::sigaddset(&mut self.0, signal) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(self)
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Trait Implementation                            */
+/* -------------------------------------------------------------------------- */
+impl std::fmt::Display for WaitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaitStatus::Exited(code) => write!(f, "exited with code {code}"),
+            WaitStatus::Signaled(signal, false) => write!(f, "killed by signal {signal}"),
+            WaitStatus::Signaled(signal, true) => {
+                write!(f, "killed by signal {signal} (core dumped)")
+            }
+            WaitStatus::Stopped(signal) => write!(f, "stopped by signal {signal}"),
+            WaitStatus::Continued => write!(f, "continued"),
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+/// send the signal to the process
+pub fn kill(pid: pid_t, signal: c_int) -> io::Result<()> {
+    if unsafe { raw::kill(pid, signal) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// the name of the signal (e.g. SIGSEGV), None if it is not one of the signals above
+pub fn signal_name(signal: c_int) -> Option<&'static str> {
+    Some(match signal {
+        SIGABRT => "SIGABRT",
+        SIGALRM => "SIGALRM",
+        SIGBUS => "SIGBUS",
+        SIGCHLD => "SIGCHLD",
+        SIGCONT => "SIGCONT",
+        SIGFPE => "SIGFPE",
+        SIGHUP => "SIGHUP",
+        SIGILL => "SIGILL",
+        SIGINT => "SIGINT",
+        SIGKILL => "SIGKILL",
+        SIGPIPE => "SIGPIPE",
+        #[cfg(target_os = "linux")]
+        SIGPOLL => "SIGPOLL",
+        SIGPROF => "SIGPROF",
+        SIGQUIT => "SIGQUIT",
+        SIGSEGV => "SIGSEGV",
+        SIGSTOP => "SIGSTOP",
+        SIGSYS => "SIGSYS",
+        SIGTERM => "SIGTERM",
+        SIGTRAP => "SIGTRAP",
+        SIGTSTP => "SIGTSTP",
+        SIGTTIN => "SIGTTIN",
+        SIGTTOU => "SIGTTOU",
+        SIGURG => "SIGURG",
+        SIGUSR1 => "SIGUSR1",
+        SIGUSR2 => "SIGUSR2",
+        SIGVTALRM => "SIGVTALRM",
+        SIGWINCH => "SIGWINCH",
+        SIGXCPU => "SIGXCPU",
+        SIGXFSZ => "SIGXFSZ",
+        _ => return None,
+    })
+}
+
+/// the pid of the calling process
+pub fn getpid() -> pid_t {
+    unsafe { raw::getpid() }
+}
+
+/// the pid of the parent of the calling process, the one of the subreaper or init once the
+/// parent died
+pub fn getppid() -> pid_t {
+    unsafe { raw::getppid() }
+}
+
+/// create a new session led by the calling process, returning its id. it is async signal
+/// safe so it can be called between fork and exec
+pub fn setsid() -> io::Result<pid_t> {
+    match unsafe { raw::setsid() } {
+        -1 => Err(io::Error::last_os_error()),
+        sid => Ok(sid),
+    }
+}
+
+/// move the process into the process group (0 for the calling process, and for a pgid
+/// equal to the pid). it is async signal safe so it can be called between fork and exec
+pub fn setpgid(pid: pid_t, pgid: pid_t) -> io::Result<()> {
+    if unsafe { raw::setpgid(pid, pgid) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// make newfd a copy of oldfd, closing it first if it was open. it is async signal safe so
+/// it can be called between fork and exec
+pub fn dup2(oldfd: c_int, newfd: c_int) -> io::Result<()> {
+    if unsafe { raw::dup2(oldfd, newfd) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// the process group of the process (0 for the calling process)
+pub fn getpgid(pid: pid_t) -> io::Result<pid_t> {
+    match unsafe { raw::getpgid(pid) } {
+        -1 => Err(io::Error::last_os_error()),
+        pgid => Ok(pgid),
+    }
+}
+
+/// call the reentrant lookup with a growing buffer until the entry fits in it. the entry
+/// must be copied by the call, as its strings point into the buffer
+fn lookup<F>(mut call: F) -> io::Result<()>
+where
+    F: FnMut(&mut [c_char]) -> c_int,
+{
+    let mut size = LOOKUP_BUFFER_SIZE;
+    loop {
+        let mut buffer = vec![0; size];
+        match call(&mut buffer) {
+            0 => return Ok(()),
+            raw::ERANGE if size < LOOKUP_BUFFER_MAX_SIZE => size *= 2,
+            error => return Err(io::Error::from_raw_os_error(error)),
+        }
+    }
+}
+
+/// copy the group entry filled by getgrnam_r
+unsafe fn owned_group(entry: &raw::group) -> Group {
+    let mut members = Vec::new();
+    let mut member = entry.gr_mem;
+    while !member.is_null() && !(*member).is_null() {
+        members.push(CStr::from_ptr(*member).to_string_lossy().into_owned());
+        member = member.add(1);
+    }
+    Group {
+        name: CStr::from_ptr(entry.gr_name).to_string_lossy().into_owned(),
+        gid: entry.gr_gid,
+        members,
+    }
+}
+
+/// make the calling process adopt its orphaned descendants (e.g. the daemons the children
+/// fork) instead of init, so it reaps them and gets their exit
+#[cfg(target_os = "linux")]
+pub fn set_child_subreaper() -> io::Result<()> {
+    if unsafe { raw::prctl(raw::PR_SET_CHILD_SUBREAPER, 1 as std::ffi::c_ulong) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// set the name of the calling thread, shown by ps and top (the one of the main thread
+/// being the name of the process). it is truncated to 15 bytes
+#[cfg(target_os = "linux")]
+pub fn set_thread_name(name: &str) -> io::Result<()> {
+    let name: Vec<u8> = name.bytes().filter(|&byte| byte != 0).take(15).collect();
+    let name = CString::new(name).expect("the nul bytes were removed");
+    if unsafe { raw::prctl(raw::PR_SET_NAME, name.as_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// set the file mode creation mask of the calling process, returning the previous one.
+/// it is async signal safe so it can be called between fork and exec
+pub fn umask(mask: mode_t) -> mode_t {
+    unsafe { raw::umask(mask) }
+}
+
+/// the effective user id of the calling process
+pub fn geteuid() -> uid_t {
+    unsafe { raw::geteuid() }
+}
+
+/// write the bytes to the file descriptor, returning how many were written. it doesn't
+/// allocate so it can be called between fork and exec
+pub fn write(fd: c_int, bytes: &[u8]) -> io::Result<usize> {
+    match unsafe { raw::write(fd, bytes.as_ptr().cast(), bytes.len()) } {
+        -1 => Err(io::Error::last_os_error()),
+        written => Ok(written as usize),
+    }
+}
+
+/// look the user up by name in the user database, it is thread safe unlike getpwent
+pub fn getpwnam(name: &str) -> io::Result<Option<User>> {
+    let Ok(name) = CString::new(name) else {
+        return Ok(None);
+    };
+    let mut user = None;
+    lookup(|buffer| unsafe {
+        let mut entry = std::mem::MaybeUninit::<raw::passwd>::uninit();
+        let mut result = std::ptr::null_mut();
+        let error = raw::getpwnam_r(
+            name.as_ptr(),
+            entry.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        );
+        if let Some(entry) = result.as_ref() {
+            user = Some(User {
+                name: CStr::from_ptr(entry.pw_name).to_string_lossy().into_owned(),
+                uid: entry.pw_uid,
+                gid: entry.pw_gid,
+            });
+        }
+        error
+    })?;
+    Ok(user)
+}
+
+/// look the group up by name in the group database, it is thread safe unlike getgrent
+pub fn getgrnam(name: &str) -> io::Result<Option<Group>> {
+    let Ok(name) = CString::new(name) else {
+        return Ok(None);
+    };
+    let mut group = None;
+    lookup(|buffer| unsafe {
+        let mut entry = std::mem::MaybeUninit::<raw::group>::uninit();
+        let mut result = std::ptr::null_mut();
+        let error = raw::getgrnam_r(
+            name.as_ptr(),
+            entry.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        );
+        if let Some(entry) = result.as_ref() {
+            group = Some(owned_group(entry));
+        }
+        error
+    })?;
+    Ok(group)
+}
+
+/// the users of the user database whose name is valid utf-8, getpwnam should be preferred
+/// to look a single user up
+pub fn users() -> Vec<User> {
+    let _guard = PASSWD_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut users = Vec::new();
+    unsafe {
+        raw::setpwent();
+        // the entry is overwritten by the next call, it is copied right away
+        while let Some(user) = raw::getpwent().as_ref() {
+            if let Ok(name) = CStr::from_ptr(user.pw_name).to_str() {
+                users.push(User {
+                    name: name.to_owned(),
+                    uid: user.pw_uid,
+                    gid: user.pw_gid,
+                });
+            }
+        }
+        raw::endpwent();
+    }
+    users
+}
+
+/// get the attributes of the terminal open on the file descriptor
+pub fn tcgetattr(fd: c_int) -> io::Result<termios> {
+    let mut termios = termios::default();
+    if unsafe { raw::tcgetattr(fd, &mut termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(termios)
+}
+
+/// break the time down in the local timezone (TZ, /etc/localtime otherwise)
+pub fn localtime(time: std::time::SystemTime) -> io::Result<LocalTime> {
+    let seconds = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs()) as raw::time_t;
+    let mut tm = std::mem::MaybeUninit::<raw::tm>::uninit();
+    if unsafe { raw::localtime_r(&seconds, tm.as_mut_ptr()) }.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let tm = unsafe { tm.assume_init() };
+    Ok(LocalTime {
+        weekday: tm.tm_wday as u32,
+        hour: tm.tm_hour as u32,
+        minute: tm.tm_min as u32,
+    })
+}
+
+/// set the attributes of the terminal open on the file descriptor, right away
+pub fn tcsetattr(fd: c_int, termios: &termios) -> io::Result<()> {
+    if unsafe { raw::tcsetattr(fd, raw::TCSANOW, termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// set what is done when the signal is received, the signals of the mask being blocked
+/// while the handler runs. the flags are the SA_ constants (e.g. SA_RESTART | SA_NOCLDSTOP),
+/// SA_SIGINFO is added for a SigInfo handler
+pub fn sigaction(
+    signal: c_int,
+    handler: SigHandler,
+    flags: c_int,
+    mask: &SigSet,
+) -> io::Result<()> {
+    let (handler, flags) = match handler {
+        SigHandler::Default => (raw::SIG_DFL, flags),
+        SigHandler::Ignore => (raw::SIG_IGN, flags),
+        SigHandler::Handler(handler) => (handler as raw::sighandler_t, flags),
+        SigHandler::SigInfo(handler) => (handler as raw::sighandler_t, flags | raw::SA_SIGINFO),
+    };
+    // the other fields depend on the platform, they are left zeroed
+    let mut action: raw::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = handler;
+    action.sa_mask = mask.0;
+    action.sa_flags = flags;
+    if unsafe { raw::sigaction(signal, &action, std::ptr::null_mut()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// the time elapsed on the monotonic clock since an unspecified point (usually the boot),
+/// only the difference between two readings is meaningful
+pub fn clock_monotonic() -> Duration {
+    let mut time = raw::timespec::default();
+    // it can only fail for an invalid clock or pointer
+    unsafe { raw::clock_gettime(raw::CLOCK_MONOTONIC, &mut time) };
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+/// the limits of the resource for the calling process
+pub fn getrlimit(resource: Resource) -> io::Result<Rlimit> {
+    let mut limit = raw::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { raw::getrlimit(resource.as_raw(), &mut limit) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let finite = |value| (value != raw::RLIM_INFINITY).then_some(value);
+    Ok(Rlimit {
+        soft: finite(limit.rlim_cur),
+        hard: finite(limit.rlim_max),
+    })
+}
+
+/// set the limits of the resource for the calling process. it is async signal safe so it
+/// can be called between fork and exec
+pub fn setrlimit(resource: Resource, limit: Rlimit) -> io::Result<()> {
+    let limit = raw::rlimit {
+        rlim_cur: limit.soft.unwrap_or(raw::RLIM_INFINITY),
+        rlim_max: limit.hard.unwrap_or(raw::RLIM_INFINITY),
+    };
+    if unsafe { raw::setrlimit(resource.as_raw(), &limit) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// the size of the terminal as (rows, columns), failing if the fd isn't a terminal
+pub fn get_terminal_size(fd: c_int) -> io::Result<(u16, u16)> {
+    let mut size = raw::winsize::default();
+    if unsafe { raw::ioctl(fd, raw::TIOCGWINSZ, &mut size as *mut raw::winsize) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((size.ws_row, size.ws_col))
+}
+
+/// wait for the given child (-1 for any child), returning its pid and how it changed state,
+/// None if WNOHANG was given and no child changed state, or if there is no child at all
+pub fn waitpid(pid: pid_t, options: c_int) -> io::Result<Option<(pid_t, WaitStatus)>> {
+    let mut status: c_int = 0;
+    match unsafe { raw::waitpid(pid, &mut status, options) } {
+        0 => Ok(None),
+        -1 => no_child_is_none(io::Error::last_os_error()),
+        pid => Ok(Some((pid, WaitStatus::from_raw(status)))),
+    }
+}
+
+/// return the pid of a child that exited without reaping it, so whoever owns the child
+/// can still collect its exit status. None if no child exited
+pub fn peek_exited_child() -> io::Result<Option<pid_t>> {
+    let mut info: raw::siginfo_t = unsafe { std::mem::zeroed() };
+    let options = raw::WEXITED | raw::WNOHANG | raw::WNOWAIT;
+    if unsafe { raw::waitid(raw::P_ALL, 0, &mut info, options) } == -1 {
+        return no_child_is_none(io::Error::last_os_error());
+    }
+    // with WNOHANG the pid is left to 0 when no child exited
+    match unsafe { info.fields.child.si_pid } {
+        0 => Ok(None),
+        pid => Ok(Some(pid)),
+    }
+}
+
+/// tell if the process of the given pid is a child of the calling process, whether it
+/// exited or not, without reaping it
+pub fn is_child(pid: pid_t) -> io::Result<bool> {
+    let mut info: raw::siginfo_t = unsafe { std::mem::zeroed() };
+    let options = raw::WEXITED | raw::WNOHANG | raw::WNOWAIT;
+    if unsafe { raw::waitid(raw::P_PID, pid as u32, &mut info, options) } == -1 {
+        return no_child_is_none(io::Error::last_os_error()).map(|_: Option<()>| false);
+    }
+    Ok(true)
+}
+
+/// return the space of the filesystem the path is on
+pub fn statvfs(path: &Path) -> io::Result<FsSpace> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stats: raw::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { raw::statvfs(path.as_ptr(), &mut stats) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let fragment_size = stats.f_frsize as u64;
+    Ok(FsSpace {
+        total: stats.f_blocks as u64 * fragment_size,
+        available: stats.f_bavail as u64 * fragment_size,
+    })
+}
+
+/// tell if the calling process may write to the file, or create files in the directory, at
+/// the path
+pub fn is_writable(path: &Path) -> bool {
+    CString::new(path.as_os_str().as_bytes())
+        .is_ok_and(|path| unsafe { raw::access(path.as_ptr(), raw::W_OK) } == 0)
+}
+
+/// make the operations on the file descriptor fail with WouldBlock instead of blocking
+pub fn set_nonblocking(fd: c_int) -> io::Result<()> {
+    let flags = unsafe { raw::fcntl(fd, raw::F_GETFL) };
+    if flags == -1 || unsafe { raw::fcntl(fd, raw::F_SETFL, flags | raw::O_NONBLOCK) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// create a pipe closed on exec, along with the flags asked for, at once where pipe2 exists
+/// so a child spawned by another thread meanwhile doesn't inherit it
+fn cloexec_pipe(nonblocking: bool) -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    #[cfg(target_os = "linux")]
+    {
+        let flags = match nonblocking {
+            true => raw::O_CLOEXEC | raw::O_NONBLOCK,
+            false => raw::O_CLOEXEC,
+        };
+        if unsafe { raw::pipe2(fds.as_mut_ptr(), flags) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    #[cfg(target_os = "macos")]
+    if unsafe { raw::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    #[cfg(target_os = "macos")]
+    for fd in [read.as_raw_fd(), write.as_raw_fd()] {
+        set_cloexec(fd)?;
+        if nonblocking {
+            set_nonblocking(fd)?;
+        }
+    }
+    Ok((read, write))
+}
+
+/// create a pipe, returning its read and write ends. both are closed on exec
+pub fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    cloexec_pipe(false)
+}
+
+/// close the file descriptor when the process execs another program
+pub fn set_cloexec(fd: c_int) -> io::Result<()> {
+    if unsafe { raw::fcntl(fd, raw::F_SETFD, raw::FD_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// keep the file descriptor open when the process execs another program, e.g. to hand it over
+pub fn set_inheritable(fd: c_int) -> io::Result<()> {
+    if unsafe { raw::fcntl(fd, raw::F_SETFD, 0) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// not having any child is not an error for the callers
+fn no_child_is_none<T>(error: io::Error) -> io::Result<Option<T>> {
+    match error.raw_os_error() {
+        Some(raw::ECHILD) => Ok(None),
+        _ => Err(error),
+    }
+}
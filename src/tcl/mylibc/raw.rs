@@ -0,0 +1,490 @@
+/* -------------------------------------------------------------------------- */
+/*                                   Import                                   */
+/* -------------------------------------------------------------------------- */
+#![allow(non_camel_case_types)]
+
+use std::ffi::{c_char, c_int, c_long, c_short, c_uint, c_ulong, c_void};
+
+/* -------------------------------------------------------------------------- */
+/*                                    Type                                    */
+/* -------------------------------------------------------------------------- */
+pub type pid_t = c_int;
+pub type uid_t = u32;
+pub type gid_t = u32;
+pub type mode_t = u32;
+pub type idtype_t = c_int;
+pub type clockid_t = c_int;
+#[cfg(target_os = "linux")]
+pub type nfds_t = c_ulong;
+#[cfg(target_os = "macos")]
+pub type nfds_t = c_uint;
+pub type time_t = i64;
+#[cfg(target_os = "linux")]
+pub type tcflag_t = c_uint;
+#[cfg(target_os = "macos")]
+pub type tcflag_t = c_ulong;
+pub type cc_t = u8;
+#[cfg(target_os = "linux")]
+pub type speed_t = c_uint;
+#[cfg(target_os = "macos")]
+pub type speed_t = c_ulong;
+#[cfg(target_os = "linux")]
+pub type fsblkcnt_t = u64;
+#[cfg(target_os = "macos")]
+pub type fsblkcnt_t = c_uint;
+pub type rlim_t = u64;
+/// a signal handler, or SIG_DFL or SIG_IGN
+pub type sighandler_t = usize;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Constant                                  */
+/* -------------------------------------------------------------------------- */
+/// wait for any child
+pub const P_ALL: idtype_t = 0;
+/// wait for the child of the given pid
+pub const P_PID: idtype_t = 1;
+
+/// a clock that can't be set and isn't affected by the changes of the system time
+#[cfg(target_os = "linux")]
+pub const CLOCK_MONOTONIC: clockid_t = 1;
+#[cfg(target_os = "macos")]
+pub const CLOCK_MONOTONIC: clockid_t = 6;
+
+/// return immediately if no child changed state
+pub const WNOHANG: c_int = 1;
+/// also report the children that were stopped
+pub const WUNTRACED: c_int = 2;
+/// also report the children that were resumed by SIGCONT
+#[cfg(target_os = "linux")]
+pub const WCONTINUED: c_int = 8;
+#[cfg(target_os = "macos")]
+pub const WCONTINUED: c_int = 0x10;
+/// wait for the children that exited
+pub const WEXITED: c_int = 4;
+/// leave the child in a waitable state, so it can be waited for again
+#[cfg(target_os = "linux")]
+pub const WNOWAIT: c_int = 0x0100_0000;
+#[cfg(target_os = "macos")]
+pub const WNOWAIT: c_int = 0x20;
+/// the status of a child resumed by SIGCONT
+#[cfg(target_os = "linux")]
+pub const W_CONTINUED_STATUS: c_int = 0xffff;
+#[cfg(target_os = "macos")]
+pub const W_CONTINUED_STATUS: c_int = 0x137f;
+
+/// the resources limited by getrlimit and setrlimit
+pub const RLIMIT_CPU: c_int = 0;
+pub const RLIMIT_FSIZE: c_int = 1;
+pub const RLIMIT_DATA: c_int = 2;
+pub const RLIMIT_STACK: c_int = 3;
+pub const RLIMIT_CORE: c_int = 4;
+#[cfg(target_os = "linux")]
+pub const RLIMIT_NPROC: c_int = 6;
+#[cfg(target_os = "linux")]
+pub const RLIMIT_NOFILE: c_int = 7;
+#[cfg(target_os = "linux")]
+pub const RLIMIT_MEMLOCK: c_int = 8;
+#[cfg(target_os = "linux")]
+pub const RLIMIT_AS: c_int = 9;
+#[cfg(target_os = "macos")]
+pub const RLIMIT_AS: c_int = 5;
+#[cfg(target_os = "macos")]
+pub const RLIMIT_MEMLOCK: c_int = 6;
+#[cfg(target_os = "macos")]
+pub const RLIMIT_NPROC: c_int = 7;
+#[cfg(target_os = "macos")]
+pub const RLIMIT_NOFILE: c_int = 8;
+/// no limit on the resource
+#[cfg(target_os = "linux")]
+pub const RLIM_INFINITY: rlim_t = !0;
+#[cfg(target_os = "macos")]
+pub const RLIM_INFINITY: rlim_t = (1 << 63) - 1;
+
+/// get the size of the terminal with ioctl
+#[cfg(target_os = "linux")]
+pub const TIOCGWINSZ: c_ulong = 0x5413;
+#[cfg(target_os = "macos")]
+pub const TIOCGWINSZ: c_ulong = 0x4008_7468;
+
+/// the name of the calling thread, at most 15 bytes
+#[cfg(target_os = "linux")]
+pub const PR_SET_NAME: c_int = 15;
+/// the orphaned descendants of the calling process are reparented to it instead of init
+#[cfg(target_os = "linux")]
+pub const PR_SET_CHILD_SUBREAPER: c_int = 36;
+
+/// the buffer given to a reentrant function is too small
+pub const ERANGE: c_int = 34;
+
+/// there is data to read on the file descriptor
+pub const POLLIN: c_short = 1;
+
+/// the calling process has no child to wait for
+pub const ECHILD: c_int = 10;
+
+/// test for the permission to write
+pub const W_OK: c_int = 2;
+
+/// set the file descriptor flags
+pub const F_SETFD: c_int = 2;
+/// close the file descriptor on exec
+pub const FD_CLOEXEC: c_int = 1;
+/// get the file status flags of a file descriptor
+pub const F_GETFL: c_int = 3;
+/// set the file status flags of a file descriptor
+pub const F_SETFL: c_int = 4;
+/// the operations on the file descriptor fail instead of blocking
+#[cfg(target_os = "linux")]
+pub const O_NONBLOCK: c_int = 0o4000;
+#[cfg(target_os = "macos")]
+pub const O_NONBLOCK: c_int = 4;
+/// the file descriptor is created closed on exec
+#[cfg(target_os = "linux")]
+pub const O_CLOEXEC: c_int = 0o2000000;
+
+/// the default action of the signal
+pub const SIG_DFL: sighandler_t = 0;
+/// ignore the signal
+pub const SIG_IGN: sighandler_t = 1;
+
+/// don't send SIGCHLD when a child is stopped or resumed
+#[cfg(target_os = "linux")]
+pub const SA_NOCLDSTOP: c_int = 1;
+#[cfg(target_os = "macos")]
+pub const SA_NOCLDSTOP: c_int = 8;
+/// don't turn the exited children into zombies
+#[cfg(target_os = "linux")]
+pub const SA_NOCLDWAIT: c_int = 2;
+#[cfg(target_os = "macos")]
+pub const SA_NOCLDWAIT: c_int = 0x20;
+/// the handler takes the siginfo_t describing the signal
+#[cfg(target_os = "linux")]
+pub const SA_SIGINFO: c_int = 4;
+#[cfg(target_os = "macos")]
+pub const SA_SIGINFO: c_int = 0x40;
+/// run the handler on the alternate signal stack
+#[cfg(target_os = "linux")]
+pub const SA_ONSTACK: c_int = 0x0800_0000;
+#[cfg(target_os = "macos")]
+pub const SA_ONSTACK: c_int = 1;
+/// restart the system calls interrupted by the signal instead of failing with EINTR
+#[cfg(target_os = "linux")]
+pub const SA_RESTART: c_int = 0x1000_0000;
+#[cfg(target_os = "macos")]
+pub const SA_RESTART: c_int = 2;
+/// don't block the signal while its handler runs
+#[cfg(target_os = "linux")]
+pub const SA_NODEFER: c_int = 0x4000_0000;
+#[cfg(target_os = "macos")]
+pub const SA_NODEFER: c_int = 0x10;
+/// restore the default action once the handler was called
+#[cfg(target_os = "linux")]
+pub const SA_RESETHAND: c_int = 0x8000_0000_u32 as c_int;
+#[cfg(target_os = "macos")]
+pub const SA_RESETHAND: c_int = 4;
+
+/// generate the signals of the special characters (e.g. SIGINT for Ctrl+C)
+#[cfg(target_os = "linux")]
+pub const ISIG: tcflag_t = 0o1;
+#[cfg(target_os = "macos")]
+pub const ISIG: tcflag_t = 0x80;
+/// read the input line by line
+#[cfg(target_os = "linux")]
+pub const ICANON: tcflag_t = 0o2;
+#[cfg(target_os = "macos")]
+pub const ICANON: tcflag_t = 0x100;
+/// echo the input characters
+#[cfg(target_os = "linux")]
+pub const ECHO: tcflag_t = 0o10;
+#[cfg(target_os = "macos")]
+pub const ECHO: tcflag_t = 0x8;
+/// apply the terminal attributes right away
+pub const TCSANOW: c_int = 0;
+
+/// the signals numbered alike on linux and macos
+pub const SIGHUP: c_int = 1;
+pub const SIGINT: c_int = 2;
+pub const SIGQUIT: c_int = 3;
+pub const SIGILL: c_int = 4;
+pub const SIGTRAP: c_int = 5;
+pub const SIGABRT: c_int = 6;
+pub const SIGFPE: c_int = 8;
+pub const SIGKILL: c_int = 9;
+pub const SIGSEGV: c_int = 11;
+pub const SIGPIPE: c_int = 13;
+pub const SIGALRM: c_int = 14;
+pub const SIGTERM: c_int = 15;
+pub const SIGTTIN: c_int = 21;
+pub const SIGTTOU: c_int = 22;
+pub const SIGXCPU: c_int = 24;
+pub const SIGXFSZ: c_int = 25;
+pub const SIGVTALRM: c_int = 26;
+pub const SIGPROF: c_int = 27;
+pub const SIGWINCH: c_int = 28;
+
+#[cfg(target_os = "linux")]
+pub const SIGBUS: c_int = 7;
+#[cfg(target_os = "linux")]
+pub const SIGUSR1: c_int = 10;
+#[cfg(target_os = "linux")]
+pub const SIGUSR2: c_int = 12;
+#[cfg(target_os = "linux")]
+pub const SIGCHLD: c_int = 17;
+#[cfg(target_os = "linux")]
+pub const SIGCONT: c_int = 18;
+#[cfg(target_os = "linux")]
+pub const SIGSTOP: c_int = 19;
+#[cfg(target_os = "linux")]
+pub const SIGTSTP: c_int = 20;
+#[cfg(target_os = "linux")]
+pub const SIGURG: c_int = 23;
+#[cfg(target_os = "linux")]
+pub const SIGPOLL: c_int = 29;
+#[cfg(target_os = "linux")]
+pub const SIGSYS: c_int = 31;
+
+#[cfg(target_os = "macos")]
+pub const SIGBUS: c_int = 10;
+#[cfg(target_os = "macos")]
+pub const SIGSYS: c_int = 12;
+#[cfg(target_os = "macos")]
+pub const SIGURG: c_int = 16;
+#[cfg(target_os = "macos")]
+pub const SIGSTOP: c_int = 17;
+#[cfg(target_os = "macos")]
+pub const SIGTSTP: c_int = 18;
+#[cfg(target_os = "macos")]
+pub const SIGCONT: c_int = 19;
+#[cfg(target_os = "macos")]
+pub const SIGCHLD: c_int = 20;
+#[cfg(target_os = "macos")]
+pub const SIGUSR1: c_int = 30;
+#[cfg(target_os = "macos")]
+pub const SIGUSR2: c_int = 31;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Struct                                   */
+/* -------------------------------------------------------------------------- */
+/// the information about a child filled by waitid, only the SIGCHLD fields are named
+#[repr(C)]
+pub struct siginfo_t {
+    pub si_signo: c_int,
+    pub si_errno: c_int,
+    pub si_code: c_int,
+    pub fields: siginfo_fields,
+}
+
+/// the union following the header of siginfo_t, aligned like a pointer
+#[cfg(target_os = "linux")]
+#[repr(C)]
+pub union siginfo_fields {
+    pub child: siginfo_child,
+    _pad: [c_int; 29],
+    _align: usize,
+}
+
+/// the fields following the header of siginfo_t, the child ones coming first
+#[cfg(target_os = "macos")]
+#[repr(C)]
+pub union siginfo_fields {
+    pub child: siginfo_child,
+    _pad: [c_int; 23],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct siginfo_child {
+    pub si_pid: pid_t,
+    pub si_uid: uid_t,
+    pub si_status: c_int,
+}
+
+/// the statistics of a filesystem filled by statvfs
+#[repr(C)]
+pub struct statvfs {
+    pub f_bsize: c_ulong,
+    pub f_frsize: c_ulong,
+    pub f_blocks: fsblkcnt_t,
+    pub f_bfree: fsblkcnt_t,
+    pub f_bavail: fsblkcnt_t,
+    pub f_files: fsblkcnt_t,
+    pub f_ffree: fsblkcnt_t,
+    pub f_favail: fsblkcnt_t,
+    pub f_fsid: c_ulong,
+    pub f_flag: c_ulong,
+    pub f_namemax: c_ulong,
+    #[cfg(target_os = "linux")]
+    __f_spare: [c_int; 6],
+}
+
+/// a set of signals
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct sigset_t {
+    #[cfg(target_os = "linux")]
+    __val: [c_ulong; 16],
+    #[cfg(target_os = "macos")]
+    __val: u32,
+}
+
+/// the action taken when a signal is received
+#[repr(C)]
+pub struct sigaction {
+    pub sa_sigaction: sighandler_t,
+    pub sa_mask: sigset_t,
+    pub sa_flags: c_int,
+    #[cfg(target_os = "linux")]
+    pub sa_restorer: Option<extern "C" fn()>,
+}
+
+/// a time in seconds and nanoseconds
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct timespec {
+    pub tv_sec: time_t,
+    pub tv_nsec: c_long,
+}
+
+/// a date and a time of the day broken down, in the local timezone for localtime_r
+#[repr(C)]
+pub struct tm {
+    pub tm_sec: c_int,
+    pub tm_min: c_int,
+    pub tm_hour: c_int,
+    pub tm_mday: c_int,
+    pub tm_mon: c_int,
+    pub tm_year: c_int,
+    /// the day of the week, sunday being 0
+    pub tm_wday: c_int,
+    pub tm_yday: c_int,
+    pub tm_isdst: c_int,
+    pub tm_gmtoff: c_long,
+    pub tm_zone: *const c_char,
+}
+
+/// a file descriptor waited on by poll
+#[repr(C)]
+pub struct pollfd {
+    pub fd: c_int,
+    pub events: c_short,
+    pub revents: c_short,
+}
+
+/// the soft and hard limits of a resource
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct rlimit {
+    pub rlim_cur: rlim_t,
+    pub rlim_max: rlim_t,
+}
+
+/// an entry of the user database returned by getpwent
+#[repr(C)]
+pub struct passwd {
+    pub pw_name: *mut c_char,
+    pub pw_passwd: *mut c_char,
+    pub pw_uid: uid_t,
+    pub pw_gid: gid_t,
+    #[cfg(target_os = "macos")]
+    pub pw_change: i64,
+    #[cfg(target_os = "macos")]
+    pub pw_class: *mut c_char,
+    pub pw_gecos: *mut c_char,
+    pub pw_dir: *mut c_char,
+    pub pw_shell: *mut c_char,
+    #[cfg(target_os = "macos")]
+    pub pw_expire: i64,
+}
+
+/// an entry of the group database
+#[repr(C)]
+pub struct group {
+    pub gr_name: *mut c_char,
+    pub gr_passwd: *mut c_char,
+    pub gr_gid: gid_t,
+    pub gr_mem: *mut *mut c_char,
+}
+
+/// the size of a terminal
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct winsize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
+}
+
+/// the attributes of a terminal
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct termios {
+    pub c_iflag: tcflag_t,
+    pub c_oflag: tcflag_t,
+    pub c_cflag: tcflag_t,
+    pub c_lflag: tcflag_t,
+    #[cfg(target_os = "linux")]
+    pub c_line: cc_t,
+    #[cfg(target_os = "linux")]
+    pub c_cc: [cc_t; 32],
+    #[cfg(target_os = "macos")]
+    pub c_cc: [cc_t; 20],
+    pub c_ispeed: speed_t,
+    pub c_ospeed: speed_t,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Function                                  */
+/* -------------------------------------------------------------------------- */
+extern "C" {
+    pub fn kill(pid: pid_t, sig: c_int) -> c_int;
+    pub fn sigaction(signum: c_int, act: *const sigaction, oldact: *mut sigaction) -> c_int;
+    pub fn sigemptyset(set: *mut sigset_t) -> c_int;
+    pub fn sigaddset(set: *mut sigset_t, signum: c_int) -> c_int;
+    pub fn getpid() -> pid_t;
+    pub fn getppid() -> pid_t;
+    pub fn setsid() -> pid_t;
+    pub fn setpgid(pid: pid_t, pgid: pid_t) -> c_int;
+    pub fn getpgid(pid: pid_t) -> pid_t;
+    pub fn umask(mask: mode_t) -> mode_t;
+    #[cfg(target_os = "linux")]
+    pub fn prctl(option: c_int, ...) -> c_int;
+    pub fn clock_gettime(clockid: clockid_t, tp: *mut timespec) -> c_int;
+    pub fn localtime_r(timep: *const time_t, result: *mut tm) -> *mut tm;
+    pub fn getrlimit(resource: c_int, rlim: *mut rlimit) -> c_int;
+    pub fn setrlimit(resource: c_int, rlim: *const rlimit) -> c_int;
+    pub fn geteuid() -> uid_t;
+    pub fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+    pub fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+    #[cfg(target_os = "macos")]
+    pub fn pipe(fds: *mut c_int) -> c_int;
+    #[cfg(target_os = "linux")]
+    pub fn pipe2(fds: *mut c_int, flags: c_int) -> c_int;
+    pub fn dup2(oldfd: c_int, newfd: c_int) -> c_int;
+    pub fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: c_int) -> c_int;
+    pub fn setpwent();
+    pub fn getpwent() -> *mut passwd;
+    pub fn endpwent();
+    pub fn getpwnam_r(
+        name: *const c_char,
+        pwd: *mut passwd,
+        buf: *mut c_char,
+        buflen: usize,
+        result: *mut *mut passwd,
+    ) -> c_int;
+    pub fn getgrnam_r(
+        name: *const c_char,
+        grp: *mut group,
+        buf: *mut c_char,
+        buflen: usize,
+        result: *mut *mut group,
+    ) -> c_int;
+    pub fn tcgetattr(fd: c_int, termios: *mut termios) -> c_int;
+    pub fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    pub fn tcsetattr(fd: c_int, optional_actions: c_int, termios: *const termios) -> c_int;
+    pub fn waitpid(pid: pid_t, status: *mut c_int, options: c_int) -> pid_t;
+    pub fn waitid(idtype: idtype_t, id: u32, infop: *mut siginfo_t, options: c_int) -> c_int;
+    pub fn statvfs(path: *const c_char, buf: *mut statvfs) -> c_int;
+    pub fn access(path: *const c_char, mode: c_int) -> c_int;
+    pub fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+}